@@ -0,0 +1,424 @@
+//! Hash time locked contract (HTLC) atomic swaps.
+//!
+//! An atomic swap locks siacoins in an output whose address is that of `SpendPolicy::atomic_swap`.
+//! The output can be redeemed by the success party once they reveal the secret or refunded to the
+//! refund party once the refund lock has expired. See the comment at the top of `types/spend_policy.rs`
+//! for the structure of the policy.
+//!
+//! `SwapParams` holds the terms agreed by both parties and builds each transaction of the swap.
+//! `AtomicSwap` pairs the params with the current `SwapState` so a swap engine can persist a swap
+//! and resume it after a restart. The lifecycle is:
+//!
+//! ```text
+//! Created --funded--> Funded --spent--> Redeemed
+//!                            \--spent--> Refunded
+//! ```
+//!
+//! `RefundWatcher` refunds HTLCs we funded once their refund lock expires, so funds are not lost
+//! if the counterparty abandons a swap.
+use crate::types::{Address, Currency, Hash256, Keypair, Preimage, PublicKey, RefundLock, SiacoinElement,
+                   SiacoinOutput, SiacoinOutputId, SpendPolicy, TransactionId, V2Transaction, V2TransactionBuilder,
+                   V2TransactionBuilderError};
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+mod refund_watcher;
+pub use refund_watcher::{RefundWatcher, RefundWatcherError, RefundWatcherEvent};
+
+#[derive(Debug, Error)]
+pub enum AtomicSwapError {
+    #[error("SwapParams::find_htlc_output: transaction {txid} has no output paying to HTLC address {address}")]
+    HtlcOutputNotFound { txid: TransactionId, address: Address },
+    #[error("SwapParams::find_htlc_output: transaction {txid} pays {found} to the HTLC address, expected {expected}")]
+    HtlcAmountMismatch {
+        txid: TransactionId,
+        expected: Currency,
+        found: Currency,
+    },
+    #[error("AtomicSwap::funded: swap is already funded, state: {0:?}")]
+    AlreadyFunded(Box<SwapState>),
+    #[error("AtomicSwap: swap is not funded, state: {0:?}")]
+    NotFunded(Box<SwapState>),
+    #[error("AtomicSwap: expected HTLC output {expected}, found {found}")]
+    HtlcOutputMismatch {
+        expected: SiacoinOutputId,
+        found: SiacoinOutputId,
+    },
+    #[error("SwapParams: keypair {found} does not match the expected public key {expected}")]
+    PublicKeyMismatch {
+        expected: Box<PublicKey>,
+        found: Box<PublicKey>,
+    },
+    #[error("SwapParams::redeem_transaction: secret does not match secret hash {0}")]
+    SecretMismatch(Hash256),
+    #[error("SwapParams: HTLC value {value} cannot cover miner fee {miner_fee}")]
+    InsufficientValue { value: Currency, miner_fee: Currency },
+    #[error("SwapParams: failed to satisfy HTLC input {0}")]
+    Satisfy(#[from] V2TransactionBuilderError),
+    #[error("AtomicSwap: transaction {txid} does not spend HTLC output {output_id}")]
+    SpendNotFound {
+        txid: TransactionId,
+        output_id: SiacoinOutputId,
+    },
+    #[error("AtomicSwap::spent: transaction {0} spends the HTLC with a policy of a different address")]
+    UnknownSpendPath(TransactionId),
+    #[error("SwapParams::extract_secret: transaction {txid} reveals no preimage of secret hash {secret_hash}")]
+    SecretNotRevealed { txid: TransactionId, secret_hash: Hash256 },
+}
+
+/// The terms of one side of an atomic swap. Both parties must construct identical params for the
+/// HTLC address to match.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapParams {
+    /// The key that can claim the HTLC by revealing the secret
+    pub success_public_key: PublicKey,
+    /// The key that can claim the HTLC once `refund_lock` has expired
+    pub refund_public_key: PublicKey,
+    /// The height and/or median timestamp after which the refund path is valid
+    pub refund_lock: RefundLock,
+    /// The sha256 hash of the secret
+    pub secret_hash: Hash256,
+    /// The value locked in the HTLC
+    pub amount: Currency,
+}
+
+/// Swaps persisted before refund locks by height were supported have a `lockTime` timestamp in place
+/// of `refundLock`. It is read as `RefundLock::Time`.
+impl<'de> Deserialize<'de> for SwapParams {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct SwapParamsHelper {
+            success_public_key: PublicKey,
+            refund_public_key: PublicKey,
+            refund_lock: Option<RefundLock>,
+            lock_time: Option<u64>,
+            secret_hash: Hash256,
+            amount: Currency,
+        }
+
+        let helper = SwapParamsHelper::deserialize(deserializer)?;
+        let refund_lock = match (helper.refund_lock, helper.lock_time) {
+            (Some(refund_lock), None) => refund_lock,
+            (None, Some(lock_time)) => RefundLock::Time(lock_time),
+            (Some(_), Some(_)) => return Err(serde::de::Error::custom("both refundLock and lockTime are set")),
+            (None, None) => return Err(serde::de::Error::missing_field("refundLock")),
+        };
+        Ok(SwapParams {
+            success_public_key: helper.success_public_key,
+            refund_public_key: helper.refund_public_key,
+            refund_lock,
+            secret_hash: helper.secret_hash,
+            amount: helper.amount,
+        })
+    }
+}
+
+impl SwapParams {
+    /// The full HTLC policy. See `SpendPolicy::atomic_swap_with_lock`
+    pub fn policy(&self) -> SpendPolicy {
+        SpendPolicy::atomic_swap_with_lock(
+            &self.success_public_key,
+            &self.refund_public_key,
+            &self.refund_lock,
+            &self.secret_hash,
+        )
+    }
+
+    /// The policy revealed when redeeming, the refund path is opacified
+    pub fn success_policy(&self) -> SpendPolicy {
+        SpendPolicy::atomic_swap_with_lock_success(
+            &self.success_public_key,
+            &self.refund_public_key,
+            &self.refund_lock,
+            &self.secret_hash,
+        )
+    }
+
+    /// The policy revealed when refunding, the success path is opacified
+    pub fn refund_policy(&self) -> SpendPolicy {
+        SpendPolicy::atomic_swap_with_lock_refund(
+            &self.success_public_key,
+            &self.refund_public_key,
+            &self.refund_lock,
+            &self.secret_hash,
+        )
+    }
+
+    pub fn address(&self) -> Address { self.policy().address() }
+
+    pub fn htlc_output(&self) -> SiacoinOutput { (self.address(), self.amount).into() }
+
+    /// A builder paying `amount` to the HTLC address. The builder must then be funded and signed,
+    /// eg, with `ApiClientHelpers::fund_tx_single_source` and `V2TransactionBuilder::sign_simple`.
+    pub fn funding_builder(&self) -> V2TransactionBuilder {
+        let mut builder = V2TransactionBuilder::new();
+        builder.add_siacoin_output(self.htlc_output());
+        builder
+    }
+
+    /// Find the output of `tx` paying exactly `amount` to the HTLC address.
+    /// The address commits to the keys, refund lock and secret hash so an output paying to a different
+    /// address is reported as HtlcOutputNotFound regardless of which of these differ.
+    pub fn find_htlc_output(&self, tx: &V2Transaction) -> Result<SiacoinOutputId, AtomicSwapError> {
+        let address = self.address();
+        let txid = tx.txid();
+        let mut htlc_outputs = tx
+            .siacoin_outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| output.address == address)
+            .peekable();
+
+        let found = match htlc_outputs.peek() {
+            Some((_, output)) => output.value,
+            None => return Err(AtomicSwapError::HtlcOutputNotFound { txid, address }),
+        };
+        match htlc_outputs.find(|(_, output)| output.value == self.amount) {
+            Some((index, _)) => Ok(SiacoinOutputId::new(txid, index as u32)),
+            None => Err(AtomicSwapError::HtlcAmountMismatch {
+                txid,
+                expected: self.amount,
+                found,
+            }),
+        }
+    }
+
+    /// Extract the secret revealed by `spend_tx` when it redeems the HTLC output `htlc_output_id`.
+    /// The preimages of the HTLC input are searched for one hashing to `secret_hash`, whichever
+    /// policy the input revealed. Fails if `spend_tx` does not spend the HTLC output or reveals no
+    /// such preimage, eg, if it is a refund.
+    pub fn extract_secret(
+        &self,
+        spend_tx: &V2Transaction,
+        htlc_output_id: &SiacoinOutputId,
+    ) -> Result<Preimage, AtomicSwapError> {
+        let htlc_input = spend_tx
+            .siacoin_inputs
+            .iter()
+            .find(|input| input.parent.id == *htlc_output_id)
+            .ok_or_else(|| AtomicSwapError::SpendNotFound {
+                txid: spend_tx.txid(),
+                output_id: htlc_output_id.clone(),
+            })?;
+
+        htlc_input
+            .satisfied_policy
+            .preimages
+            .iter()
+            .find(|preimage| preimage.compute_hash() == self.secret_hash)
+            .cloned()
+            .ok_or_else(|| AtomicSwapError::SecretNotRevealed {
+                txid: spend_tx.txid(),
+                secret_hash: self.secret_hash.clone(),
+            })
+    }
+
+    /// Build a transaction spending `htlc` to `destination` via the success path
+    pub fn redeem_transaction(
+        &self,
+        htlc: SiacoinElement,
+        keypair: &Keypair,
+        secret: Preimage,
+        destination: &Address,
+        miner_fee: Currency,
+    ) -> Result<V2Transaction, AtomicSwapError> {
+        self.check_public_key(&self.success_public_key, keypair)?;
+        if secret.compute_hash() != self.secret_hash {
+            return Err(AtomicSwapError::SecretMismatch(self.secret_hash.clone()));
+        }
+        let mut builder = self.spend_builder(htlc, self.success_policy(), destination, miner_fee)?;
+        builder.satisfy_atomic_swap_success(keypair, secret, 0)?;
+        Ok(builder.build())
+    }
+
+    /// Build a transaction spending `htlc` to `destination` via the refund path.
+    /// The transaction is not valid until `refund_lock` has expired, see `RefundLock::is_expired`.
+    pub fn refund_transaction(
+        &self,
+        htlc: SiacoinElement,
+        keypair: &Keypair,
+        destination: &Address,
+        miner_fee: Currency,
+    ) -> Result<V2Transaction, AtomicSwapError> {
+        self.check_public_key(&self.refund_public_key, keypair)?;
+        let mut builder = self.spend_builder(htlc, self.refund_policy(), destination, miner_fee)?;
+        builder.satisfy_atomic_swap_refund(keypair, 0)?;
+        Ok(builder.build())
+    }
+
+    fn check_public_key(&self, expected: &PublicKey, keypair: &Keypair) -> Result<(), AtomicSwapError> {
+        let found = keypair.public();
+        if *expected != found {
+            return Err(AtomicSwapError::PublicKeyMismatch {
+                expected: Box::new(expected.clone()),
+                found: Box::new(found),
+            });
+        }
+        Ok(())
+    }
+
+    fn spend_builder(
+        &self,
+        htlc: SiacoinElement,
+        policy: SpendPolicy,
+        destination: &Address,
+        miner_fee: Currency,
+    ) -> Result<V2TransactionBuilder, AtomicSwapError> {
+        let value = htlc.siacoin_output.value;
+        if *value <= *miner_fee {
+            return Err(AtomicSwapError::InsufficientValue { value, miner_fee });
+        }
+        let mut builder = V2TransactionBuilder::new();
+        builder
+            .add_siacoin_input(htlc, policy)
+            .add_siacoin_output((destination.clone(), Currency(*value - *miner_fee)).into())
+            .miner_fee(miner_fee);
+        Ok(builder)
+    }
+}
+
+/// The progress of an atomic swap
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum SwapState {
+    /// The params are agreed but no funding transaction has been seen
+    Created,
+    /// The HTLC output has been created
+    #[serde(rename_all = "camelCase")]
+    Funded {
+        funding_txid: TransactionId,
+        htlc_output_id: SiacoinOutputId,
+    },
+    /// The HTLC output was spent via the success path
+    #[serde(rename_all = "camelCase")]
+    Redeemed {
+        htlc_output_id: SiacoinOutputId,
+        txid: TransactionId,
+    },
+    /// The HTLC output was spent via the refund path
+    #[serde(rename_all = "camelCase")]
+    Refunded {
+        htlc_output_id: SiacoinOutputId,
+        txid: TransactionId,
+    },
+}
+
+/// An atomic swap and its current state. Serialize this to persist the swap between restarts.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtomicSwap {
+    pub params: SwapParams,
+    pub state: SwapState,
+}
+
+impl AtomicSwap {
+    pub fn new(params: SwapParams) -> Self {
+        AtomicSwap {
+            params,
+            state: SwapState::Created,
+        }
+    }
+
+    /// The HTLC output, None until the swap is funded
+    pub fn htlc_output_id(&self) -> Option<&SiacoinOutputId> {
+        match &self.state {
+            SwapState::Created => None,
+            SwapState::Funded { htlc_output_id, .. }
+            | SwapState::Redeemed { htlc_output_id, .. }
+            | SwapState::Refunded { htlc_output_id, .. } => Some(htlc_output_id),
+        }
+    }
+
+    /// Whether the HTLC has been spent by either party
+    pub fn is_complete(&self) -> bool { matches!(self.state, SwapState::Redeemed { .. } | SwapState::Refunded { .. }) }
+
+    /// Record the funding transaction. Fails if `funding_tx` does not pay `amount` to the HTLC
+    /// address, so this also validates a funding transaction received from the counterparty.
+    pub fn funded(&mut self, funding_tx: &V2Transaction) -> Result<&SwapState, AtomicSwapError> {
+        if self.state != SwapState::Created {
+            return Err(AtomicSwapError::AlreadyFunded(Box::new(self.state.clone())));
+        }
+        let htlc_output_id = self.params.find_htlc_output(funding_tx)?;
+        self.state = SwapState::Funded {
+            funding_txid: funding_tx.txid(),
+            htlc_output_id,
+        };
+        Ok(&self.state)
+    }
+
+    /// Build the redeem transaction for the funded HTLC. `htlc` must be the element of the HTLC output.
+    pub fn redeem_transaction(
+        &self,
+        htlc: SiacoinElement,
+        keypair: &Keypair,
+        secret: Preimage,
+        destination: &Address,
+        miner_fee: Currency,
+    ) -> Result<V2Transaction, AtomicSwapError> {
+        self.check_htlc(&htlc)?;
+        self.params
+            .redeem_transaction(htlc, keypair, secret, destination, miner_fee)
+    }
+
+    /// Build the refund transaction for the funded HTLC. `htlc` must be the element of the HTLC output.
+    pub fn refund_transaction(
+        &self,
+        htlc: SiacoinElement,
+        keypair: &Keypair,
+        destination: &Address,
+        miner_fee: Currency,
+    ) -> Result<V2Transaction, AtomicSwapError> {
+        self.check_htlc(&htlc)?;
+        self.params.refund_transaction(htlc, keypair, destination, miner_fee)
+    }
+
+    /// Record the transaction that spent the HTLC, moving to Redeemed or Refunded. The spend may have
+    /// been made by either party. Any policy with the HTLC address is accepted, however much of it is
+    /// opacified; the spend is a redeem if it reveals a preimage of `secret_hash` and a refund otherwise.
+    pub fn spent(&mut self, spend_tx: &V2Transaction) -> Result<&SwapState, AtomicSwapError> {
+        let htlc_output_id = match &self.state {
+            SwapState::Funded { htlc_output_id, .. } => htlc_output_id.clone(),
+            state => return Err(AtomicSwapError::NotFunded(Box::new(state.clone()))),
+        };
+        let txid = spend_tx.txid();
+        let input = spend_tx
+            .siacoin_inputs
+            .iter()
+            .find(|input| input.parent.id == htlc_output_id)
+            .ok_or_else(|| AtomicSwapError::SpendNotFound {
+                txid: txid.clone(),
+                output_id: htlc_output_id.clone(),
+            })?;
+
+        if input.satisfied_policy.policy.address() != self.params.address() {
+            return Err(AtomicSwapError::UnknownSpendPath(txid));
+        }
+        let reveals_secret = input
+            .satisfied_policy
+            .preimages
+            .iter()
+            .any(|preimage| preimage.compute_hash() == self.params.secret_hash);
+        self.state = if reveals_secret {
+            SwapState::Redeemed { htlc_output_id, txid }
+        } else {
+            SwapState::Refunded { htlc_output_id, txid }
+        };
+        Ok(&self.state)
+    }
+
+    fn check_htlc(&self, htlc: &SiacoinElement) -> Result<(), AtomicSwapError> {
+        match &self.state {
+            SwapState::Funded { htlc_output_id, .. } if *htlc_output_id == htlc.id => Ok(()),
+            SwapState::Funded { htlc_output_id, .. } => Err(AtomicSwapError::HtlcOutputMismatch {
+                expected: htlc_output_id.clone(),
+                found: htlc.id.clone(),
+            }),
+            state => Err(AtomicSwapError::NotFunded(Box::new(state.clone()))),
+        }
+    }
+}
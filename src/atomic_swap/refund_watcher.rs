@@ -0,0 +1,259 @@
+use super::{AtomicSwap, AtomicSwapError, SwapState};
+use crate::transport::client::{ApiClientError, ApiClientHelpers, HelperError};
+use crate::types::{Address, Currency, Keypair, SiacoinOutputId, TransactionId};
+use std::future::Future;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RefundWatcherError {
+    #[error("RefundWatcher::watch: swap is not funded, state: {0:?}")]
+    NotFunded(Box<SwapState>),
+    #[error("RefundWatcher::watch: HTLC output {0} is already watched")]
+    AlreadyWatched(SiacoinOutputId),
+    #[error("RefundWatcher::poll: failed to fetch current height {0}")]
+    FetchHeight(ApiClientError),
+    #[error("RefundWatcher::poll: failed to fetch HTLC utxos {0}")]
+    FetchUtxos(ApiClientError),
+    #[error("RefundWatcher: {0}")]
+    Swap(#[from] AtomicSwapError),
+    #[error("RefundWatcher: {0}")]
+    Helper(#[from] HelperError),
+}
+
+/// Progress reported by `RefundWatcher::poll`
+#[derive(Debug)]
+pub enum RefundWatcherEvent {
+    /// The HTLC output is not yet confirmed or the refund lock has not expired
+    Waiting {
+        htlc_output_id: SiacoinOutputId,
+        height: u64,
+        median_timestamp: u64,
+    },
+    /// A refund transaction was broadcast. It is rebroadcast if it drops out of the mempool before
+    /// being confirmed.
+    RefundBroadcast {
+        htlc_output_id: SiacoinOutputId,
+        txid: TransactionId,
+    },
+    /// The HTLC output was spent, either by our refund or by the counterparty redeeming it.
+    /// The swap's state is Redeemed or Refunded and it is no longer watched.
+    Completed(Box<AtomicSwap>),
+    /// The HTLC output was spent by a transaction whose policy does not have the HTLC address.
+    /// Consensus rejects such a spend so this indicates bad data from the node. The spend is
+    /// confirmed so the swap is no longer watched; its state is left unchanged.
+    UnknownSpend { swap: Box<AtomicSwap>, txid: TransactionId },
+    /// Checking or refunding the HTLC output failed. It remains watched and is retried on the next poll.
+    Failed {
+        htlc_output_id: SiacoinOutputId,
+        error: RefundWatcherError,
+    },
+    /// The chain state could not be fetched so no HTLC was checked. Only reported by `RefundWatcher::run`.
+    PollFailed(RefundWatcherError),
+}
+
+impl RefundWatcherEvent {
+    /// Whether the swap reported by this event is no longer watched
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            RefundWatcherEvent::Completed(_) | RefundWatcherEvent::UnknownSpend { .. }
+        )
+    }
+}
+
+/// A funded HTLC to be refunded once its refund lock expires
+struct WatchedHtlc {
+    swap: AtomicSwap,
+    htlc_output_id: SiacoinOutputId,
+    keypair: Keypair,
+    destination: Address,
+    /// The height the funding transaction was broadcast at, the chain is scanned for spends from here
+    begin_height: u64,
+    refund_txid: Option<TransactionId>,
+}
+
+/// Watches HTLC outputs we funded and refunds them once the refund path is valid.
+///
+/// The watcher is runtime agnostic. Either call `poll` periodically or hand the watcher to `run`
+/// along with a sleep function from the async runtime in use. Events are reported to a callback,
+/// which may forward them to a channel.
+pub struct RefundWatcher {
+    htlcs: Vec<WatchedHtlc>,
+    miner_fee: Currency,
+}
+
+impl RefundWatcher {
+    /// Create a watcher paying `miner_fee` for each refund transaction
+    pub fn new(miner_fee: Currency) -> Self {
+        RefundWatcher {
+            htlcs: Vec::new(),
+            miner_fee,
+        }
+    }
+
+    /// Watch the HTLC of a funded swap. `keypair` must be the swap's refund key and the refund is
+    /// paid to `destination`. `begin_height` should be the height the swap was funded at.
+    pub fn watch(
+        &mut self,
+        swap: AtomicSwap,
+        keypair: Keypair,
+        destination: Address,
+        begin_height: u64,
+    ) -> Result<(), RefundWatcherError> {
+        let htlc_output_id = match &swap.state {
+            SwapState::Funded { htlc_output_id, .. } => htlc_output_id.clone(),
+            state => return Err(RefundWatcherError::NotFunded(Box::new(state.clone()))),
+        };
+        if self.htlcs.iter().any(|htlc| htlc.htlc_output_id == htlc_output_id) {
+            return Err(RefundWatcherError::AlreadyWatched(htlc_output_id));
+        }
+        if keypair.public() != swap.params.refund_public_key {
+            return Err(AtomicSwapError::PublicKeyMismatch {
+                expected: Box::new(swap.params.refund_public_key),
+                found: Box::new(keypair.public()),
+            })?;
+        }
+        self.htlcs.push(WatchedHtlc {
+            swap,
+            htlc_output_id,
+            keypair,
+            destination,
+            begin_height,
+            refund_txid: None,
+        });
+        Ok(())
+    }
+
+    /// The swaps still being watched, eg, to persist them before shutting down
+    pub fn swaps(&self) -> impl Iterator<Item = &AtomicSwap> { self.htlcs.iter().map(|htlc| &htlc.swap) }
+
+    pub fn is_empty(&self) -> bool { self.htlcs.is_empty() }
+
+    /// Check every watched HTLC once, broadcasting a refund for each unspent HTLC whose refund lock
+    /// has expired. Swaps whose HTLC output was spent are removed from the watcher.
+    /// Fails only if the chain state cannot be fetched. Failures of individual HTLCs are reported
+    /// as `RefundWatcherEvent::Failed`.
+    pub async fn poll<C, F>(&mut self, client: &C, on_event: &mut F) -> Result<(), RefundWatcherError>
+    where
+        C: ApiClientHelpers + Sync,
+        F: FnMut(RefundWatcherEvent),
+    {
+        // the refund is expected to be included in the child of the current tip
+        let height = client.current_height().await.map_err(RefundWatcherError::FetchHeight)? + 1;
+        let median_timestamp = client.get_median_timestamp().await?;
+
+        let mut watching = Vec::with_capacity(self.htlcs.len());
+        for mut htlc in self.htlcs.drain(..) {
+            match htlc.check(client, height, median_timestamp, self.miner_fee).await {
+                Ok(Some(event)) if event.is_final() => on_event(event),
+                Ok(event) => {
+                    if let Some(event) = event {
+                        on_event(event);
+                    }
+                    watching.push(htlc);
+                },
+                Err(error) => {
+                    on_event(RefundWatcherEvent::Failed {
+                        htlc_output_id: htlc.htlc_output_id.clone(),
+                        error,
+                    });
+                    watching.push(htlc);
+                },
+            }
+        }
+        self.htlcs = watching;
+        Ok(())
+    }
+
+    /// Poll until every watched swap is complete, calling `sleep` between polls.
+    /// Failing to fetch the chain state does not stop the watcher, the error is reported as
+    /// `RefundWatcherEvent::PollFailed` and polling continues.
+    pub async fn run<C, S, Fut, F>(mut self, client: &C, sleep: S, mut on_event: F)
+    where
+        C: ApiClientHelpers + Sync,
+        S: Fn() -> Fut,
+        Fut: Future<Output = ()>,
+        F: FnMut(RefundWatcherEvent),
+    {
+        while !self.is_empty() {
+            if let Err(error) = self.poll(client, &mut on_event).await {
+                on_event(RefundWatcherEvent::PollFailed(error));
+            }
+            if !self.is_empty() {
+                sleep().await;
+            }
+        }
+    }
+}
+
+impl WatchedHtlc {
+    async fn check<C>(
+        &mut self,
+        client: &C,
+        height: u64,
+        median_timestamp: u64,
+        miner_fee: Currency,
+    ) -> Result<Option<RefundWatcherEvent>, RefundWatcherError>
+    where
+        C: ApiClientHelpers + Sync,
+    {
+        let unspent = client
+            .get_unspent_outputs(&self.swap.params.address(), None, None)
+            .await
+            .map_err(RefundWatcherError::FetchUtxos)?
+            .into_iter()
+            .find(|element| element.id == self.htlc_output_id);
+
+        let htlc = match unspent {
+            Some(htlc) => htlc,
+            None => {
+                // either the funding transaction is unconfirmed or the HTLC has been spent
+                return match client
+                    .find_where_utxo_spent(&self.htlc_output_id, self.begin_height)
+                    .await?
+                {
+                    Some(spend_tx) => match self.swap.spent(&spend_tx) {
+                        Ok(_) => Ok(Some(RefundWatcherEvent::Completed(Box::new(self.swap.clone())))),
+                        // a confirmed spend is final whichever path it took
+                        Err(AtomicSwapError::UnknownSpendPath(txid)) => Ok(Some(RefundWatcherEvent::UnknownSpend {
+                            swap: Box::new(self.swap.clone()),
+                            txid,
+                        })),
+                        Err(e) => Err(e.into()),
+                    },
+                    None => Ok(Some(self.waiting(height, median_timestamp))),
+                };
+            },
+        };
+
+        if !self.swap.params.refund_lock.is_expired(height, median_timestamp) {
+            return Ok(Some(self.waiting(height, median_timestamp)));
+        }
+
+        // a refund already in the mempool is left alone until it is confirmed
+        if let Some(txid) = &self.refund_txid {
+            if client.get_unconfirmed_transaction(txid).await?.is_some() {
+                return Ok(None);
+            }
+        }
+
+        let refund_tx = self
+            .swap
+            .refund_transaction(htlc, &self.keypair, &self.destination, miner_fee)?;
+        client.broadcast_transaction(&refund_tx).await?;
+        let txid = refund_tx.txid();
+        self.refund_txid = Some(txid.clone());
+        Ok(Some(RefundWatcherEvent::RefundBroadcast {
+            htlc_output_id: self.htlc_output_id.clone(),
+            txid,
+        }))
+    }
+
+    fn waiting(&self, height: u64, median_timestamp: u64) -> RefundWatcherEvent {
+        RefundWatcherEvent::Waiting {
+            htlc_output_id: self.htlc_output_id.clone(),
+            height,
+            median_timestamp,
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! Coin selection strategies for funding transactions.
+//!
+//! A CoinSelector picks which SiacoinElements (UTXOs) to spend for a payment. Selection is fee aware:
+//! every input added to a transaction increases its weight and therefore its fee, so each strategy
+//! works with an input's effective value, ie, its value minus the fee it costs to spend it.
+//! Inputs whose effective value is not positive are never selected.
+//!
+//! Strategies operate on a plain `Vec<SiacoinElement>` so wallets can choose a strategy per payment
+//! regardless of where the UTXOs came from.
+use crate::types::{Currency, FeePolicy, SiacoinElement, SiafundElement, SpendPolicy, V2TransactionBuilder};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, RngCore, SeedableRng};
+use thiserror::Error;
+
+/// the v2 encoding of a SiacoinOutput, a 16 byte Currency followed by a 32 byte Address
+const V2_SIACOIN_OUTPUT_SIZE: u64 = 48;
+
+#[derive(Debug, Error)]
+pub enum CoinSelectionError {
+    #[error("CoinSelector::select: insufficient funds, available: {available} required: {required}")]
+    InsufficientFunds { available: Currency, required: Currency },
+    #[error("BranchAndBound::select: no changeless selection found")]
+    NoChangelessSolution,
+    #[error("Consolidate::select: payment requires {required} inputs but at most {max_inputs} are allowed")]
+    TooManyInputs { required: usize, max_inputs: usize },
+    #[error("select_siafunds: insufficient siafunds, available: {available} required: {required}")]
+    InsufficientSiafunds { available: u64, required: u64 },
+    #[error("CoinSelector::select: sum of utxo values overflows")]
+    ValueOverflow,
+}
+
+/// Sum utxo values, failing rather than overflowing
+fn checked_sum<I: IntoIterator<Item = u128>>(values: I) -> Result<u128, CoinSelectionError> {
+    values
+        .into_iter()
+        .try_fold(0u128, |total, value| total.checked_add(value))
+        .ok_or(CoinSelectionError::ValueOverflow)
+}
+
+/// The amount a selection must cover and the fees incurred by the transaction funded by it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelectionParams {
+    /// the total value of the outputs being paid
+    pub target: Currency,
+    /// the fee of the transaction before any inputs or change output are added
+    pub base_fee: Currency,
+    /// the fee added by each input
+    pub input_fee: Currency,
+    /// the fee added by a change output
+    pub change_fee: Currency,
+    /// change at or below this amount is added to the fee rather than creating a change output
+    pub min_change: Currency,
+}
+
+impl SelectionParams {
+    /// Derive the params for funding `builder` with inputs spendable by `input_policy`.
+    /// Fees are calculated from the builder's `fee_policy`. If no fee policy is set, the builder's
+    /// current `miner_fee` is used as a fixed fee.
+    pub fn from_builder(builder: &V2TransactionBuilder, input_policy: &SpendPolicy) -> Self {
+        let target = Currency(builder.siacoin_outputs.iter().map(|output| *output.value).sum());
+        let (base_fee, input_fee, change_fee) = match &builder.fee_policy {
+            Some(fee_policy @ FeePolicy::HastingsPerByte(_)) => (
+                fee_policy.fee(builder.satisfied_weight()),
+                fee_policy.fee(input_policy.max_satisfied_size()),
+                fee_policy.fee(V2_SIACOIN_OUTPUT_SIZE),
+            ),
+            Some(fee_policy @ FeePolicy::HastingsFixed(_)) => (fee_policy.fee(0), Currency::ZERO, Currency::ZERO),
+            None => (builder.miner_fee, Currency::ZERO, Currency::ZERO),
+        };
+        SelectionParams {
+            target,
+            base_fee,
+            input_fee,
+            change_fee,
+            min_change: Currency::DUST,
+        }
+    }
+
+    /// the value an input contributes after paying for its own inclusion
+    fn effective_value(&self, utxo: &SiacoinElement) -> Option<u128> {
+        utxo.siacoin_output
+            .value
+            .checked_sub(*self.input_fee)
+            .filter(|value| *value > 0)
+    }
+
+    /// the amount the inputs' effective values must cover
+    fn required(&self) -> u128 { self.target.saturating_add(*self.base_fee) }
+
+    fn insufficient_funds(&self, utxos: &[SiacoinElement]) -> CoinSelectionError {
+        CoinSelectionError::InsufficientFunds {
+            available: Currency(
+                utxos
+                    .iter()
+                    .filter_map(|utxo| self.effective_value(utxo))
+                    .fold(0, u128::saturating_add),
+            ),
+            required: Currency(self.required()),
+        }
+    }
+}
+
+/// The result of coin selection. The value of `inputs` is exactly `target + fee + change`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    pub inputs: Vec<SiacoinElement>,
+    /// the miner fee of the funded transaction
+    pub fee: Currency,
+    /// the value of the change output. Zero if no change output should be created.
+    pub change: Currency,
+}
+
+impl Selection {
+    /// Build a Selection from `inputs` if they cover the target and fees
+    fn new(inputs: Vec<SiacoinElement>, params: &SelectionParams) -> Result<Option<Selection>, CoinSelectionError> {
+        let total = checked_sum(inputs.iter().map(|utxo| *utxo.siacoin_output.value))?;
+        Ok(Self::with_total(inputs, total, params))
+    }
+
+    fn with_total(inputs: Vec<SiacoinElement>, total: u128, params: &SelectionParams) -> Option<Selection> {
+        let fee = params
+            .base_fee
+            .checked_add((*params.input_fee).checked_mul(inputs.len() as u128)?)?;
+        let excess = total.checked_sub(params.target.checked_add(fee)?)?;
+        let (fee, change) = match excess.checked_sub(*params.change_fee) {
+            Some(change) if change > *params.min_change => (fee + *params.change_fee, change),
+            _ => (fee + excess, 0),
+        };
+        Some(Selection {
+            inputs,
+            fee: Currency(fee),
+            change: Currency(change),
+        })
+    }
+}
+
+pub trait CoinSelector {
+    fn select(&self, utxos: Vec<SiacoinElement>, params: &SelectionParams) -> Result<Selection, CoinSelectionError>;
+}
+
+/// Add utxos in the given order until the selection covers the target and fees
+fn select_in_order(utxos: Vec<SiacoinElement>, params: &SelectionParams) -> Result<Selection, CoinSelectionError> {
+    let mut selected = Vec::new();
+    for utxo in utxos.iter().filter(|utxo| params.effective_value(utxo).is_some()) {
+        selected.push(utxo.clone());
+        if let Some(selection) = Selection::new(selected.clone(), params)? {
+            return Ok(selection);
+        }
+    }
+    Err(params.insufficient_funds(&utxos))
+}
+
+/// Spend the largest UTXOs first. Minimizes the number of inputs of the transaction.
+#[derive(Clone, Debug, Default)]
+pub struct LargestFirst;
+
+impl CoinSelector for LargestFirst {
+    fn select(
+        &self,
+        mut utxos: Vec<SiacoinElement>,
+        params: &SelectionParams,
+    ) -> Result<Selection, CoinSelectionError> {
+        utxos.sort_by(|a, b| b.siacoin_output.value.cmp(&a.siacoin_output.value));
+        select_in_order(utxos, params)
+    }
+}
+
+/// Spend the smallest UTXOs first. Reduces the number of UTXOs held by the wallet at the cost of larger
+/// transactions.
+#[derive(Clone, Debug, Default)]
+pub struct SmallestFirst;
+
+impl CoinSelector for SmallestFirst {
+    fn select(
+        &self,
+        mut utxos: Vec<SiacoinElement>,
+        params: &SelectionParams,
+    ) -> Result<Selection, CoinSelectionError> {
+        utxos.sort_by(|a, b| a.siacoin_output.value.cmp(&b.siacoin_output.value));
+        select_in_order(utxos, params)
+    }
+}
+
+/// Search for a selection that requires no change output, ie, one where the excess is no more than
+/// the cost of creating change. The first such selection found is returned.
+/// Fails with `NoChangelessSolution` if none is found within `max_tries` steps. Callers will typically
+/// fall back to another strategy in that case.
+#[derive(Clone, Debug)]
+pub struct BranchAndBound {
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    fn default() -> Self { BranchAndBound { max_tries: 100_000 } }
+}
+
+struct BranchAndBoundSearch<'a> {
+    values: &'a [u128],
+    target: u128,
+    upper_bound: u128,
+    tries: usize,
+    selected: Vec<usize>,
+}
+
+impl<'a> BranchAndBoundSearch<'a> {
+    /// depth first search including then excluding each value; `remaining` is the sum of values[index..]
+    fn search(&mut self, index: usize, current: u128, remaining: u128) -> bool {
+        if current > self.upper_bound {
+            return false;
+        }
+        if current >= self.target {
+            return true;
+        }
+        if index == self.values.len() || current + remaining < self.target || self.tries == 0 {
+            return false;
+        }
+        self.tries -= 1;
+
+        let value = self.values[index];
+        self.selected.push(index);
+        if self.search(index + 1, current + value, remaining - value) {
+            return true;
+        }
+        self.selected.pop();
+        self.search(index + 1, current, remaining - value)
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(&self, utxos: Vec<SiacoinElement>, params: &SelectionParams) -> Result<Selection, CoinSelectionError> {
+        let mut candidates: Vec<(u128, SiacoinElement)> = utxos
+            .iter()
+            .filter_map(|utxo| params.effective_value(utxo).map(|value| (value, utxo.clone())))
+            .collect();
+        // trying the largest values first reaches the target in fewer steps
+        candidates.sort_by(|a, b| b.0.cmp(&a.0));
+        let values: Vec<u128> = candidates.iter().map(|(value, _)| *value).collect();
+
+        let total = checked_sum(values.iter().copied())?;
+        if total < params.required() {
+            return Err(params.insufficient_funds(&utxos));
+        }
+
+        let mut search = BranchAndBoundSearch {
+            values: &values,
+            target: params.required(),
+            upper_bound: params
+                .required()
+                .saturating_add(*params.change_fee)
+                .saturating_add(*params.min_change),
+            tries: self.max_tries,
+            selected: Vec::new(),
+        };
+        if !search.search(0, 0, total) {
+            return Err(CoinSelectionError::NoChangelessSolution);
+        }
+        let inputs = search.selected.iter().map(|i| candidates[*i].1.clone()).collect();
+        Selection::new(inputs, params)?.ok_or(CoinSelectionError::NoChangelessSolution)
+    }
+}
+
+/// Select random UTXOs until the target is covered, then keep adding random UTXOs while doing so moves
+/// the total closer to twice the target without exceeding three times the target. The resulting change
+/// is roughly the size of the payment which keeps the wallet's UTXO set useful for future payments.
+/// See CIP-2 <https://cips.cardano.org/cip/CIP-0002>.
+#[derive(Clone, Debug, Default)]
+pub struct RandomImprove {
+    /// A fixed seed for reproducible selection. A thread local RNG is used if None.
+    pub seed: Option<u64>,
+}
+
+impl RandomImprove {
+    fn select_with_rng<R: RngCore>(
+        &self,
+        mut utxos: Vec<SiacoinElement>,
+        params: &SelectionParams,
+        rng: &mut R,
+    ) -> Result<Selection, CoinSelectionError> {
+        let mut candidates: Vec<SiacoinElement> = utxos
+            .iter()
+            .filter(|utxo| params.effective_value(utxo).is_some())
+            .cloned()
+            .collect();
+        candidates.shuffle(rng);
+
+        // random selection until the target is covered
+        let mut selected = Vec::new();
+        let mut selection = None;
+        while let Some(utxo) = candidates.pop() {
+            selected.push(utxo);
+            selection = Selection::new(selected.clone(), params)?;
+            if selection.is_some() {
+                break;
+            }
+        }
+        let mut selection = match selection {
+            Some(selection) => selection,
+            None => {
+                utxos.retain(|utxo| params.effective_value(utxo).is_some());
+                return Err(params.insufficient_funds(&utxos));
+            },
+        };
+
+        // improvement
+        let effective_total =
+            |inputs: &[SiacoinElement]| checked_sum(inputs.iter().filter_map(|utxo| params.effective_value(utxo)));
+        let ideal = params.required().saturating_mul(2);
+        let maximum = params.required().saturating_mul(3);
+        while let Some(utxo) = candidates.pop() {
+            let current = effective_total(&selection.inputs)?;
+            let improved = current
+                .checked_add(params.effective_value(&utxo).unwrap_or_default())
+                .ok_or(CoinSelectionError::ValueOverflow)?;
+            if improved > maximum || ideal.abs_diff(improved) >= ideal.abs_diff(current) {
+                break;
+            }
+            let mut inputs = selection.inputs.clone();
+            inputs.push(utxo);
+            match Selection::new(inputs, params)? {
+                Some(improved) => selection = improved,
+                None => break,
+            }
+        }
+        Ok(selection)
+    }
+}
+
+impl CoinSelector for RandomImprove {
+    fn select(&self, utxos: Vec<SiacoinElement>, params: &SelectionParams) -> Result<Selection, CoinSelectionError> {
+        match self.seed {
+            Some(seed) => self.select_with_rng(utxos, params, &mut StdRng::seed_from_u64(seed)),
+            None => self.select_with_rng(utxos, params, &mut thread_rng()),
+        }
+    }
+}
+
+/// Use up to `max_inputs` inputs. The fewest largest UTXOs needed to cover the target are selected,
+/// then the remaining slots are filled with the smallest UTXOs to consolidate them into the change
+/// output. Useful when fees are low.
+#[derive(Clone, Debug)]
+pub struct Consolidate {
+    pub max_inputs: usize,
+}
+
+impl CoinSelector for Consolidate {
+    fn select(&self, utxos: Vec<SiacoinElement>, params: &SelectionParams) -> Result<Selection, CoinSelectionError> {
+        let selection = LargestFirst.select(utxos.clone(), params)?;
+        if selection.inputs.len() > self.max_inputs {
+            return Err(CoinSelectionError::TooManyInputs {
+                required: selection.inputs.len(),
+                max_inputs: self.max_inputs,
+            });
+        }
+
+        let mut remaining: Vec<SiacoinElement> = utxos
+            .into_iter()
+            .filter(|utxo| params.effective_value(utxo).is_some() && !selection.inputs.contains(utxo))
+            .collect();
+        remaining.sort_by(|a, b| a.siacoin_output.value.cmp(&b.siacoin_output.value));
+
+        let mut inputs = selection.inputs.clone();
+        inputs.extend(remaining.into_iter().take(self.max_inputs - selection.inputs.len()));
+        // each additional input has a positive effective value so the selection remains funded
+        Ok(Selection::new(inputs, params)?.unwrap_or(selection))
+    }
+}
+
+/// Select siafund UTXOs largest first, returning the selected UTXOs and the change.
+/// Siafunds cannot pay miner fees so, unlike siacoin selection, no fees are considered. The fee of
+/// a transaction sending siafunds is funded with siacoins.
+pub fn select_siafunds(
+    mut utxos: Vec<SiafundElement>,
+    amount: u64,
+) -> Result<(Vec<SiafundElement>, u64), CoinSelectionError> {
+    utxos.sort_by(|a, b| b.siafund_output.value.cmp(&a.siafund_output.value));
+
+    let mut selected = Vec::new();
+    let mut selected_amount: u64 = 0;
+    for utxo in utxos {
+        if selected_amount >= amount {
+            break;
+        }
+        selected_amount = selected_amount.saturating_add(utxo.siafund_output.value);
+        selected.push(utxo);
+    }
+    if selected_amount < amount {
+        return Err(CoinSelectionError::InsufficientSiafunds {
+            available: selected_amount,
+            required: amount,
+        });
+    }
+    Ok((selected, selected_amount - amount))
+}
@@ -1,5 +1,7 @@
 use crate::blake2b_internal::hash_blake2b_single;
-use crate::types::Hash256;
+use crate::types::{Hash256, PublicKeyError};
+use std::convert::TryInto;
+use thiserror::Error;
 
 // https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/types/encoding.go#L16
 // TODO go implementation limits this to 1024 bytes, should we?
@@ -57,6 +59,129 @@ impl Encodable for Hash256 {
     fn encode(&self, encoder: &mut Encoder) { encoder.write_slice(&self.0); }
 }
 
+impl Decodable for Hash256 {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(Hash256(decoder.read_array()?)) }
+}
+
+#[derive(Debug, Error)]
+pub enum DecoderError {
+    #[error("Decoder: unexpected end of input, expected {expected} bytes, found {remaining}")]
+    UnexpectedEof { expected: usize, remaining: usize },
+    #[error("Decoder: invalid length prefix {prefix}, only {remaining} bytes remain in the input")]
+    InvalidLengthPrefix { prefix: u64, remaining: usize },
+    #[error("Decoder: invalid bool value {0}")]
+    InvalidBool(u8),
+    #[error("Decoder: invalid utf8 string {0}")]
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+    #[error("Decoder: {0} trailing bytes remain after decoding")]
+    TrailingBytes(usize),
+    #[error("Decoder: invalid public key {0}")]
+    PublicKey(#[from] PublicKeyError),
+    #[error("Decoder: invalid signature {0}")]
+    Signature(#[from] ed25519_dalek::ed25519::Error),
+    #[error("Decoder: Currency length prefix exceeds 16 bytes, found {0}")]
+    CurrencyOverflow(usize),
+    #[error("Decoder: unsupported SpendPolicy version {0}")]
+    PolicyVersion(u8),
+    #[error("Decoder: unknown SpendPolicy opcode {0}")]
+    PolicyOpcode(u8),
+    #[error("Decoder: SpendPolicy exceeds the maximum of {0} policies")]
+    PolicyTooComplex(usize),
+    #[error("Decoder: unsupported V2Transaction version {0}")]
+    TransactionVersion(u8),
+    #[error("Decoder: unknown V2FileContractResolution type {0}")]
+    ResolutionType(u8),
+}
+
+// https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/types/encoding.go#L146
+/// Reads the Sia binary encoding produced by `Encoder`.
+/// Length prefixes are checked against the remaining input so a malformed prefix can never trigger
+/// a large allocation. This mirrors the io.LimitedReader check of Sia Go's `ReadPrefix`.
+pub struct Decoder<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self { Decoder { buffer, position: 0 } }
+
+    /// number of bytes that have not been consumed yet
+    pub fn remaining(&self) -> usize { self.buffer.len() - self.position }
+
+    pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecoderError> {
+        if len > self.remaining() {
+            return Err(DecoderError::UnexpectedEof {
+                expected: len,
+                remaining: self.remaining(),
+            });
+        }
+        let slice = &self.buffer[self.position..self.position + len];
+        self.position += len;
+        Ok(slice)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecoderError> {
+        Ok(self.read_slice(N)?.try_into().expect("slice is N bytes long"))
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecoderError> { Ok(self.read_slice(1)?[0]) }
+
+    pub fn read_u64(&mut self) -> Result<u64, DecoderError> { Ok(u64::from_le_bytes(self.read_array()?)) }
+
+    pub fn read_u128(&mut self) -> Result<u128, DecoderError> { Ok(u128::from_le_bytes(self.read_array()?)) }
+
+    pub fn read_bool(&mut self) -> Result<bool, DecoderError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(DecoderError::InvalidBool(b)),
+        }
+    }
+
+    /// reads a u64 length prefix. The prefix can never exceed the number of remaining bytes because
+    /// every encoded element occupies at least one byte.
+    pub fn read_prefix(&mut self) -> Result<usize, DecoderError> {
+        let prefix = self.read_u64()?;
+        if prefix > self.remaining() as u64 {
+            return Err(DecoderError::InvalidLengthPrefix {
+                prefix,
+                remaining: self.remaining(),
+            });
+        }
+        Ok(prefix as usize)
+    }
+
+    /// reads a length-prefixed []byte from the underlying stream.
+    pub fn read_len_prefixed_bytes(&mut self) -> Result<Vec<u8>, DecoderError> {
+        let len = self.read_prefix()?;
+        Ok(self.read_slice(len)?.to_vec())
+    }
+
+    // equivalent of Sia Core's DecodeSlice()
+    pub fn read_len_prefixed_vec<T: Decodable>(&mut self) -> Result<Vec<T>, DecoderError> {
+        let len = self.read_prefix()?;
+        (0..len).map(|_| T::decode(self)).collect()
+    }
+
+    pub fn read_string(&mut self) -> Result<String, DecoderError> {
+        Ok(String::from_utf8(self.read_len_prefixed_bytes()?)?)
+    }
+
+    // Utility method to decode an item that must consume the entire input
+    pub fn decode_all<T: Decodable>(buffer: &[u8]) -> Result<T, DecoderError> {
+        let mut decoder = Decoder::new(buffer);
+        let item = T::decode(&mut decoder)?;
+        match decoder.remaining() {
+            0 => Ok(item),
+            n => Err(DecoderError::TrailingBytes(n)),
+        }
+    }
+}
+
+pub trait Decodable: Sized {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError>;
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -142,5 +267,63 @@ mod test {
                 Hash256::from_str("b66d7a9bef9fb303fe0e41f6b5c5af410303e428c4ff9231f6eb381248693221").unwrap()
             );
         }
+
+        fn test_decoder_primitives() {
+            let mut encoder = Encoder::default();
+            encoder.write_bool(true);
+            encoder.write_u8(7);
+            encoder.write_u64(u64::MAX - 1);
+            encoder.write_u128(u128::MAX - 1);
+            encoder.write_string("sia");
+            encoder.write_len_prefixed_bytes(&[1, 2, 3, 4]);
+
+            let mut decoder = Decoder::new(&encoder.buffer);
+            assert!(decoder.read_bool().unwrap());
+            assert_eq!(decoder.read_u8().unwrap(), 7);
+            assert_eq!(decoder.read_u64().unwrap(), u64::MAX - 1);
+            assert_eq!(decoder.read_u128().unwrap(), u128::MAX - 1);
+            assert_eq!(decoder.read_string().unwrap(), "sia");
+            assert_eq!(decoder.read_len_prefixed_bytes().unwrap(), vec![1, 2, 3, 4]);
+            assert_eq!(decoder.remaining(), 0);
+        }
+
+        fn test_decoder_unexpected_eof() {
+            let mut decoder = Decoder::new(&[1, 2, 3]);
+            match decoder.read_u64() {
+                Err(DecoderError::UnexpectedEof { expected: 8, remaining: 3 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_decoder_invalid_length_prefix() {
+            let mut encoder = Encoder::default();
+            encoder.write_u64(u64::MAX);
+            encoder.write_slice(&[1, 2, 3, 4]);
+
+            let mut decoder = Decoder::new(&encoder.buffer);
+            match decoder.read_len_prefixed_bytes() {
+                Err(DecoderError::InvalidLengthPrefix { prefix: u64::MAX, remaining: 4 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_decoder_invalid_bool() {
+            let mut decoder = Decoder::new(&[2]);
+            match decoder.read_bool() {
+                Err(DecoderError::InvalidBool(2)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_decoder_trailing_bytes() {
+            let mut encoder = Encoder::default();
+            Hash256::default().encode(&mut encoder);
+            encoder.write_u8(0);
+
+            match Decoder::decode_all::<Hash256>(&encoder.buffer) {
+                Err(DecoderError::TrailingBytes(1)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
     }
 }
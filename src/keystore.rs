@@ -0,0 +1,289 @@
+use crate::seed::Seed;
+use crate::types::{Keypair, KeypairError, PublicKey};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// The current version of the keystore format
+pub const KEYSTORE_VERSION: u8 = 1;
+
+/// Argon2id version 0x13
+const KDF_ALGORITHM: &str = "argon2id";
+const CIPHER_ALGORITHM: &str = "xchacha20-poly1305";
+const SALT_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error("Keystore::decrypt: unsupported keystore version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Keystore::decrypt: unsupported algorithm {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("Keystore::decrypt: expected a {expected:?} keystore, found {found:?}")]
+    KindMismatch {
+        expected: KeystoreKind,
+        found: KeystoreKind,
+    },
+    #[error("Keystore::decrypt: invalid password or corrupted keystore")]
+    Decrypt,
+    #[error("Keystore::decrypt: expected {expected} bytes of plaintext, found {found}")]
+    PlaintextLength { expected: usize, found: usize },
+    #[error("Keystore::decrypt_keypair: invalid private key {0}")]
+    InvalidKeypair(#[from] KeypairError),
+    #[error("Keystore::decrypt_keypair: decrypted keypair does not match public key {0}")]
+    PublicKeyMismatch(Box<PublicKey>),
+    #[error("Keystore: invalid kdf parameters {0}")]
+    InvalidKdfParams(argon2::Error),
+    #[error("Keystore::from_json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Keystore::load: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The type of secret held by a Keystore
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeystoreKind {
+    Seed,
+    Keypair,
+}
+
+/// Parameters of the password based key derivation
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    pub algorithm: String,
+    /// Memory cost in KiB
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    #[serde(with = "hex")]
+    pub salt: Vec<u8>,
+}
+
+impl KdfParams {
+    /// The OWASP recommended minimum for Argon2id: 19 MiB of memory, 2 iterations and 1 lane
+    pub const DEFAULT_MEMORY: u32 = 19 * 1024;
+    pub const DEFAULT_ITERATIONS: u32 = 2;
+    pub const DEFAULT_PARALLELISM: u32 = 1;
+
+    /// Argon2id parameters with a freshly generated salt
+    pub fn new(memory: u32, iterations: u32, parallelism: u32) -> Self {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        KdfParams {
+            algorithm: KDF_ALGORITHM.to_string(),
+            memory,
+            iterations,
+            parallelism,
+            salt,
+        }
+    }
+
+    /// The default parameters with a freshly generated salt
+    pub fn recommended() -> Self {
+        KdfParams::new(
+            KdfParams::DEFAULT_MEMORY,
+            KdfParams::DEFAULT_ITERATIONS,
+            KdfParams::DEFAULT_PARALLELISM,
+        )
+    }
+
+    fn derive_key(&self, password: &str) -> Result<Zeroizing<[u8; KEY_LENGTH]>, KeystoreError> {
+        if self.algorithm != KDF_ALGORITHM {
+            return Err(KeystoreError::UnsupportedAlgorithm(self.algorithm.clone()));
+        }
+        let params = Params::new(self.memory, self.iterations, self.parallelism, Some(KEY_LENGTH))
+            .map_err(KeystoreError::InvalidKdfParams)?;
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &self.salt, key.as_mut())
+            .map_err(KeystoreError::InvalidKdfParams)?;
+        Ok(key)
+    }
+}
+
+/// A password encrypted Seed or Keypair suitable for storing at rest.
+///
+/// The secret is encrypted with XChaCha20-Poly1305 under a key derived from the password with
+/// Argon2id. The version, kind, kdf parameters and public key are authenticated as associated data
+/// so they cannot be altered without invalidating the keystore.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keystore {
+    pub version: u8,
+    pub kind: KeystoreKind,
+    /// The public key of a Keypair keystore, allowing the keystore to be identified without the password
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub public_key: Option<PublicKey>,
+    pub kdf: KdfParams,
+    pub cipher: String,
+    #[serde(with = "hex")]
+    pub nonce: Vec<u8>,
+    /// The encrypted secret followed by the Poly1305 tag
+    #[serde(with = "hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypt a seed with the default kdf parameters
+    pub fn encrypt_seed(seed: &Seed, password: &str) -> Result<Self, KeystoreError> {
+        Keystore::encrypt(
+            KeystoreKind::Seed,
+            None,
+            seed.as_bytes(),
+            password,
+            KdfParams::recommended(),
+        )
+    }
+
+    /// Encrypt a keypair with the default kdf parameters
+    pub fn encrypt_keypair(keypair: &Keypair, password: &str) -> Result<Self, KeystoreError> {
+        let private = keypair.private();
+        Keystore::encrypt(
+            KeystoreKind::Keypair,
+            Some(keypair.public()),
+            private.as_ref(),
+            password,
+            KdfParams::recommended(),
+        )
+    }
+
+    /// Encrypt `secret` with a freshly generated nonce. `kdf` should be created with `KdfParams::new`
+    /// so that each keystore has a unique salt.
+    pub fn encrypt(
+        kind: KeystoreKind,
+        public_key: Option<PublicKey>,
+        secret: &[u8],
+        password: &str,
+        kdf: KdfParams,
+    ) -> Result<Self, KeystoreError> {
+        let mut nonce = vec![0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut nonce);
+
+        let mut keystore = Keystore {
+            version: KEYSTORE_VERSION,
+            kind,
+            public_key,
+            kdf,
+            cipher: CIPHER_ALGORITHM.to_string(),
+            nonce,
+            ciphertext: Vec::new(),
+        };
+
+        let payload = Payload {
+            msg: secret,
+            aad: &keystore.aad(),
+        };
+        keystore.ciphertext = keystore
+            .cipher(password)?
+            .encrypt(XNonce::from_slice(&keystore.nonce), payload)
+            .map_err(|_| KeystoreError::Decrypt)?;
+        Ok(keystore)
+    }
+
+    /// Decrypt a seed keystore
+    pub fn decrypt_seed(&self, password: &str) -> Result<Seed, KeystoreError> {
+        let plaintext = self.decrypt(KeystoreKind::Seed, password)?;
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        bytes.copy_from_slice(&plaintext);
+        Ok(Seed::from_bytes(*bytes))
+    }
+
+    /// Decrypt a keypair keystore, checking the keypair against the stored public key
+    pub fn decrypt_keypair(&self, password: &str) -> Result<Keypair, KeystoreError> {
+        let plaintext = self.decrypt(KeystoreKind::Keypair, password)?;
+        let keypair = Keypair::from_private_bytes(&plaintext)?;
+        match &self.public_key {
+            Some(public_key) if *public_key != keypair.public() => {
+                Err(KeystoreError::PublicKeyMismatch(Box::new(public_key.clone())))
+            },
+            _ => Ok(keypair),
+        }
+    }
+
+    /// Decrypt the secret held by the keystore. The returned buffer is zeroized when dropped.
+    pub fn decrypt(&self, expected: KeystoreKind, password: &str) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+        if self.kind != expected {
+            return Err(KeystoreError::KindMismatch {
+                expected,
+                found: self.kind,
+            });
+        }
+        if self.cipher != CIPHER_ALGORITHM {
+            return Err(KeystoreError::UnsupportedAlgorithm(self.cipher.clone()));
+        }
+
+        if self.nonce.len() != NONCE_LENGTH {
+            return Err(KeystoreError::Decrypt);
+        }
+        let payload = Payload {
+            msg: &self.ciphertext,
+            aad: &self.aad(),
+        };
+        let buffer = Zeroizing::new(
+            self.cipher(password)?
+                .decrypt(XNonce::from_slice(&self.nonce), payload)
+                .map_err(|_| KeystoreError::Decrypt)?,
+        );
+
+        // both a Seed and a Keypair's private key are 32 bytes
+        if buffer.len() != KEY_LENGTH {
+            return Err(KeystoreError::PlaintextLength {
+                expected: KEY_LENGTH,
+                found: buffer.len(),
+            });
+        }
+        Ok(buffer)
+    }
+
+    fn cipher(&self, password: &str) -> Result<XChaCha20Poly1305, KeystoreError> {
+        let key = self.kdf.derive_key(password)?;
+        Ok(XChaCha20Poly1305::new(Key::from_slice(key.as_ref())))
+    }
+
+    /// Every field except the ciphertext is authenticated
+    fn aad(&self) -> Vec<u8> {
+        let public_key = self.public_key.as_ref().map(|pk| pk.to_string()).unwrap_or_default();
+        format!(
+            "sia-keystore|{}|{:?}|{}|{}|{}|{}|{}|{}|{}|{}",
+            self.version,
+            self.kind,
+            public_key,
+            self.kdf.algorithm,
+            self.kdf.memory,
+            self.kdf.iterations,
+            self.kdf.parallelism,
+            hex::encode(&self.kdf.salt),
+            self.cipher,
+            hex::encode(&self.nonce),
+        )
+        .into_bytes()
+    }
+
+    /// Export the keystore as versioned JSON
+    pub fn to_json(&self) -> Result<String, KeystoreError> { Ok(serde_json::to_string_pretty(self)?) }
+
+    /// Import a keystore previously exported with `to_json`
+    pub fn from_json(json: &str) -> Result<Self, KeystoreError> { Ok(serde_json::from_str(json)?) }
+
+    /// Write the keystore to `path` as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), KeystoreError> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+
+    /// Read a keystore from a JSON file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, KeystoreError> {
+        Keystore::from_json(&std::fs::read_to_string(path)?)
+    }
+}
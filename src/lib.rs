@@ -1,11 +1,16 @@
 #[macro_use]
 pub(crate) mod utils;
 
+pub mod atomic_swap;
 // TODO Alright - if this is truly "internal" it should not be public
 pub mod blake2b_internal;
+pub mod coin_selection;
 pub mod encoding;
+pub mod keystore;
+pub mod seed;
 pub mod transport;
 pub mod types;
+pub mod validation;
 
 #[cfg(test)] mod tests;
 #[cfg(test)]
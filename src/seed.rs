@@ -0,0 +1,269 @@
+use crate::encoding::Encoder;
+use crate::types::{Keypair, UnlockCondition};
+use blake2b_simd::Params;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// The BIP39 English wordlist, one word per line
+const BIP39_ENGLISH: &str = include_str!("seed/bip39_english.txt");
+
+/// The dictionary of legacy siad seed phrases, one word per line
+// FIXME verify against english.go of github.com/NebulousLabs/entropy-mnemonics
+const LEGACY_ENGLISH: &str = include_str!("seed/legacy_english.txt");
+
+/// Legacy words are identified by their first 3 characters
+const LEGACY_UNIQUE_PREFIX_LENGTH: usize = 3;
+const LEGACY_SEED_CHECKSUM_LENGTH: usize = 6;
+
+/// Sia seed phrases encode 128 bits of entropy plus a 4 bit checksum in 11 bit words
+const SEED_PHRASE_WORDS: usize = 12;
+const SEED_ENTROPY_LENGTH: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum SeedError {
+    #[error("Seed::from_phrase: expected {SEED_PHRASE_WORDS} words, found {0}")]
+    WordCount(usize),
+    #[error("Seed::from_phrase: unrecognized word {0:?}")]
+    UnknownWord(String),
+    #[error("Seed::from_phrase: invalid checksum")]
+    InvalidChecksum,
+    #[error("Seed::from_legacy_phrase: unrecognized word {0:?}")]
+    LegacyUnknownWord(String),
+    #[error("Seed::from_legacy_phrase: expected 38 bytes of seed and checksum, found {0}")]
+    LegacyLength(usize),
+    #[error("Seed::from_legacy_phrase: invalid checksum")]
+    LegacyInvalidChecksum,
+}
+
+fn bip39_words() -> impl Iterator<Item = &'static str> { BIP39_ENGLISH.lines() }
+
+fn bip39_index(word: &str) -> Option<usize> { bip39_words().position(|w| w == word) }
+
+/// Encode 128 bits of entropy as a 12 word BIP39 phrase
+pub fn entropy_to_phrase(entropy: &[u8; SEED_ENTROPY_LENGTH]) -> String {
+    let checksum = Sha256::digest(entropy)[0] >> 4;
+    let mut bits = u128::from_be_bytes(*entropy);
+    let words: Vec<&str> = bip39_words().collect();
+
+    let mut indices = [0usize; SEED_PHRASE_WORDS];
+    // the final word holds the low 7 bits of the entropy followed by the 4 bit checksum
+    indices[SEED_PHRASE_WORDS - 1] = (((bits & 0x7f) as usize) << 4) | checksum as usize;
+    bits >>= 7;
+    for index in indices[..SEED_PHRASE_WORDS - 1].iter_mut().rev() {
+        *index = (bits & 0x7ff) as usize;
+        bits >>= 11;
+    }
+    indices.iter().map(|i| words[*i]).collect::<Vec<_>>().join(" ")
+}
+
+/// Decode a 12 word BIP39 phrase to its 128 bits of entropy, validating the checksum
+pub fn phrase_to_entropy(phrase: &str) -> Result<[u8; SEED_ENTROPY_LENGTH], SeedError> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if words.len() != SEED_PHRASE_WORDS {
+        return Err(SeedError::WordCount(words.len()));
+    }
+
+    // 12 words * 11 bits = 132 bits, the 128 bits of entropy followed by the 4 bit checksum
+    let mut bits: u128 = 0;
+    let mut checksum: u8 = 0;
+    for (i, word) in words.iter().enumerate() {
+        let index = bip39_index(word).ok_or_else(|| SeedError::UnknownWord(word.to_string()))? as u128;
+        if i == SEED_PHRASE_WORDS - 1 {
+            bits = (bits << 7) | (index >> 4);
+            checksum = (index & 0xf) as u8;
+        } else {
+            bits = (bits << 11) | index;
+        }
+    }
+
+    let entropy = bits.to_be_bytes();
+    if Sha256::digest(&entropy)[0] >> 4 != checksum {
+        return Err(SeedError::InvalidChecksum);
+    }
+    Ok(entropy)
+}
+
+/// Generate a random 12 word seed phrase from the operating system's CSPRNG
+pub fn new_seed_phrase() -> String {
+    let mut entropy = [0u8; SEED_ENTROPY_LENGTH];
+    OsRng.fill_bytes(&mut entropy);
+    entropy_to_phrase(&entropy)
+}
+
+/// An unsigned integer of arbitrary size stored as little-endian u32 limbs.
+/// Only supports the operations required to convert between legacy phrases and bytes.
+struct LegacyInt(Vec<u32>);
+
+impl LegacyInt {
+    fn is_less_than(&self, n: u32) -> bool { self.0.iter().skip(1).all(|limb| *limb == 0) && self.0[0] < n }
+
+    fn mul_add(&mut self, m: u32, a: u32) {
+        let mut carry = a as u64;
+        for limb in self.0.iter_mut() {
+            let product = *limb as u64 * m as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    fn div_rem(&mut self, d: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.0.iter_mut().rev() {
+            let dividend = (rem << 32) | *limb as u64;
+            *limb = (dividend / d as u64) as u32;
+            rem = dividend % d as u64;
+        }
+        rem as u32
+    }
+
+    fn decrement(&mut self) {
+        for limb in self.0.iter_mut() {
+            let (value, borrow) = limb.overflowing_sub(1);
+            *limb = value;
+            if !borrow {
+                break;
+            }
+        }
+    }
+
+    /// Interpret `digits`, least significant first, as a number in bijective base `base` offset by -1.
+    /// This is the encoding used by entropy-mnemonics for both bytes and phrases.
+    fn from_digits(digits: &[u32], base: u32) -> Self {
+        let mut n = LegacyInt(vec![0]);
+        for digit in digits.iter().rev() {
+            n.mul_add(base, digit + 1);
+        }
+        n.decrement();
+        n
+    }
+
+    /// The inverse of `from_digits`
+    fn into_digits(mut self, base: u32) -> Vec<u32> {
+        let mut digits = Vec::new();
+        while !self.is_less_than(base) {
+            digits.push(self.div_rem(base));
+            self.decrement();
+        }
+        digits.push(self.0[0]);
+        digits
+    }
+}
+
+fn legacy_checksum(seed: &[u8; 32]) -> [u8; LEGACY_SEED_CHECKSUM_LENGTH] {
+    let hash = Params::new().hash_length(32).hash(seed);
+    let mut checksum = [0u8; LEGACY_SEED_CHECKSUM_LENGTH];
+    checksum.copy_from_slice(&hash.as_bytes()[..LEGACY_SEED_CHECKSUM_LENGTH]);
+    checksum
+}
+
+/// A wallet seed from which any number of keypairs can be derived.
+/// Ported from walletd:
+/// <https://github.com/SiaFoundation/walletd/blob/d71cf08d4579ba952c51e535f988000e43ed8722/wallet/seed.go>
+pub struct Seed([u8; 32]);
+
+impl Drop for Seed {
+    fn drop(&mut self) { self.0.zeroize() }
+}
+
+impl std::fmt::Debug for Seed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str("Seed(<redacted>)") }
+}
+
+impl Seed {
+    /// Restore the seed encoded by a 12 word BIP39 phrase as generated by walletd or `new_seed_phrase`
+    pub fn from_phrase(phrase: &str) -> Result<Self, SeedError> { Ok(Seed::from_entropy(&phrase_to_entropy(phrase)?)) }
+
+    /// The seed is the blake2b hash of the phrase's entropy. Unlike BIP39, no PBKDF2 stretching is applied.
+    pub fn from_entropy(entropy: &[u8; SEED_ENTROPY_LENGTH]) -> Self {
+        let hash = Params::new().hash_length(32).hash(entropy);
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(hash.as_bytes());
+        Seed(seed)
+    }
+
+    pub(crate) fn from_bytes(bytes: [u8; 32]) -> Self { Seed(bytes) }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; 32] { &self.0 }
+
+    /// Derive the keypair at `index`, equivalent to walletd's `KeyFromSeed`.
+    /// The ed25519 private key is blake2b(seed | index) where index is encoded as a little-endian u64.
+    pub fn keypair(&self, index: u64) -> Keypair {
+        let mut encoder = Encoder::default();
+        encoder.write_slice(&self.0);
+        encoder.write_u64(index);
+        Keypair::from_private_bytes(&encoder.hash().0).expect("blake2b hash is a valid ed25519 private key")
+    }
+
+    /// The standard v1 unlock condition of the keypair at `index`. Legacy siad wallets received funds
+    /// at the addresses of these unlock conditions.
+    pub fn unlock_condition(&self, index: u64) -> UnlockCondition {
+        UnlockCondition::standard_unlock(self.keypair(index).public())
+    }
+
+    /// Restore a seed from the 28 or 29 word phrase generated by siad.
+    /// The phrase encodes the 32 byte seed followed by the first 6 bytes of its blake2b hash.
+    /// Words are matched by their first 3 characters as siad does; shorter words are rejected.
+    /// Ported from siad and entropy-mnemonics:
+    /// <https://github.com/SiaFoundation/siad/blob/master/modules/wallet/seed.go>
+    pub fn from_legacy_phrase(phrase: &str) -> Result<Self, SeedError> {
+        let dictionary: Vec<&str> = LEGACY_ENGLISH.lines().collect();
+        let digits = phrase
+            .split_whitespace()
+            .map(|word| {
+                // entropy-mnemonics rejects words shorter than the unique prefix
+                if word.chars().count() < LEGACY_UNIQUE_PREFIX_LENGTH {
+                    return Err(SeedError::LegacyUnknownWord(word.to_string()));
+                }
+                let prefix: String = word.chars().take(LEGACY_UNIQUE_PREFIX_LENGTH).collect();
+                dictionary
+                    .iter()
+                    .position(|w| w.starts_with(&prefix))
+                    .map(|index| index as u32)
+                    .ok_or_else(|| SeedError::LegacyUnknownWord(word.to_string()))
+            })
+            .collect::<Result<Vec<u32>, SeedError>>()?;
+        if digits.is_empty() {
+            return Err(SeedError::LegacyLength(0));
+        }
+
+        let bytes: Vec<u8> = LegacyInt::from_digits(&digits, dictionary.len() as u32)
+            .into_digits(256)
+            .into_iter()
+            .map(|byte| byte as u8)
+            .collect();
+        if bytes.len() != 32 + LEGACY_SEED_CHECKSUM_LENGTH {
+            return Err(SeedError::LegacyLength(bytes.len()));
+        }
+
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&bytes[..32]);
+        if bytes[32..] != legacy_checksum(&seed) {
+            return Err(SeedError::LegacyInvalidChecksum);
+        }
+        Ok(Seed(seed))
+    }
+
+    /// Encode the seed as a legacy siad phrase. Only seeds restored with `from_legacy_phrase` have
+    /// a phrase that siad can import.
+    pub fn to_legacy_phrase(&self) -> String {
+        let dictionary: Vec<&str> = LEGACY_ENGLISH.lines().collect();
+        let digits: Vec<u32> = self
+            .0
+            .iter()
+            .chain(legacy_checksum(&self.0).iter())
+            .map(|byte| *byte as u32)
+            .collect();
+        LegacyInt::from_digits(&digits, 256)
+            .into_digits(dictionary.len() as u32)
+            .into_iter()
+            .map(|index| dictionary[index as usize])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
@@ -0,0 +1,685 @@
+#[cfg(test)]
+mod test {
+    use crate::atomic_swap::{AtomicSwap, AtomicSwapError, RefundWatcher, RefundWatcherError, SwapParams, SwapState};
+    use crate::types::{Currency, Hash256, Keypair, Preimage, RefundLock, SiacoinElement, SiacoinOutputId, SpendPolicy,
+                       StateElement, V2Transaction, V2TransactionBuilder};
+    use crate::validation::{validate_v2_transaction, ValidationContext};
+
+    const LOCK_TIME: u64 = 1_700_000_000;
+
+    fn test_keypair(seed: u8) -> Keypair {
+        let mut private = [0u8; 32];
+        private[0] = seed;
+        Keypair::from_private_bytes(&private).unwrap()
+    }
+
+    fn test_params(secret: &Preimage) -> SwapParams {
+        SwapParams {
+            success_public_key: test_keypair(1).public(),
+            refund_public_key: test_keypair(2).public(),
+            refund_lock: RefundLock::Time(LOCK_TIME),
+            secret_hash: secret.compute_hash(),
+            amount: Currency::COIN,
+        }
+    }
+
+    /// a signed transaction paying the HTLC output of `params` from the refund party's address
+    fn test_funding_tx(params: &SwapParams) -> V2Transaction {
+        let funder = test_keypair(2);
+        let parent = SiacoinElement {
+            id: SiacoinOutputId(Hash256([7u8; 32])),
+            state_element: StateElement {
+                leaf_index: 7,
+                merkle_proof: vec![],
+            },
+            siacoin_output: (
+                funder.public().address(),
+                Currency(*params.amount + *Currency::DEFAULT_FEE),
+            )
+                .into(),
+            maturity_height: 0,
+        };
+        let mut builder = params.funding_builder();
+        builder
+            .add_siacoin_input(parent, SpendPolicy::PublicKey(funder.public()))
+            .miner_fee(Currency::DEFAULT_FEE)
+            .sign_simple(vec![&funder]);
+        builder.build()
+    }
+
+    /// the element created by the HTLC output of a funded swap
+    fn test_htlc_element(swap: &AtomicSwap) -> SiacoinElement {
+        SiacoinElement {
+            id: swap.htlc_output_id().unwrap().clone(),
+            state_element: StateElement {
+                leaf_index: 8,
+                merkle_proof: vec![],
+            },
+            siacoin_output: swap.params.htlc_output(),
+            maturity_height: 0,
+        }
+    }
+
+    cross_target_tests! {
+        fn test_atomic_swap_redeem() {
+            let secret = Preimage([9u8; 32]);
+            let params = test_params(&secret);
+            let funding_tx = test_funding_tx(&params);
+            validate_v2_transaction(&funding_tx, &ValidationContext::new(0, 0)).unwrap();
+
+            let mut swap = AtomicSwap::new(params);
+            assert_eq!(swap.htlc_output_id(), None);
+            swap.funded(&funding_tx).unwrap();
+            assert_eq!(
+                swap.state,
+                SwapState::Funded {
+                    funding_txid: funding_tx.txid(),
+                    htlc_output_id: SiacoinOutputId::new(funding_tx.txid(), 0),
+                }
+            );
+
+            let destination = test_keypair(1).public().address();
+            let htlc = test_htlc_element(&swap);
+            let redeem_tx = swap
+                .redeem_transaction(htlc, &test_keypair(1), secret, &destination, Currency::DEFAULT_FEE)
+                .unwrap();
+            validate_v2_transaction(&redeem_tx, &ValidationContext::new(0, 0)).unwrap();
+            assert_eq!(redeem_tx.siacoin_outputs[0].address, destination);
+
+            swap.spent(&redeem_tx).unwrap();
+            assert_eq!(
+                swap.state,
+                SwapState::Redeemed {
+                    htlc_output_id: SiacoinOutputId::new(funding_tx.txid(), 0),
+                    txid: redeem_tx.txid(),
+                }
+            );
+            assert!(swap.is_complete());
+        }
+
+        fn test_atomic_swap_refund() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+
+            let destination = test_keypair(2).public().address();
+            let refund_tx = swap
+                .refund_transaction(test_htlc_element(&swap), &test_keypair(2), &destination, Currency::DEFAULT_FEE)
+                .unwrap();
+            assert!(validate_v2_transaction(&refund_tx, &ValidationContext::new(0, LOCK_TIME)).is_err());
+            validate_v2_transaction(&refund_tx, &ValidationContext::new(0, LOCK_TIME + 1)).unwrap();
+
+            swap.spent(&refund_tx).unwrap();
+            assert!(matches!(swap.state, SwapState::Refunded { .. }));
+        }
+
+        fn test_atomic_swap_persist() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+
+            let json = serde_json::to_value(&swap).unwrap();
+            assert_eq!(json["state"]["state"], "funded");
+            let resumed: AtomicSwap = serde_json::from_value(json).unwrap();
+            assert_eq!(resumed, swap);
+        }
+
+        fn test_atomic_swap_persist_legacy_lock_time() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+
+            // swaps persisted before refund locks were added have a lockTime timestamp
+            let mut json = serde_json::to_value(&swap).unwrap();
+            let params = json["params"].as_object_mut().unwrap();
+            assert_eq!(params.remove("refundLock").unwrap(), json!({ "time": LOCK_TIME }));
+            params.insert("lockTime".to_string(), json!(LOCK_TIME));
+            let resumed: AtomicSwap = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(resumed, swap);
+
+            // a swap cannot have both
+            json["params"]["refundLock"] = json!({ "height": 100 });
+            assert!(serde_json::from_value::<AtomicSwap>(json.clone()).is_err());
+
+            // or neither
+            json["params"].as_object_mut().unwrap().remove("refundLock");
+            json["params"].as_object_mut().unwrap().remove("lockTime");
+            assert!(serde_json::from_value::<AtomicSwap>(json).is_err());
+        }
+
+        fn test_atomic_swap_funding_mismatch() {
+            let secret = Preimage([9u8; 32]);
+            let params = test_params(&secret);
+            let funding_tx = test_funding_tx(&params);
+
+            // the counterparty funded the HTLC with a different lock time
+            let mut swap = AtomicSwap::new(SwapParams {
+                refund_lock: RefundLock::Time(LOCK_TIME + 1),
+                ..params.clone()
+            });
+            match swap.funded(&funding_tx) {
+                Err(AtomicSwapError::HtlcOutputNotFound { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            // or with less than the agreed amount
+            let mut swap = AtomicSwap::new(SwapParams {
+                amount: Currency(*params.amount + 1),
+                ..params.clone()
+            });
+            match swap.funded(&funding_tx) {
+                Err(AtomicSwapError::HtlcAmountMismatch { expected, found, .. }) => {
+                    assert_eq!(expected, Currency(*params.amount + 1));
+                    assert_eq!(found, params.amount);
+                },
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert_eq!(swap.state, SwapState::Created);
+        }
+
+        fn test_atomic_swap_invalid_spend() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            let destination = test_keypair(1).public().address();
+
+            let fee = Currency::DEFAULT_FEE;
+            let unfunded_htlc = SiacoinElement {
+                id: SiacoinOutputId::default(),
+                state_element: StateElement {
+                    leaf_index: 8,
+                    merkle_proof: vec![],
+                },
+                siacoin_output: swap.params.htlc_output(),
+                maturity_height: 0,
+            };
+            match swap.refund_transaction(unfunded_htlc, &test_keypair(2), &destination, fee) {
+                Err(AtomicSwapError::NotFunded(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+            let htlc = test_htlc_element(&swap);
+
+            match swap.redeem_transaction(htlc.clone(), &test_keypair(1), Preimage([8u8; 32]), &destination, fee) {
+                Err(AtomicSwapError::SecretMismatch(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match swap.redeem_transaction(htlc.clone(), &test_keypair(2), secret, &destination, fee) {
+                Err(AtomicSwapError::PublicKeyMismatch { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match swap.refund_transaction(htlc.clone(), &test_keypair(2), &destination, Currency::COIN) {
+                Err(AtomicSwapError::InsufficientValue { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            let other_htlc = SiacoinElement {
+                id: SiacoinOutputId(Hash256([1u8; 32])),
+                ..htlc
+            };
+            match swap.refund_transaction(other_htlc, &test_keypair(2), &destination, fee) {
+                Err(AtomicSwapError::HtlcOutputMismatch { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            // a transaction that does not spend the HTLC
+            match swap.spent(&V2TransactionBuilder::new().build()) {
+                Err(AtomicSwapError::SpendNotFound { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(!swap.is_complete());
+        }
+
+        fn test_atomic_swap_spent_any_policy() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+            let htlc = test_htlc_element(&swap);
+            let fee = Currency::DEFAULT_FEE;
+
+            // a redeem revealing the full HTLC policy rather than opacifying the refund path
+            let destination = test_keypair(1).public().address();
+            let mut redeem_tx = swap
+                .redeem_transaction(htlc.clone(), &test_keypair(1), secret, &destination, fee)
+                .unwrap();
+            redeem_tx.siacoin_inputs[0].satisfied_policy.policy = swap.params.policy();
+            let mut redeemed = swap.clone();
+            redeemed.spent(&redeem_tx).unwrap();
+            assert!(matches!(redeemed.state, SwapState::Redeemed { .. }));
+
+            // without a preimage of the secret hash the spend is a refund
+            let destination = test_keypair(2).public().address();
+            let mut refund_tx = swap
+                .refund_transaction(htlc, &test_keypair(2), &destination, fee)
+                .unwrap();
+            refund_tx.siacoin_inputs[0].satisfied_policy.policy = swap.params.policy();
+            refund_tx.siacoin_inputs[0].satisfied_policy.preimages = vec![Preimage([8u8; 32])];
+            let mut refunded = swap.clone();
+            refunded.spent(&refund_tx).unwrap();
+            assert!(matches!(refunded.state, SwapState::Refunded { .. }));
+
+            // a policy of a different address cannot have spent the HTLC
+            refund_tx.siacoin_inputs[0].satisfied_policy.policy = SpendPolicy::PublicKey(test_keypair(2).public());
+            match swap.spent(&refund_tx) {
+                Err(AtomicSwapError::UnknownSpendPath(txid)) => assert_eq!(txid, refund_tx.txid()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(!swap.is_complete());
+        }
+
+        fn test_atomic_swap_extract_secret() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+            let htlc = test_htlc_element(&swap);
+            let htlc_output_id = htlc.id.clone();
+            let fee = Currency::DEFAULT_FEE;
+
+            let destination = test_keypair(1).public().address();
+            let redeem_tx = swap
+                .redeem_transaction(htlc.clone(), &test_keypair(1), secret.clone(), &destination, fee)
+                .unwrap();
+            assert_eq!(swap.params.extract_secret(&redeem_tx, &htlc_output_id).unwrap(), secret);
+
+            // the secret is found whichever policy the HTLC input revealed
+            let mut full_policy_tx = redeem_tx.clone();
+            full_policy_tx.siacoin_inputs[0].satisfied_policy.policy = swap.params.policy();
+            assert_eq!(swap.params.extract_secret(&full_policy_tx, &htlc_output_id).unwrap(), secret);
+
+            // but only in the input spending the HTLC output
+            let other_output_id = SiacoinOutputId(Hash256([1u8; 32]));
+            match swap.params.extract_secret(&redeem_tx, &other_output_id) {
+                Err(AtomicSwapError::SpendNotFound { txid, output_id }) => {
+                    assert_eq!(txid, redeem_tx.txid());
+                    assert_eq!(output_id, other_output_id);
+                },
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            // a refund reveals no secret
+            let destination = test_keypair(2).public().address();
+            let refund_tx = swap
+                .refund_transaction(htlc, &test_keypair(2), &destination, fee)
+                .unwrap();
+            match swap.params.extract_secret(&refund_tx, &htlc_output_id) {
+                Err(AtomicSwapError::SecretNotRevealed { txid, .. }) => assert_eq!(txid, refund_tx.txid()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            // the revealed preimage must match the secret hash
+            let mut forged_tx = redeem_tx;
+            forged_tx.siacoin_inputs[0].satisfied_policy.preimages = vec![Preimage([8u8; 32])];
+            match swap.params.extract_secret(&forged_tx, &htlc_output_id) {
+                Err(AtomicSwapError::SecretNotRevealed { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_atomic_swap_find_htlc_output() {
+            let secret = Preimage([9u8; 32]);
+            let params = test_params(&secret);
+
+            // the HTLC output follows an output paying a different amount to the HTLC address
+            let mut builder = V2TransactionBuilder::new();
+            builder
+                .add_siacoin_output((params.address(), Currency::DEFAULT_FEE).into())
+                .add_siacoin_output(params.htlc_output());
+            let tx = builder.build();
+            assert_eq!(params.find_htlc_output(&tx).unwrap(), SiacoinOutputId::new(tx.txid(), 1));
+
+            match params.find_htlc_output(&V2TransactionBuilder::new().build()) {
+                Err(AtomicSwapError::HtlcOutputNotFound { address, .. }) => assert_eq!(address, params.address()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_atomic_swap_height_refund() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(SwapParams {
+                refund_lock: RefundLock::Height(100),
+                ..test_params(&secret)
+            });
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+
+            let destination = test_keypair(2).public().address();
+            let refund_tx = swap
+                .refund_transaction(test_htlc_element(&swap), &test_keypair(2), &destination, Currency::DEFAULT_FEE)
+                .unwrap();
+            assert!(validate_v2_transaction(&refund_tx, &ValidationContext::new(99, u64::MAX)).is_err());
+            validate_v2_transaction(&refund_tx, &ValidationContext::new(100, 0)).unwrap();
+
+            swap.spent(&refund_tx).unwrap();
+            assert!(matches!(swap.state, SwapState::Refunded { .. }));
+        }
+
+        fn test_refund_watcher_watch() {
+            let secret = Preimage([9u8; 32]);
+            let mut swap = AtomicSwap::new(test_params(&secret));
+            let destination = test_keypair(2).public().address();
+            let mut watcher = RefundWatcher::new(Currency::DEFAULT_FEE);
+
+            match watcher.watch(swap.clone(), test_keypair(2), destination.clone(), 0) {
+                Err(RefundWatcherError::NotFunded(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+            // only the refund key can refund the HTLC
+            match watcher.watch(swap.clone(), test_keypair(1), destination.clone(), 0) {
+                Err(RefundWatcherError::Swap(AtomicSwapError::PublicKeyMismatch { .. })) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(watcher.is_empty());
+
+            watcher.watch(swap.clone(), test_keypair(2), destination.clone(), 0).unwrap();
+            match watcher.watch(swap.clone(), test_keypair(2), destination, 0) {
+                Err(RefundWatcherError::AlreadyWatched(id)) => assert_eq!(Some(&id), swap.htlc_output_id()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert_eq!(watcher.swaps().collect::<Vec<_>>(), vec![&swap]);
+        }
+    }
+
+    /// The watcher and the swap helpers of ApiClientHelpers are exercised against a stub client serving
+    /// canned chain state
+    #[cfg(not(target_arch = "wasm32"))]
+    mod stub_client {
+        use super::*;
+        use crate::atomic_swap::RefundWatcherEvent;
+        use crate::tests::stub_client::StubClient;
+        use crate::transport::client::{ApiClientHelpers, FindSwapSecretError, HelperError, ValidateSwapFundingError};
+
+        /// a funded swap and a client whose chain holds its unspent HTLC output at height 100
+        fn test_funded_swap() -> (AtomicSwap, StubClient) {
+            let params = test_params(&Preimage([9u8; 32]));
+            let mut swap = AtomicSwap::new(params);
+            swap.funded(&test_funding_tx(&swap.params)).unwrap();
+
+            let client = StubClient::default();
+            {
+                let mut chain = client.chain();
+                chain.height = 100;
+                chain.median_timestamp = LOCK_TIME;
+                chain.utxos.push(test_htlc_element(&swap));
+            }
+            (swap, client)
+        }
+
+        async fn poll(watcher: &mut RefundWatcher, client: &StubClient) -> Vec<RefundWatcherEvent> {
+            let mut events = Vec::new();
+            watcher.poll(client, &mut |event| events.push(event)).await.unwrap();
+            events
+        }
+
+        #[tokio::test]
+        async fn test_validate_swap_funding() {
+            let params = test_params(&Preimage([9u8; 32]));
+            let funding_tx = test_funding_tx(&params);
+            let client = StubClient::default();
+
+            // the funding transaction is only in the mempool
+            client.broadcast_transaction(&funding_tx).await.unwrap();
+            match client.validate_swap_funding_tx(&params, &funding_tx).await {
+                Err(HelperError::ValidateSwapFunding(ValidateSwapFundingError::NotConfirmed(id))) => {
+                    assert_eq!(id, SiacoinOutputId::new(funding_tx.txid(), 0))
+                },
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            client.chain().confirm(funding_tx.clone());
+            let htlc = client.validate_swap_funding(&params, &funding_tx.txid()).await.unwrap();
+            assert_eq!(htlc.id, SiacoinOutputId::new(funding_tx.txid(), 0));
+            assert_eq!(htlc.siacoin_output, params.htlc_output());
+
+            // the counterparty agreed to fund more than they paid
+            let params = SwapParams {
+                amount: Currency(*params.amount + 1),
+                ..params
+            };
+            match client.validate_swap_funding(&params, &funding_tx.txid()).await {
+                Err(HelperError::ValidateSwapFunding(ValidateSwapFundingError::Mismatch(
+                    AtomicSwapError::HtlcAmountMismatch { expected, found, .. },
+                ))) => {
+                    assert_eq!(expected, params.amount);
+                    assert_eq!(*found + 1, *params.amount);
+                },
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        #[tokio::test]
+        async fn test_find_swap_secret_mempool() {
+            let secret = Preimage([9u8; 32]);
+            let (swap, client) = test_funded_swap();
+            let htlc_output_id = swap.htlc_output_id().unwrap().clone();
+            let secret_found = client
+                .find_swap_secret(&swap.params, &htlc_output_id, 100)
+                .await
+                .unwrap();
+            assert_eq!(secret_found, None);
+
+            // the secret is available as soon as the redeem is broadcast
+            let redeem_tx = swap
+                .redeem_transaction(
+                    test_htlc_element(&swap),
+                    &test_keypair(1),
+                    secret.clone(),
+                    &test_keypair(1).public().address(),
+                    Currency::DEFAULT_FEE,
+                )
+                .unwrap();
+            client.broadcast_transaction(&redeem_tx).await.unwrap();
+            let secret_found = client
+                .find_swap_secret(&swap.params, &htlc_output_id, 100)
+                .await
+                .unwrap();
+            assert_eq!(secret_found, Some(secret));
+        }
+
+        #[tokio::test]
+        async fn test_find_swap_secret_confirmed() {
+            let secret = Preimage([9u8; 32]);
+            let (swap, client) = test_funded_swap();
+            let htlc_output_id = swap.htlc_output_id().unwrap().clone();
+
+            // the redeem is found by scanning the chain once it has left the mempool
+            let redeem_tx = swap
+                .redeem_transaction(
+                    test_htlc_element(&swap),
+                    &test_keypair(1),
+                    secret.clone(),
+                    &test_keypair(1).public().address(),
+                    Currency::DEFAULT_FEE,
+                )
+                .unwrap();
+            client.chain().confirm(redeem_tx);
+            assert!(client.chain().txpool.is_empty());
+            let secret_found = client
+                .find_swap_secret(&swap.params, &htlc_output_id, 100)
+                .await
+                .unwrap();
+            assert_eq!(secret_found, Some(secret));
+        }
+
+        #[tokio::test]
+        async fn test_find_swap_secret_refunded() {
+            let (swap, client) = test_funded_swap();
+            let htlc_output_id = swap.htlc_output_id().unwrap().clone();
+            let refund_tx = swap
+                .refund_transaction(
+                    test_htlc_element(&swap),
+                    &test_keypair(2),
+                    &test_keypair(2).public().address(),
+                    Currency::DEFAULT_FEE,
+                )
+                .unwrap();
+            client.chain().confirm(refund_tx.clone());
+
+            match client.find_swap_secret(&swap.params, &htlc_output_id, 100).await {
+                Err(HelperError::FindSwapSecret(FindSwapSecretError::ExtractSecret(
+                    AtomicSwapError::SecretNotRevealed { txid, .. },
+                ))) => assert_eq!(txid, refund_tx.txid()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        #[tokio::test]
+        async fn test_refund_watcher_refund() {
+            let (swap, client) = test_funded_swap();
+            let htlc_output_id = swap.htlc_output_id().unwrap().clone();
+            let destination = test_keypair(2).public().address();
+            let mut watcher = RefundWatcher::new(Currency::DEFAULT_FEE);
+            watcher.watch(swap, test_keypair(2), destination.clone(), 100).unwrap();
+
+            // the refund lock requires a median timestamp after LOCK_TIME
+            let events = poll(&mut watcher, &client).await;
+            match events.as_slice() {
+                [RefundWatcherEvent::Waiting {
+                    htlc_output_id: id,
+                    height: 101,
+                    median_timestamp: LOCK_TIME,
+                }] => {
+                    assert_eq!(*id, htlc_output_id)
+                },
+                other => panic!("unexpected events: {:?}", other),
+            }
+            assert!(client.chain().broadcast.is_empty());
+
+            client.chain().median_timestamp = LOCK_TIME + 1;
+            let refund_txid = match poll(&mut watcher, &client).await.as_slice() {
+                [RefundWatcherEvent::RefundBroadcast {
+                    htlc_output_id: id,
+                    txid,
+                }] => {
+                    assert_eq!(*id, htlc_output_id);
+                    txid.clone()
+                },
+                other => panic!("unexpected events: {:?}", other),
+            };
+            let refund_tx = client.chain().broadcast[0].clone();
+            assert_eq!(refund_tx.txid(), refund_txid);
+            assert_eq!(refund_tx.siacoin_inputs[0].parent.id, htlc_output_id);
+            assert_eq!(refund_tx.siacoin_outputs[0].address, destination);
+            validate_v2_transaction(&refund_tx, &ValidationContext::new(101, LOCK_TIME + 1)).unwrap();
+
+            // the refund is not rebroadcast while it is in the mempool
+            assert!(poll(&mut watcher, &client).await.is_empty());
+            assert_eq!(client.chain().broadcast.len(), 1);
+
+            // but is once it drops out
+            client.chain().txpool.clear();
+            match poll(&mut watcher, &client).await.as_slice() {
+                [RefundWatcherEvent::RefundBroadcast { txid, .. }] => assert_eq!(*txid, refund_txid),
+                other => panic!("unexpected events: {:?}", other),
+            }
+            assert_eq!(client.chain().broadcast.len(), 2);
+
+            client.chain().confirm(refund_tx);
+            match poll(&mut watcher, &client).await.as_slice() {
+                [RefundWatcherEvent::Completed(swap)] => assert_eq!(swap.state, SwapState::Refunded {
+                    htlc_output_id,
+                    txid: refund_txid
+                }),
+                other => panic!("unexpected events: {:?}", other),
+            }
+            assert!(watcher.is_empty());
+            assert_eq!(client.chain().broadcast.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_refund_watcher_height_lock() {
+            let (swap, client) = test_funded_swap();
+            let params = SwapParams {
+                refund_lock: RefundLock::Height(105),
+                ..swap.params.clone()
+            };
+            let mut swap = AtomicSwap::new(params);
+            let funding_tx = test_funding_tx(&swap.params);
+            swap.funded(&funding_tx).unwrap();
+            client.chain().utxos = vec![test_htlc_element(&swap)];
+
+            let mut watcher = RefundWatcher::new(Currency::DEFAULT_FEE);
+            watcher
+                .watch(swap, test_keypair(2), test_keypair(2).public().address(), 100)
+                .unwrap();
+
+            // the refund is expected in the child of the tip, so it is broadcast once the tip is 104
+            for _ in 100..104 {
+                match poll(&mut watcher, &client).await.as_slice() {
+                    [RefundWatcherEvent::Waiting { .. }] => (),
+                    other => panic!("unexpected events: {:?}", other),
+                }
+                assert!(client.chain().broadcast.is_empty());
+                client.chain().height += 1;
+            }
+            match poll(&mut watcher, &client).await.as_slice() {
+                [RefundWatcherEvent::RefundBroadcast { .. }] => (),
+                other => panic!("unexpected events: {:?}", other),
+            }
+            validate_v2_transaction(&client.chain().broadcast[0], &ValidationContext::new(105, LOCK_TIME)).unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_refund_watcher_redeemed() {
+            let secret = Preimage([9u8; 32]);
+            let (swap, client) = test_funded_swap();
+            let mut watcher = RefundWatcher::new(Currency::DEFAULT_FEE);
+            watcher
+                .watch(swap.clone(), test_keypair(2), test_keypair(2).public().address(), 100)
+                .unwrap();
+
+            // the counterparty redeems before the refund lock expires
+            let redeem_tx = swap
+                .redeem_transaction(
+                    test_htlc_element(&swap),
+                    &test_keypair(1),
+                    secret,
+                    &test_keypair(1).public().address(),
+                    Currency::DEFAULT_FEE,
+                )
+                .unwrap();
+            client.chain().confirm(redeem_tx.clone());
+            client.chain().median_timestamp = LOCK_TIME + 1;
+
+            match poll(&mut watcher, &client).await.as_slice() {
+                [RefundWatcherEvent::Completed(swap)] => match &swap.state {
+                    SwapState::Redeemed { txid, .. } => assert_eq!(*txid, redeem_tx.txid()),
+                    state => panic!("unexpected state: {:?}", state),
+                },
+                other => panic!("unexpected events: {:?}", other),
+            }
+            assert!(watcher.is_empty());
+            assert!(client.chain().broadcast.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_refund_watcher_unknown_spend() {
+            let (swap, client) = test_funded_swap();
+            let mut watcher = RefundWatcher::new(Currency::DEFAULT_FEE);
+            watcher
+                .watch(swap.clone(), test_keypair(2), test_keypair(2).public().address(), 100)
+                .unwrap();
+
+            // the node reports a spend whose policy does not have the HTLC address
+            let mut spend_tx = swap
+                .refund_transaction(
+                    test_htlc_element(&swap),
+                    &test_keypair(2),
+                    &test_keypair(2).public().address(),
+                    Currency::DEFAULT_FEE,
+                )
+                .unwrap();
+            spend_tx.siacoin_inputs[0].satisfied_policy.policy = SpendPolicy::PublicKey(test_keypair(2).public());
+            client.chain().confirm(spend_tx.clone());
+
+            match poll(&mut watcher, &client).await.as_slice() {
+                [RefundWatcherEvent::UnknownSpend { swap: spent, txid }] => {
+                    assert_eq!(*txid, spend_tx.txid());
+                    assert_eq!(spent.state, swap.state);
+                },
+                other => panic!("unexpected events: {:?}", other),
+            }
+            assert!(watcher.is_empty());
+            assert!(client.chain().broadcast.is_empty());
+        }
+    }
+}
@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod test {
+    use crate::coin_selection::{select_siafunds, BranchAndBound, CoinSelectionError, CoinSelector, Consolidate,
+                                LargestFirst, RandomImprove, Selection, SelectionParams, SmallestFirst};
+    use crate::types::{Currency, FeePolicy, Hash256, SiacoinElement, SiacoinOutput, SiacoinOutputId, SiafundElement,
+                       SiafundOutput, SiafundOutputId, SpendPolicy, StateElement, V2TransactionBuilder};
+    use std::str::FromStr;
+
+    fn test_utxo(index: u8, value: u128) -> SiacoinElement {
+        let mut id = [0u8; 32];
+        id[0] = index;
+        SiacoinElement {
+            id: SiacoinOutputId(Hash256(id)),
+            state_element: StateElement {
+                leaf_index: index as u64,
+                merkle_proof: vec![],
+            },
+            siacoin_output: SiacoinOutput {
+                value: Currency(value),
+                address: SpendPolicy::anyone_can_spend().address(),
+            },
+            maturity_height: 0,
+        }
+    }
+
+    fn test_utxos(values: &[u128]) -> Vec<SiacoinElement> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| test_utxo(i as u8, *value))
+            .collect()
+    }
+
+    fn test_params(target: u128) -> SelectionParams {
+        SelectionParams {
+            target: Currency(target),
+            base_fee: Currency(10),
+            input_fee: Currency(5),
+            change_fee: Currency(3),
+            min_change: Currency(1),
+        }
+    }
+
+    fn values(selection: &Selection) -> Vec<u128> {
+        selection.inputs.iter().map(|utxo| *utxo.siacoin_output.value).collect()
+    }
+
+    fn assert_balanced(selection: &Selection, params: &SelectionParams) {
+        let total: u128 = values(selection).iter().sum();
+        assert_eq!(total, *params.target + *selection.fee + *selection.change);
+    }
+
+    cross_target_tests! {
+        fn test_largest_first() {
+            let params = test_params(100);
+            let selection = LargestFirst.select(test_utxos(&[30, 80, 50, 4]), &params).unwrap();
+            assert_eq!(values(&selection), vec![80, 50]);
+            // base fee 10, 2 inputs 10, change output 3
+            assert_eq!(selection.fee, Currency(23));
+            assert_eq!(selection.change, Currency(7));
+            assert_balanced(&selection, &params);
+        }
+
+        fn test_smallest_first_skips_uneconomical() {
+            let params = test_params(50);
+            let selection = SmallestFirst.select(test_utxos(&[30, 80, 5, 50]), &params).unwrap();
+            // the 5 hasting utxo costs as much as it is worth
+            assert_eq!(values(&selection), vec![30, 50]);
+            assert_balanced(&selection, &params);
+        }
+
+        fn test_insufficient_funds() {
+            let params = test_params(100);
+            match LargestFirst.select(test_utxos(&[30, 50]), &params) {
+                Err(CoinSelectionError::InsufficientFunds { available, required }) => {
+                    assert_eq!(available, Currency(70));
+                    assert_eq!(required, Currency(110));
+                },
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_branch_and_bound_changeless() {
+            let params = test_params(100);
+            // 65 + 55 - 10 input fees covers 100 + 10 base fee exactly
+            let selection = BranchAndBound::default()
+                .select(test_utxos(&[80, 65, 200, 55, 31]), &params)
+                .unwrap();
+            let mut selected = values(&selection);
+            selected.sort_unstable();
+            assert_eq!(selected, vec![55, 65]);
+            assert_eq!(selection.change, Currency::ZERO);
+            assert_balanced(&selection, &params);
+        }
+
+        fn test_branch_and_bound_no_solution() {
+            let params = test_params(100);
+            match BranchAndBound::default().select(test_utxos(&[200, 300]), &params) {
+                Err(CoinSelectionError::NoChangelessSolution) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_random_improve() {
+            let params = test_params(100);
+            let utxos = test_utxos(&[20, 25, 30, 35, 40, 45, 50, 55, 60, 65, 70]);
+            for seed in 0..20 {
+                let selection = RandomImprove { seed: Some(seed) }.select(utxos.clone(), &params).unwrap();
+                assert_balanced(&selection, &params);
+                let effective: u128 = values(&selection).iter().map(|value| value - 5).sum();
+                assert!(effective <= 330);
+                assert_eq!(selection, RandomImprove { seed: Some(seed) }.select(utxos.clone(), &params).unwrap());
+            }
+        }
+
+        fn test_consolidate() {
+            let params = test_params(100);
+            let utxos = test_utxos(&[200, 8, 12, 9, 50]);
+            let selection = Consolidate { max_inputs: 3 }.select(utxos, &params).unwrap();
+            assert_eq!(values(&selection), vec![200, 8, 9]);
+            assert_balanced(&selection, &params);
+
+            match (Consolidate { max_inputs: 1 }).select(test_utxos(&[60, 60]), &params) {
+                Err(CoinSelectionError::TooManyInputs { required: 2, max_inputs: 1 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_selection_params_from_builder() {
+            let policy = SpendPolicy::PublicKey(
+                crate::types::PublicKey::from_str(
+                    "ed25519:0102030000000000000000000000000000000000000000000000000000000000",
+                )
+                .unwrap(),
+            );
+            let mut builder = V2TransactionBuilder::new();
+            builder.add_siacoin_output((policy.address(), Currency(1000)).into());
+            builder.fee_policy = Some(FeePolicy::HastingsPerByte(Currency(2)));
+
+            let params = SelectionParams::from_builder(&builder, &policy);
+            assert_eq!(params.target, Currency(1000));
+            assert_eq!(params.base_fee, Currency(2 * builder.satisfied_weight() as u128));
+            assert_eq!(params.input_fee, Currency(2 * policy.max_satisfied_size() as u128));
+            assert_eq!(params.change_fee, Currency(96));
+        }
+
+        fn test_value_overflow() {
+            // no single utxo covers the target and any two of them overflow a u128
+            let utxos = test_utxos(&[u128::MAX - 20, u128::MAX - 20]);
+            let params = test_params(u128::MAX - 20);
+            let selectors: Vec<Box<dyn CoinSelector>> = vec![
+                Box::new(LargestFirst),
+                Box::new(SmallestFirst),
+                Box::new(BranchAndBound { max_tries: 100 }),
+                Box::new(RandomImprove { seed: Some(1) }),
+                Box::new(Consolidate { max_inputs: 2 }),
+            ];
+            for selector in selectors {
+                match selector.select(utxos.clone(), &params) {
+                    Err(CoinSelectionError::ValueOverflow) => (),
+                    other => panic!("unexpected result: {:?}", other),
+                }
+            }
+        }
+
+        fn test_select_siafunds() {
+            let utxos: Vec<SiafundElement> = [3u64, 10, 7]
+                .iter()
+                .enumerate()
+                .map(|(i, value)| SiafundElement {
+                    id: SiafundOutputId(Hash256([i as u8; 32])),
+                    state_element: StateElement {
+                        leaf_index: i as u64,
+                        merkle_proof: vec![],
+                    },
+                    siafund_output: SiafundOutput {
+                        value: *value,
+                        address: SpendPolicy::anyone_can_spend().address(),
+                    },
+                    claim_start: Currency::ZERO,
+                })
+                .collect();
+
+            let (selected, change) = select_siafunds(utxos.clone(), 12).unwrap();
+            let values: Vec<u64> = selected.iter().map(|utxo| utxo.siafund_output.value).collect();
+            assert_eq!(values, vec![10, 7]);
+            assert_eq!(change, 5);
+
+            match select_siafunds(utxos, 21) {
+                Err(CoinSelectionError::InsufficientSiafunds { available: 20, required: 21 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+}
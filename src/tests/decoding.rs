@@ -0,0 +1,246 @@
+#[cfg(test)]
+mod test {
+    use crate::encoding::{Decodable, Decoder, DecoderError, Encodable, Encoder};
+    use crate::types::{Address, Attestation, Currency, CurrencyVersion, Hash256, PublicKey, SatisfiedPolicy,
+                       SiacoinElement, SiacoinInputV1, SiacoinInputV2, SiacoinOutput, SiacoinOutputId, SiafundOutput,
+                       Signature, SpendPolicy, StateElement, UnlockCondition, V1ArbitraryData, V1Transaction,
+                       V1TransactionSansSigs, V2Transaction, V2TransactionFull};
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+
+    fn encode<T: Encodable>(item: &T) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        item.encode(&mut encoder);
+        encoder.buffer
+    }
+
+    fn atomic_swap_success_satisfied_policy() -> SatisfiedPolicy {
+        let alice_pubkey = PublicKey::from_bytes(
+            &hex::decode("0102030000000000000000000000000000000000000000000000000000000000").unwrap(),
+        )
+        .unwrap();
+        let bob_pubkey = PublicKey::from_bytes(
+            &hex::decode("06C87838297B7BB16AB23946C99DFDF77FF834E35DB07D71E9B1D2B01A11E96D").unwrap(),
+        )
+        .unwrap();
+        let secret_hash =
+            Hash256::from_str("0100000000000000000000000000000000000000000000000000000000000000").unwrap();
+        let signature = Signature::try_from(
+            hex::decode("105641BF4AE119CB15617FC9658BEE5D448E2CC27C9BC3369F4BA5D0E1C3D01EBCB21B669A7B7A17CF8457189EAA657C41D4A2E6F9E0F25D0996D3A17170F309").unwrap()).unwrap();
+        let mut preimage = [0u8; 32];
+        preimage[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+        SatisfiedPolicy {
+            policy: SpendPolicy::atomic_swap_success(&alice_pubkey, &bob_pubkey, 77777777, &secret_hash),
+            signatures: vec![signature],
+            preimages: vec![preimage.into()],
+        }
+    }
+
+    cross_target_tests! {
+        fn test_currency_decode_v1() {
+            for value in [0u128, 1, u64::MAX as u128 + 1, u128::MAX] {
+                let currency = Currency(value);
+                let buffer = encode(&CurrencyVersion::V1(&currency));
+                let decoded = Currency::decode_v1(&mut Decoder::new(&buffer)).unwrap();
+                assert_eq!(decoded, currency);
+            }
+        }
+
+        fn test_currency_encode_v1_zero() {
+            // Sia Go encodes a zero Currency as an empty byte slice
+            assert_eq!(encode(&CurrencyVersion::V1(&Currency::ZERO)), vec![0u8; 8]);
+        }
+
+        fn test_currency_decode_v1_overflow() {
+            let mut encoder = Encoder::default();
+            encoder.write_len_prefixed_bytes(&[1u8; 17]);
+            match Currency::decode_v1(&mut Decoder::new(&encoder.buffer)) {
+                Err(DecoderError::CurrencyOverflow(17)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_spend_policy_decode_atomic_swap() {
+            let policy = atomic_swap_success_satisfied_policy().policy;
+            let decoded: SpendPolicy = Decoder::decode_all(&encode(&policy)).unwrap();
+            assert_eq!(decoded, policy);
+            assert_eq!(decoded.address(), policy.address());
+        }
+
+        fn test_spend_policy_decode_invalid_version() {
+            let mut buffer = encode(&SpendPolicy::Above(1));
+            buffer[0] = 2;
+            match SpendPolicy::decode(&mut Decoder::new(&buffer)) {
+                Err(DecoderError::PolicyVersion(2)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_spend_policy_decode_invalid_opcode() {
+            let mut buffer = encode(&SpendPolicy::Above(1));
+            buffer[1] = 99;
+            match SpendPolicy::decode(&mut Decoder::new(&buffer)) {
+                Err(DecoderError::PolicyOpcode(99)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_satisfied_policy_decode_atomic_swap_success() {
+            let satisfied_policy = atomic_swap_success_satisfied_policy();
+            let decoded: SatisfiedPolicy = Decoder::decode_all(&encode(&satisfied_policy)).unwrap();
+            assert_eq!(decoded, satisfied_policy);
+
+            let hash = Encoder::encode_and_hash(&decoded);
+            let expected = Hash256::from_str("08852e4ad99f726120028ecd82925b5f55fa441952cfc034a5cf4f09159b9372").unwrap();
+            assert_eq!(hash, expected);
+        }
+
+        fn test_siacoin_input_decode_v2() {
+            let policy = SpendPolicy::Threshold {
+                n: 1,
+                of: vec![SpendPolicy::Hash(Hash256::default())],
+            };
+            let mut preimage = [0u8; 32];
+            preimage[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+            let vin = SiacoinInputV2 {
+                parent: SiacoinElement {
+                    id: SiacoinOutputId::default(),
+                    state_element: StateElement {
+                        leaf_index: 0,
+                        merkle_proof: vec![Hash256::default()],
+                    },
+                    siacoin_output: SiacoinOutput {
+                        value: 1u64.into(),
+                        address: policy.address(),
+                    },
+                    maturity_height: 0,
+                },
+                satisfied_policy: SatisfiedPolicy {
+                    policy,
+                    signatures: vec![],
+                    preimages: vec![preimage.into()],
+                },
+            };
+
+            let decoded: SiacoinInputV2 = Decoder::decode_all(&encode(&vin)).unwrap();
+            assert_eq!(decoded, vin);
+
+            let hash = Encoder::encode_and_hash(&decoded);
+            let expected = Hash256::from_str("d31a05b155113a5244f14ae833887fd8b30f555129be126ca4b90592290db24a").unwrap();
+            assert_eq!(hash, expected);
+        }
+
+        fn test_attestation_decode() {
+            let attestation = Attestation {
+                public_key: PublicKey::from_bytes(
+                    &hex::decode("0102030000000000000000000000000000000000000000000000000000000000").unwrap(),
+                )
+                .unwrap(),
+                key: "HostAnnouncement".to_string(),
+                value: vec![1u8, 2u8, 3u8, 4u8],
+                signature: Signature::try_from(
+                    hex::decode("105641BF4AE119CB15617FC9658BEE5D448E2CC27C9BC3369F4BA5D0E1C3D01EBCB21B669A7B7A17CF8457189EAA657C41D4A2E6F9E0F25D0996D3A17170F309").unwrap()).unwrap(),
+            };
+
+            let decoded: Attestation = Decoder::decode_all(&encode(&attestation)).unwrap();
+            assert_eq!(decoded, attestation);
+        }
+
+        fn test_v1_transaction_sans_sigs_decode() {
+            let public_key = PublicKey::from_bytes(
+                &hex::decode("0102030000000000000000000000000000000000000000000000000000000000").unwrap(),
+            )
+            .unwrap();
+            let address =
+                Address::from_str("72b0762b382d4c251af5ae25b6777d908726d75962e5224f98d7f619bb39515dd64b9a56043a").unwrap();
+
+            let tx = V1TransactionSansSigs(V1Transaction {
+                siacoin_inputs: vec![SiacoinInputV1 {
+                    parent_id: SiacoinOutputId::default(),
+                    unlock_condition: UnlockCondition::new(vec![public_key], 0, 1),
+                }],
+                siacoin_outputs: vec![SiacoinOutput {
+                    value: Currency::ZERO,
+                    address: address.clone(),
+                }],
+                siafund_outputs: vec![SiafundOutput { value: 10, address }],
+                miner_fees: vec![Currency::DEFAULT_FEE],
+                arbitrary_data: Some(V1ArbitraryData {
+                    data: vec![vec![1, 2, 3], vec![]],
+                }),
+                ..Default::default()
+            });
+
+            let decoded: V1TransactionSansSigs = Decoder::decode_all(&encode(&tx)).unwrap();
+            assert_eq!(decoded, tx);
+        }
+
+        fn test_v2_transaction_full_decode() {
+            let j = json!(
+                {
+                    "siacoinInputs": [
+                        {
+                            "parent": {
+                                "id": "b49cba94064a92a75bf8c6f9d32ab18f38bfb14a2252e3e117d04da89d536f29",
+                                "stateElement": {
+                                    "leafIndex": 302,
+                                    "merkleProof": [
+                                        "6f41d366712e9dfa423160b5388f3faf673addf43566d7b3562106d15b833f46",
+                                        "eb7df5e13eccd812a47f29a233bbf3212b7379ca6dd20ba9981524bfd5eadce6"
+                                    ],
+                                },
+                                "siacoinOutput": {
+                                    "value": "288594172736732570239334030000",
+                                    "address": "2757c80b7ec2e493a138fed45b906f9f5735a992b68dcbd2069fbdf418c8b25158f3ac7a816b"
+                                },
+                                "maturityHeight": 0
+                            },
+                            "satisfiedPolicy": {
+                                "policy": {
+                                    "type": "uc",
+                                    "policy": {
+                                        "timelock": 0,
+                                        "publicKeys": [
+                                            "ed25519:7931b69fe8888e354d601a778e31bfa97fa89dc6f625cd01cc8aa28046e557e7"
+                                        ],
+                                        "signaturesRequired": 1
+                                    }
+                                },
+                                "signatures": [
+                                    "f43380794a6384e3d24d9908143c05dd37aaac8959efb65d986feb70fe289a5e26b84e0ac712af01a2f85f8727da18aae13a599a51fb066d098591e40cb26902"
+                                ]
+                            }
+                        }
+                    ],
+                    "siacoinOutputs": [
+                        {
+                            "value": "1000000000000000000000000000",
+                            "address": "000000000000000000000000000000000000000000000000000000000000000089eb0d6a8a69"
+                        }
+                    ],
+                    "minerFee": "10000000000000000000"
+                }
+            );
+            let tx = V2TransactionFull(serde_json::from_value::<V2Transaction>(j).unwrap());
+
+            let decoded: V2TransactionFull = Decoder::decode_all(&encode(&tx)).unwrap();
+            assert_eq!(decoded, tx);
+            assert_eq!(decoded.txid(), tx.txid());
+        }
+
+        fn test_v2_transaction_full_decode_truncated() {
+            let tx = V2TransactionFull(V2Transaction {
+                miner_fee: Currency::DEFAULT_FEE,
+                ..Default::default()
+            });
+            let buffer = encode(&tx);
+
+            match V2TransactionFull::decode(&mut Decoder::new(&buffer[..buffer.len() - 1])) {
+                Err(DecoderError::UnexpectedEof { expected: 16, remaining: 15 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+    use crate::coin_selection::LargestFirst;
+    use crate::seed::Seed;
+    use crate::tests::stub_client::StubClient;
+    use crate::transport::client::{ApiClientHelpers, FundTxError, GetSiafundClaimError, HelperError, SelectUtxosError};
+    use crate::types::{Address, Currency, FeePolicy, Hash256, Keypair, SiacoinElement, SiacoinOutputId,
+                       SiafundElement, SiafundOutput, SiafundOutputId, StateElement, V2TransactionBuilder};
+
+    fn test_keypair(seed: u8) -> Keypair {
+        let mut private = [0u8; 32];
+        private[0] = seed;
+        Keypair::from_private_bytes(&private).unwrap()
+    }
+
+    fn test_utxo(index: u8, address: &Address, value: Currency) -> SiacoinElement {
+        SiacoinElement {
+            id: SiacoinOutputId(Hash256([index; 32])),
+            state_element: StateElement {
+                leaf_index: index as u64,
+                merkle_proof: vec![],
+            },
+            siacoin_output: (address.clone(), value).into(),
+            maturity_height: 0,
+        }
+    }
+
+    fn coins(n: u128) -> Currency { Currency(n * *Currency::COIN) }
+
+    #[tokio::test]
+    async fn test_fund_tx_single_source_fee_policy() {
+        let keypair = test_keypair(1);
+        let address = keypair.public().address();
+        let client = StubClient::default();
+        client.chain().utxos = vec![test_utxo(1, &address, coins(3)), test_utxo(2, &address, coins(5))];
+
+        let mut builder = V2TransactionBuilder::new();
+        builder.add_siacoin_output((test_keypair(2).public().address(), coins(4)).into());
+        builder.fee_policy = Some(FeePolicy::HastingsPerByte(Currency(10)));
+        client
+            .fund_tx_single_source(&mut builder, &keypair.public())
+            .await
+            .unwrap();
+
+        // the largest utxo covers the payment and the change pays for its own output
+        assert_eq!(builder.siacoin_inputs.len(), 1);
+        assert_eq!(builder.siacoin_inputs[0].parent.id, SiacoinOutputId(Hash256([2u8; 32])));
+        assert_eq!(builder.siacoin_outputs.len(), 2);
+        assert_eq!(builder.siacoin_outputs[1].address, address);
+        assert_eq!(builder.miner_fee, Currency(10 * builder.satisfied_weight() as u128));
+        assert_eq!(
+            *builder.siacoin_outputs[1].value,
+            *coins(5) - *coins(4) - *builder.miner_fee
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fund_tx_single_source_insufficient_funds() {
+        let keypair = test_keypair(1);
+        let client = StubClient::default();
+        client.chain().utxos = vec![test_utxo(1, &keypair.public().address(), coins(1))];
+
+        let mut builder = V2TransactionBuilder::new();
+        builder
+            .add_siacoin_output((test_keypair(2).public().address(), coins(4)).into())
+            .miner_fee(Currency::DEFAULT_FEE);
+        match client.fund_tx_single_source(&mut builder, &keypair.public()).await {
+            Err(HelperError::SelectUtxos(SelectUtxosError::Funding { available, required })) => {
+                assert_eq!(available, coins(1));
+                assert_eq!(required, Currency(*coins(4) + *Currency::DEFAULT_FEE));
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(builder.siacoin_inputs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fund_tx_multi_source_no_sources() {
+        let client = StubClient::default();
+        let mut builder = V2TransactionBuilder::new();
+        let change_address = test_keypair(1).public().address();
+        match client
+            .fund_tx_multi_source(&mut builder, &[], &change_address, &LargestFirst)
+            .await
+        {
+            Err(HelperError::FundTx(FundTxError::NoSources)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_siafund_claim() {
+        let client = StubClient::default();
+        client.chain().height = 10;
+        let claim_address = test_keypair(1).public().address();
+        let element = SiafundElement {
+            id: SiafundOutputId(Hash256([1u8; 32])),
+            state_element: StateElement {
+                leaf_index: 1,
+                merkle_proof: vec![],
+            },
+            siafund_output: SiafundOutput {
+                value: 2,
+                address: test_keypair(2).public().address(),
+            },
+            claim_start: Currency::ZERO,
+        };
+
+        // a tipstate without the siafund pool cannot be used to predict a claim
+        match client.get_siafund_claim(&element, claim_address.clone()).await {
+            Err(HelperError::GetSiafundClaim(GetSiafundClaimError::MissingTaxRevenue(tipstate))) => {
+                assert_eq!(tipstate.index.height, 10)
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        client.chain().siafund_tax_revenue = Some(Currency(10_000 * 10_000));
+        let claim = client.get_siafund_claim(&element, claim_address.clone()).await.unwrap();
+        assert_eq!(
+            claim,
+            element.claim_element(&Currency(10_000 * 10_000), claim_address, 10)
+        );
+        assert_eq!(claim.siacoin_output.value, Currency(20_000));
+    }
+
+    #[tokio::test]
+    async fn test_discover_seed_addresses() {
+        let seed = Seed::from_entropy(&[1u8; 16]);
+        let client = StubClient::default();
+
+        // key 0 received at its v2 address and key 3, beyond a gap of 2 unused keys, at its v1 address
+        let mut builder = V2TransactionBuilder::new();
+        builder
+            .add_siacoin_output((seed.keypair(0).public().address(), coins(1)).into())
+            .add_siacoin_output((seed.keypair(3).public().v1_address(), coins(2)).into());
+        client.chain().confirm(builder.build());
+
+        let discovered = client.discover_seed_addresses(&seed, 3).await.unwrap();
+        assert_eq!(discovered.len(), 2);
+        assert_eq!(discovered[0].index, 0);
+        assert_eq!(discovered[0].public_key, seed.keypair(0).public());
+        assert_eq!(discovered[0].balance.as_ref().unwrap().siacoins, coins(1));
+        assert!(discovered[0].v1_balance.is_none());
+        assert_eq!(discovered[1].index, 3);
+        assert!(discovered[1].balance.is_none());
+        assert_eq!(discovered[1].v1_balance.as_ref().unwrap().siacoins, coins(2));
+
+        // the scan stops after keys 4, 5 and 6 are found to be unused
+        let queried: Vec<Address> = (0..7)
+            .flat_map(|index| {
+                let public_key = seed.keypair(index).public();
+                vec![public_key.address(), public_key.v1_address()]
+            })
+            .collect();
+        assert_eq!(client.chain().event_queries, queried);
+    }
+}
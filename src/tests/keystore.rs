@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod test {
+    use crate::keystore::{KdfParams, Keystore, KeystoreError, KeystoreKind, KEYSTORE_VERSION};
+    use crate::seed::Seed;
+    use crate::types::Keypair;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    // keep the tests fast, the recommended parameters are intentionally slow
+    fn test_kdf() -> KdfParams { KdfParams::new(64, 1, 1) }
+
+    fn test_seed() -> Seed {
+        Seed::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap()
+    }
+
+    fn test_seed_keystore() -> Keystore {
+        Keystore::encrypt(KeystoreKind::Seed, None, test_seed().as_bytes(), PASSWORD, test_kdf()).unwrap()
+    }
+
+    fn test_keypair_keystore(keypair: &Keypair) -> Keystore {
+        Keystore::encrypt(
+            KeystoreKind::Keypair,
+            Some(keypair.public()),
+            keypair.private().as_ref(),
+            PASSWORD,
+            test_kdf(),
+        )
+        .unwrap()
+    }
+
+    cross_target_tests! {
+        fn test_keystore_seed_round_trip() {
+            let keystore = test_seed_keystore();
+            assert_eq!(keystore.version, KEYSTORE_VERSION);
+            assert_eq!(keystore.ciphertext.len(), 32 + 16);
+
+            let imported = Keystore::from_json(&keystore.to_json().unwrap()).unwrap();
+            assert_eq!(imported, keystore);
+
+            let seed = imported.decrypt_seed(PASSWORD).unwrap();
+            assert_eq!(seed.keypair(0).public(), test_seed().keypair(0).public());
+        }
+
+        fn test_keystore_keypair_round_trip() {
+            let keypair = test_seed().keypair(3);
+            let keystore = test_keypair_keystore(&keypair);
+            assert_eq!(keystore.public_key, Some(keypair.public()));
+
+            let imported = Keystore::from_json(&keystore.to_json().unwrap()).unwrap();
+            assert_eq!(imported.decrypt_keypair(PASSWORD).unwrap().public(), keypair.public());
+        }
+
+        fn test_keystore_kdf_params_json() {
+            let keystore = test_seed_keystore();
+            let j: serde_json::Value = serde_json::from_str(&keystore.to_json().unwrap()).unwrap();
+            assert_eq!(j["cipher"], "xchacha20-poly1305");
+            assert_eq!(j["nonce"].as_str().unwrap().len(), 2 * 24);
+            assert_eq!(j["kdf"]["algorithm"], "argon2id");
+            assert_eq!(j["kdf"]["memory"], 64);
+            assert_eq!(j["kdf"]["iterations"], 1);
+            assert_eq!(j["kdf"]["parallelism"], 1);
+            assert_eq!(j["kdf"]["salt"].as_str().unwrap().len(), 2 * 32);
+        }
+
+        fn test_keystore_invalid_kdf_params() {
+            match Keystore::encrypt(KeystoreKind::Seed, None, test_seed().as_bytes(), PASSWORD, KdfParams::new(64, 0, 1)) {
+                Err(KeystoreError::InvalidKdfParams(_)) => (),
+                other => panic!("unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+
+        fn test_keystore_unique_salt_and_nonce() {
+            let a = test_seed_keystore();
+            let b = test_seed_keystore();
+            assert_ne!(a.kdf.salt, b.kdf.salt);
+            assert_ne!(a.nonce, b.nonce);
+            assert_ne!(a.ciphertext, b.ciphertext);
+        }
+
+        fn test_keystore_wrong_password() {
+            match test_seed_keystore().decrypt_seed("hunter2") {
+                Err(KeystoreError::Decrypt) => (),
+                other => panic!("unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+
+        fn test_keystore_tampered_header() {
+            let keypair = test_seed().keypair(0);
+            let mut keystore = test_keypair_keystore(&keypair);
+            keystore.public_key = Some(test_seed().keypair(1).public());
+            match keystore.decrypt_keypair(PASSWORD) {
+                Err(KeystoreError::Decrypt) => (),
+                other => panic!("unexpected result: {:?}", other.map(|_| ())),
+            }
+
+            let mut keystore = test_seed_keystore();
+            keystore.kdf.memory += 8;
+            match keystore.decrypt_seed(PASSWORD) {
+                Err(KeystoreError::Decrypt) => (),
+                other => panic!("unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+
+        fn test_keystore_kind_mismatch() {
+            match test_seed_keystore().decrypt_keypair(PASSWORD) {
+                Err(KeystoreError::KindMismatch {
+                    expected: KeystoreKind::Keypair,
+                    found: KeystoreKind::Seed,
+                }) => (),
+                other => panic!("unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+
+        fn test_keystore_unsupported_version() {
+            let mut keystore = test_seed_keystore();
+            keystore.version = KEYSTORE_VERSION + 1;
+            match keystore.decrypt_seed(PASSWORD) {
+                Err(KeystoreError::UnsupportedVersion(version)) => assert_eq!(version, KEYSTORE_VERSION + 1),
+                other => panic!("unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    // wasm has no filesystem
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_keystore_save_load() {
+        let path = std::env::temp_dir().join(format!("sia-rust-keystore-{}.json", std::process::id()));
+        let keystore = test_seed_keystore();
+        keystore.save(&path).unwrap();
+        let loaded = Keystore::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, keystore);
+        loaded.decrypt_seed(PASSWORD).unwrap();
+    }
+}
@@ -1,4 +1,13 @@
+mod atomic_swap;
+mod coin_selection;
+mod decoding;
 mod encoding;
+mod helpers;
+mod keystore;
+mod partially_signed;
+mod seed;
 mod serde;
 mod spend_policy;
+#[cfg(not(target_arch = "wasm32"))] mod stub_client;
 mod transaction;
+mod validation;
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod test {
+    use crate::types::{Currency, Hash256, Keypair, PartiallySignedError, PartiallySignedV2Transaction, Preimage,
+                       SiacoinElement, SiacoinOutput, SiacoinOutputId, SpendPolicy, StateElement, V2TransactionBuilder};
+    use crate::validation::{validate_v2_transaction, ValidationContext};
+
+    fn test_keypair(seed: u8) -> Keypair {
+        let mut private = [0u8; 32];
+        private[0] = seed;
+        Keypair::from_private_bytes(&private).unwrap()
+    }
+
+    /// a transaction spending a single input locked by `policy`
+    fn test_partially_signed(policy: &SpendPolicy) -> PartiallySignedV2Transaction {
+        let parent = SiacoinElement {
+            id: SiacoinOutputId(Hash256([7u8; 32])),
+            state_element: StateElement {
+                leaf_index: 7,
+                merkle_proof: vec![],
+            },
+            siacoin_output: SiacoinOutput {
+                value: Currency::COIN,
+                address: policy.address(),
+            },
+            maturity_height: 0,
+        };
+        let mut builder = V2TransactionBuilder::new();
+        builder
+            .add_siacoin_input(parent, policy.clone())
+            .add_siacoin_output(SiacoinOutput {
+                value: Currency(*Currency::COIN - *Currency::DEFAULT_FEE),
+                address: SpendPolicy::PublicKey(test_keypair(9).public()).address(),
+            })
+            .miner_fee(Currency::DEFAULT_FEE);
+        PartiallySignedV2Transaction::new(builder)
+    }
+
+    fn test_multisig_policy(keypairs: &[Keypair]) -> SpendPolicy {
+        SpendPolicy::threshold(
+            2,
+            keypairs.iter().map(|kp| SpendPolicy::PublicKey(kp.public())).collect(),
+        )
+    }
+
+    /// pass the container through JSON as it would be between cosigners
+    fn json_round_trip(psbt: &PartiallySignedV2Transaction) -> PartiallySignedV2Transaction {
+        serde_json::from_str(&serde_json::to_string(psbt).unwrap()).unwrap()
+    }
+
+    cross_target_tests! {
+        fn test_partially_signed_multisig_merge() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_keypair).collect();
+            let psbt = test_partially_signed(&test_multisig_policy(&keypairs));
+
+            let mut alice = json_round_trip(&psbt);
+            alice.sign(&keypairs[0]).unwrap();
+            assert_eq!(alice.unsatisfied(0, 0).siacoin_inputs, vec![0]);
+            match alice.finalize(0, 0) {
+                Err(PartiallySignedError::Unsatisfied(unsatisfied)) => assert_eq!(unsatisfied.siacoin_inputs, vec![0]),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            let mut carol = json_round_trip(&psbt);
+            carol.sign(&keypairs[2]).unwrap();
+
+            let mut merged = json_round_trip(&alice);
+            merged.merge(&json_round_trip(&carol)).unwrap();
+            assert!(merged.unsatisfied(0, 0).is_empty());
+
+            let tx = merged.finalize(0, 0).unwrap();
+            assert_eq!(tx.siacoin_inputs[0].satisfied_policy.signatures.len(), 2);
+            validate_v2_transaction(&tx, &ValidationContext::new(0, 0)).unwrap();
+        }
+
+        fn test_partially_signed_preimage() {
+            let success = test_keypair(1);
+            let refund = test_keypair(2);
+            let secret = Preimage([9u8; 32]);
+            let policy = SpendPolicy::atomic_swap(&success.public(), &refund.public(), 100, &secret.compute_hash());
+            let mut psbt = test_partially_signed(&policy);
+            assert_eq!(psbt.siacoin_inputs[0].preimages.get(&secret.compute_hash()), Some(&None));
+
+            psbt.sign(&success).unwrap();
+            assert_eq!(psbt.unsatisfied(0, 0).siacoin_inputs, vec![0]);
+
+            match psbt.add_preimage(Preimage([8u8; 32])) {
+                Err(PartiallySignedError::PreimageNotRequired(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            psbt.add_preimage(secret.clone()).unwrap();
+
+            let tx = json_round_trip(&psbt).finalize(0, 0).unwrap();
+            assert_eq!(tx.siacoin_inputs[0].satisfied_policy.preimages, vec![secret]);
+            validate_v2_transaction(&tx, &ValidationContext::new(0, 0)).unwrap();
+        }
+
+        fn test_partially_signed_add_signature_invalid() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_keypair).collect();
+            let mut psbt = test_partially_signed(&test_multisig_policy(&keypairs));
+
+            // signature over the wrong message
+            let signature = keypairs[0].sign(&[0u8; 32]);
+            match psbt.add_signature(&keypairs[0].public(), signature) {
+                Err(PartiallySignedError::InvalidSignature { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            match psbt.sign(&test_keypair(4)) {
+                Err(PartiallySignedError::SignatureNotRequired(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(psbt.siacoin_inputs[0].signatures.is_empty());
+        }
+
+        fn test_partially_signed_merge_mismatch() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_keypair).collect();
+            let mut psbt = test_partially_signed(&test_multisig_policy(&keypairs));
+            let mut other = psbt.clone();
+            other.builder.miner_fee(Currency::ZERO);
+
+            match psbt.merge(&other) {
+                Err(PartiallySignedError::SigHashMismatch { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod test {
+    use crate::seed::{entropy_to_phrase, new_seed_phrase, phrase_to_entropy, Seed, SeedError};
+    use crate::types::UnlockCondition;
+
+    // 12 word vectors from the BIP39 specification
+    // https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+    const BIP39_VECTORS: [(&str, &str); 6] = [
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+        ),
+        (
+            "f30f8c1da665478f49b001d94c5fc452",
+            "vessel ladder alter error federal sibling chat ability sun glass valve picture",
+        ),
+    ];
+
+    // the legacy encoding of Seed::from_phrase(BIP39_VECTORS[0].1)
+    // FIXME replace with a vector generated by siad
+    const LEGACY_PHRASE: &str =
+        "biggest invoke syllabus oozed rowboat trash ardent jubilee algebra amply gills yesterday \
+                                 sizes calamity system unrest refer smog testing satin befit madness update skew \
+                                 vacation unafraid veteran gigantic afield";
+
+    cross_target_tests! {
+        fn test_seed_phrase_bip39_vectors() {
+            for (entropy_hex, phrase) in BIP39_VECTORS.iter() {
+                let mut entropy = [0u8; 16];
+                entropy.copy_from_slice(&hex::decode(entropy_hex).unwrap());
+
+                assert_eq!(entropy_to_phrase(&entropy), *phrase);
+                assert_eq!(phrase_to_entropy(phrase).unwrap(), entropy);
+            }
+        }
+
+        fn test_seed_phrase_round_trip() {
+            let phrase = new_seed_phrase();
+            let entropy = phrase_to_entropy(&phrase).unwrap();
+            assert_eq!(entropy_to_phrase(&entropy), phrase);
+        }
+
+        fn test_seed_phrase_invalid_checksum() {
+            let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+            match phrase_to_entropy(phrase) {
+                Err(SeedError::InvalidChecksum) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_seed_phrase_unknown_word() {
+            let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon sia";
+            match phrase_to_entropy(phrase) {
+                Err(SeedError::UnknownWord(word)) => assert_eq!(word, "sia"),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_seed_phrase_word_count() {
+            match phrase_to_entropy("abandon about") {
+                Err(SeedError::WordCount(2)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_seed_keypair_derivation() {
+            let seed = Seed::from_phrase(BIP39_VECTORS[0].1).unwrap();
+
+            // private keys are blake2b(blake2b(entropy) | index as u64 little-endian)
+            let expected = [
+                (0, "5602bf6f86e73e057e262854958307b151a798288871c8e4485a32b6253a904d"),
+                (1, "495d0603592e2fd827c6771988ad96330680acc7ca393fcb36903d9f5976f299"),
+                (5, "58c7babd873326a9dda5935c05c05d9592b7f7b53c54e72524ea613b9125263a"),
+            ];
+            for (index, private_hex) in expected.iter() {
+                assert_eq!(hex::encode(seed.keypair(*index).private()), *private_hex);
+            }
+        }
+
+        fn test_seed_legacy_dictionary() {
+            // siad's dictionary has 1626 words, each identified by its first 3 characters
+            let dictionary: Vec<&str> = include_str!("../seed/legacy_english.txt").lines().collect();
+            assert_eq!(dictionary.len(), 1626);
+            let mut prefixes: Vec<&str> = dictionary.iter().map(|word| &word[..3]).collect();
+            prefixes.sort_unstable();
+            prefixes.dedup();
+            assert_eq!(prefixes.len(), dictionary.len());
+            assert!(dictionary.iter().all(|word| word.chars().all(|c| c.is_ascii_lowercase())));
+        }
+
+        fn test_seed_legacy_phrase_round_trip() {
+            let seed = Seed::from_phrase(BIP39_VECTORS[0].1).unwrap();
+            assert_eq!(seed.to_legacy_phrase(), LEGACY_PHRASE);
+
+            let legacy = Seed::from_legacy_phrase(LEGACY_PHRASE).unwrap();
+            assert_eq!(legacy.keypair(0).private(), seed.keypair(0).private());
+            assert_eq!(legacy.to_legacy_phrase(), LEGACY_PHRASE);
+        }
+
+        fn test_seed_legacy_phrase_prefix_match() {
+            // siad only considers the first 3 characters of each word
+            let abbreviated: Vec<String> = LEGACY_PHRASE.split_whitespace().map(|w| w[..3].to_string()).collect();
+            let seed = Seed::from_legacy_phrase(&abbreviated.join(" ")).unwrap();
+            assert_eq!(seed.to_legacy_phrase(), LEGACY_PHRASE);
+        }
+
+        fn test_seed_legacy_phrase_invalid_checksum() {
+            let phrase = LEGACY_PHRASE.replacen("biggest", "bikini", 1);
+            match Seed::from_legacy_phrase(&phrase) {
+                Err(SeedError::LegacyInvalidChecksum) => (),
+                other => panic!("unexpected result: {:?}", other.map(|seed| seed.to_legacy_phrase())),
+            }
+        }
+
+        fn test_seed_legacy_phrase_unknown_word() {
+            let phrase = LEGACY_PHRASE.replacen("biggest", "qwerty", 1);
+            match Seed::from_legacy_phrase(&phrase) {
+                Err(SeedError::LegacyUnknownWord(word)) => assert_eq!(word, "qwerty"),
+                other => panic!("unexpected result: {:?}", other.map(|seed| seed.to_legacy_phrase())),
+            }
+        }
+
+        fn test_seed_legacy_phrase_short_word() {
+            // "bi" is a prefix of "biggest" but shorter than the 3 characters siad requires
+            let phrase = LEGACY_PHRASE.replacen("biggest", "bi", 1);
+            match Seed::from_legacy_phrase(&phrase) {
+                Err(SeedError::LegacyUnknownWord(word)) => assert_eq!(word, "bi"),
+                other => panic!("unexpected result: {:?}", other.map(|seed| seed.to_legacy_phrase())),
+            }
+        }
+
+        fn test_seed_legacy_phrase_length() {
+            let truncated: Vec<&str> = LEGACY_PHRASE.split_whitespace().take(20).collect();
+            match Seed::from_legacy_phrase(&truncated.join(" ")) {
+                Err(SeedError::LegacyLength(_)) => (),
+                other => panic!("unexpected result: {:?}", other.map(|seed| seed.to_legacy_phrase())),
+            }
+        }
+
+        fn test_seed_legacy_unlock_condition() {
+            let seed = Seed::from_legacy_phrase(LEGACY_PHRASE).unwrap();
+            let unlock_condition = seed.unlock_condition(1);
+            assert_eq!(unlock_condition, UnlockCondition::standard_unlock(seed.keypair(1).public()));
+            assert_eq!(unlock_condition.address(), seed.keypair(1).public().v1_address());
+        }
+
+        fn test_seed_debug_redacted() {
+            let seed = Seed::from_phrase(BIP39_VECTORS[0].1).unwrap();
+            assert_eq!(format!("{:?}", seed), "Seed(<redacted>)");
+        }
+    }
+}
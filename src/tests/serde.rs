@@ -88,7 +88,9 @@ mod test {
         }};
     }
     // Ensure the original value matches the value after round-trip (serialize -> deserialize -> serialize)
-    use crate::types::{Address, Event, Hash256, SiacoinElement, SiacoinOutput, StateElement, UnlockKey, V2Transaction};
+    use crate::transport::endpoints::ConsensusTipstateResponse;
+    use crate::types::{Address, Currency, Event, Hash256, SiacoinElement, SiacoinOutput, SiafundElement, StateElement,
+                       TransactionSignature, UnlockKey, V1Transaction, V2Transaction};
 
     cross_target_tests! {
             fn test_serde_address() {
@@ -112,6 +114,62 @@ mod test {
                 );
             }
 
+            fn test_serde_transaction_signature() {
+                let j = json!({
+                    "parentID": "dc07e5bf84fbda867a7ed7ca80c6d1d81db05cef16ff38f6ba80b6bf01e1ddb1",
+                    "publicKeyIndex": 1,
+                    "timelock": 0,
+                    "coveredFields": {
+                        "wholeTransaction": true,
+                        "siacoinInputs": [],
+                        "siacoinOutputs": [],
+                        "fileContracts": [],
+                        "fileContractRevisions": [],
+                        "storageProofs": [],
+                        "siafundInputs": [],
+                        "siafundOutputs": [],
+                        "minerFees": [],
+                        "arbitraryData": [],
+                        "signatures": []
+                    },
+                    "signature": "EFZBv0rhGcsVYX/JZYvuXUSOLMJ8m8M2n0ul0OHD0B68shtmmnt6F8+EVxieqmV8QdSi5vng8l0JltOhcXDzCQ=="
+                });
+                test_serde!(TransactionSignature, j);
+            }
+
+            fn test_serde_v1_transaction() {
+                let j = json!({
+                    "siacoinInputs": [],
+                    "siacoinOutputs": [],
+                    "fileContracts": [],
+                    "fileContractRevisions": [],
+                    "storageProofs": [],
+                    "siafundInputs": [
+                        {
+                            "parentID": "dc07e5bf84fbda867a7ed7ca80c6d1d81db05cef16ff38f6ba80b6bf01e1ddb1",
+                            "unlockConditions": {
+                                "timelock": 0,
+                                "publicKeys": [
+                                    "ed25519:0102030000000000000000000000000000000000000000000000000000000000"
+                                ],
+                                "signaturesRequired": 1
+                            },
+                            "claimAddress": "591fcf237f8854b5653d1ac84ae4c107b37f148c3c7b413f292d48db0c25a8840be0653e411f"
+                        }
+                    ],
+                    "siafundOutputs": [
+                        {
+                            "value": 10,
+                            "address": "591fcf237f8854b5653d1ac84ae4c107b37f148c3c7b413f292d48db0c25a8840be0653e411f"
+                        }
+                    ],
+                    "minerFees": ["10000000000000000000"],
+                    "arbitraryData": ["AQID", ""],
+                    "signatures": []
+                });
+                test_serde!(V1Transaction, j);
+            }
+
             fn test_serde_siacoin_output() {
                 let j = json!({
                     "value": "300000000000000000000000000000",
@@ -182,6 +240,50 @@ mod test {
         serde_json::from_str::<SiacoinElement>(json_str).unwrap();
     }
 
+    fn test_serde_siafund_element() {
+        let j = json!(
+            {
+                "id": "0102030000000000000000000000000000000000000000000000000000000000",
+                "stateElement": {
+                    "leafIndex": 1,
+                    "merkleProof": [
+                        "0405060000000000000000000000000000000000000000000000000000000000"
+                    ]
+                },
+                "siafundOutput": {
+                    "value": 100,
+                    "address": "72b0762b382d4c251af5ae25b6777d908726d75962e5224f98d7f619bb39515dd64b9a56043a"
+                },
+                "claimStart": "5"
+            }
+        );
+        test_serde!(SiafundElement, j);
+    }
+
+    fn test_serde_consensus_tipstate_response() {
+        let mut j = json!(
+            {
+                "index": {
+                    "height": 10,
+                    "id": "0102030000000000000000000000000000000000000000000000000000000000"
+                },
+                "prevTimestamps": ["2024-01-01T00:00:00Z"],
+                "siafundTaxRevenue": "5"
+            }
+        );
+        let tipstate = serde_json::from_value::<ConsensusTipstateResponse>(j.clone()).unwrap();
+        assert_eq!(tipstate.siafund_tax_revenue, Some(Currency(5)));
+
+        // walletd versions prior to the v2 hardfork name the field siafundPool
+        let siafund_pool = j.as_object_mut().unwrap().remove("siafundTaxRevenue").unwrap();
+        let tipstate = serde_json::from_value::<ConsensusTipstateResponse>(j.clone()).unwrap();
+        assert_eq!(tipstate.siafund_tax_revenue, None);
+
+        j["siafundPool"] = siafund_pool;
+        let tipstate = serde_json::from_value::<ConsensusTipstateResponse>(j).unwrap();
+        assert_eq!(tipstate.siafund_tax_revenue, Some(Currency(5)));
+    }
+
     fn test_serde_event_v2_contract_resolution_storage_proof() {
         let j = r#"
             {
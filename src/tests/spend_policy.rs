@@ -1,8 +1,18 @@
 #[cfg(test)]
+// the original deserialization tests convert SpendPolicy into itself
+#[allow(clippy::useless_conversion)]
 mod test {
-    use crate::types::{Address, Hash256, PublicKey, SpendPolicy, UnlockCondition, UnlockKey};
+    use crate::encoding::{Encodable, Encoder};
+    use crate::types::{Address, Hash256, Keypair, Preimage, PublicKey, RefundLock, SatisfiedPolicy, Specifier,
+                       SpendPolicy, SpendPolicyVerifyError, UnlockCondition, UnlockKey};
     use std::str::FromStr;
 
+    fn test_keypair(seed: u8) -> Keypair {
+        let mut private = [0u8; 32];
+        private[0] = seed;
+        Keypair::from_private_bytes(&private).unwrap()
+    }
+
     cross_target_tests! {
         fn test_serde_spend_policy_above() {
             let j = json!(
@@ -157,5 +167,399 @@ mod test {
 
             assert_eq!(spend_policy, spend_policy_deser);
         }
+
+        fn test_preimage_compute_hash() {
+            // sha256 of 32 zero bytes
+            let expected = Hash256::from_str("66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925").unwrap();
+            assert_eq!(Preimage::default().compute_hash(), expected);
+        }
+
+        fn test_spend_policy_verify_atomic_swap_success() {
+            let alice = test_keypair(1);
+            let bob = test_keypair(2);
+            let secret = Preimage([7u8; 32]);
+            let sig_hash = Hash256::from_str("0102030000000000000000000000000000000000000000000000000000000000").unwrap();
+            let policy = SpendPolicy::atomic_swap_success(&alice.public(), &bob.public(), 1000, &secret.compute_hash());
+
+            let satisfied_policy = SatisfiedPolicy {
+                policy: policy.clone(),
+                signatures: vec![alice.sign(&sig_hash.0)],
+                preimages: vec![secret],
+            };
+            satisfied_policy.verify(0, 0, &sig_hash).unwrap();
+
+            // a wrong preimage is fatal even though the policy is within a threshold
+            let satisfied_policy = SatisfiedPolicy {
+                policy: policy.clone(),
+                signatures: vec![alice.sign(&sig_hash.0)],
+                preimages: vec![Preimage([8u8; 32])],
+            };
+            match satisfied_policy.verify(0, 0, &sig_hash) {
+                Err(SpendPolicyVerifyError::InvalidPreimage(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            // a signature from the wrong key
+            let satisfied_policy = SatisfiedPolicy {
+                policy,
+                signatures: vec![bob.sign(&sig_hash.0)],
+                preimages: vec![Preimage([7u8; 32])],
+            };
+            match satisfied_policy.verify(0, 0, &sig_hash) {
+                Err(SpendPolicyVerifyError::InvalidSignature(pk)) => assert_eq!(*pk, alice.public()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_spend_policy_verify_atomic_swap_refund() {
+            let alice = test_keypair(1);
+            let bob = test_keypair(2);
+            let sig_hash = Hash256::default();
+            let policy = SpendPolicy::atomic_swap_refund(&alice.public(), &bob.public(), 1000, &Hash256::default());
+            let satisfied_policy = SatisfiedPolicy {
+                policy,
+                signatures: vec![bob.sign(&sig_hash.0)],
+                preimages: vec![],
+            };
+
+            // the refund path is not satisfied until the median timestamp is after the lock time
+            match satisfied_policy.verify(0, 1000, &sig_hash) {
+                Err(SpendPolicyVerifyError::ThresholdNotReached { remaining: 1 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            satisfied_policy.verify(0, 1001, &sig_hash).unwrap();
+        }
+
+        fn test_spend_policy_verify_atomic_swap_height_refund() {
+            let alice = test_keypair(1);
+            let bob = test_keypair(2);
+            let sig_hash = Hash256::default();
+            let policy = SpendPolicy::atomic_swap_height_refund(&alice.public(), &bob.public(), 100, &Hash256::default());
+            let satisfied_policy = SatisfiedPolicy {
+                policy,
+                signatures: vec![bob.sign(&sig_hash.0)],
+                preimages: vec![],
+            };
+
+            // the refund path is not satisfied until the height reaches the lock height
+            match satisfied_policy.verify(99, u64::MAX, &sig_hash) {
+                Err(SpendPolicyVerifyError::ThresholdNotReached { remaining: 1 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            satisfied_policy.verify(100, 0, &sig_hash).unwrap();
+        }
+
+        fn test_spend_policy_verify_atomic_swap_height_and_time_refund() {
+            let alice = test_keypair(1);
+            let bob = test_keypair(2);
+            let sig_hash = Hash256::default();
+            let policy =
+                SpendPolicy::atomic_swap_height_and_time_refund(&alice.public(), &bob.public(), 100, 1000, &sig_hash);
+            let satisfied_policy = SatisfiedPolicy {
+                policy,
+                signatures: vec![bob.sign(&sig_hash.0)],
+                preimages: vec![],
+            };
+
+            for (height, median_timestamp) in [(99, 1001), (100, 1000)] {
+                match satisfied_policy.verify(height, median_timestamp, &sig_hash) {
+                    Err(SpendPolicyVerifyError::ThresholdNotReached { remaining: 1 }) => (),
+                    other => panic!("unexpected result: {:?}", other),
+                }
+                assert!(!RefundLock::HeightAndTime { height: 100, time: 1000 }.is_expired(height, median_timestamp));
+            }
+            satisfied_policy.verify(100, 1001, &sig_hash).unwrap();
+            assert!(RefundLock::HeightAndTime { height: 100, time: 1000 }.is_expired(100, 1001));
+        }
+
+        fn test_spend_policy_atomic_swap_refund_locks() {
+            let alice = test_keypair(1).public();
+            let bob = test_keypair(2).public();
+            let hash = Hash256([1u8; 32]);
+
+            // atomic_swap is the time locked variant
+            assert_eq!(
+                SpendPolicy::atomic_swap(&alice, &bob, 100, &hash),
+                SpendPolicy::atomic_swap_with_lock(&alice, &bob, &RefundLock::Time(100), &hash)
+            );
+
+            let height = SpendPolicy::atomic_swap_height(&alice, &bob, 100, &hash);
+            let paths = height.satisfaction_paths();
+            assert_eq!(paths[1].min_height, 100);
+            assert_eq!(paths[1].min_timestamp, 0);
+            assert_ne!(height.address(), SpendPolicy::atomic_swap(&alice, &bob, 100, &hash).address());
+
+            // the opacified variants share the address of the full policy
+            assert_eq!(SpendPolicy::atomic_swap_height_success(&alice, &bob, 100, &hash).address(), height.address());
+            assert_eq!(SpendPolicy::atomic_swap_height_refund(&alice, &bob, 100, &hash).address(), height.address());
+
+            let both = SpendPolicy::atomic_swap_height_and_time(&alice, &bob, 100, 1000, &hash);
+            let paths = both.satisfaction_paths();
+            assert_eq!((paths[1].min_height, paths[1].min_timestamp), (100, 1001));
+            assert_eq!(
+                SpendPolicy::atomic_swap_height_and_time_success(&alice, &bob, 100, 1000, &hash).address(),
+                both.address()
+            );
+            assert_eq!(
+                SpendPolicy::atomic_swap_height_and_time_refund(&alice, &bob, 100, 1000, &hash).address(),
+                both.address()
+            );
+        }
+
+        fn test_spend_policy_verify_superfluous() {
+            let alice = test_keypair(1);
+            let sig_hash = Hash256::default();
+            let policy = SpendPolicy::PublicKey(alice.public());
+
+            match policy.verify(0, 0, &sig_hash, &[alice.sign(&sig_hash.0), alice.sign(&sig_hash.0)], &[]) {
+                Err(SpendPolicyVerifyError::SuperfluousSignatures) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match policy.verify(0, 0, &sig_hash, &[alice.sign(&sig_hash.0)], &[Preimage::default()]) {
+                Err(SpendPolicyVerifyError::SuperfluousPreimages) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_spend_policy_verify_opaque() {
+            let policy = SpendPolicy::Above(10).opacify();
+            match policy.verify(100, 0, &Hash256::default(), &[], &[]) {
+                Err(SpendPolicyVerifyError::Opaque(address)) => assert_eq!(address, SpendPolicy::Above(10).address()),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_spend_policy_verify_unlock_conditions() {
+            let alice = test_keypair(1);
+            let bob = test_keypair(2);
+            let sig_hash = Hash256::default();
+            let policy = SpendPolicy::unlock_condition(vec![alice.public(), bob.public()], 50, 2);
+            let signatures = [alice.sign(&sig_hash.0), bob.sign(&sig_hash.0)];
+
+            match policy.verify(49, 0, &sig_hash, &signatures, &[]) {
+                Err(SpendPolicyVerifyError::HeightNotAbove { height: 49, above: 50 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            policy.verify(50, 0, &sig_hash, &signatures, &[]).unwrap();
+
+            // UnlockConditions are only valid at the root of a policy
+            let nested = SpendPolicy::threshold(1, vec![policy]);
+            match nested.verify(50, 0, &sig_hash, &signatures, &[]) {
+                Err(SpendPolicyVerifyError::UnlockConditionsSubPolicy) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_spend_policy_address_opaque() {
+            let policy = SpendPolicy::threshold(
+                1,
+                vec![
+                    SpendPolicy::PublicKey(test_keypair(1).public()),
+                    SpendPolicy::After(100),
+                ],
+            );
+            // an opaque policy is hashed like any other policy rather than returning the address it wraps
+            let opaque = policy.opacify();
+            assert_ne!(opaque.address(), policy.address());
+            let mut encoder = Encoder::default();
+            encoder.write_distinguisher("address");
+            opaque.encode(&mut encoder);
+            assert_eq!(opaque.address(), Address(encoder.hash()));
+
+            // opacifying a sub-policy does not change the address of its parent
+            let partially_opaque = SpendPolicy::threshold(
+                1,
+                vec![
+                    SpendPolicy::PublicKey(test_keypair(1).public()),
+                    SpendPolicy::After(100).opacify(),
+                ],
+            );
+            assert_eq!(partially_opaque.address(), policy.address());
+        }
+
+        fn test_spend_policy_string_atomic_swap() {
+            let alice = test_keypair(1).public();
+            let bob = test_keypair(2).public();
+            let hash = Hash256([3u8; 32]);
+            let policy = SpendPolicy::threshold(
+                1,
+                vec![
+                    SpendPolicy::threshold(2, vec![SpendPolicy::Hash(hash.clone()), SpendPolicy::PublicKey(alice.clone())]),
+                    SpendPolicy::threshold(2, vec![SpendPolicy::After(1700000000), SpendPolicy::PublicKey(bob.clone())]),
+                ],
+            );
+
+            let expected = format!(
+                "thresh(1,[thresh(2,[h(0x{}),pk(0x{})]),thresh(2,[after(1700000000),pk(0x{})])])",
+                hex::encode(hash.0),
+                hex::encode(alice.as_bytes()),
+                hex::encode(bob.as_bytes())
+            );
+            assert_eq!(policy.to_string(), expected);
+            assert_eq!(SpendPolicy::from_str(&expected).unwrap(), policy);
+        }
+
+        fn test_spend_policy_string_nesting_limit() {
+            fn nested(depth: usize) -> String {
+                format!("{}above(1){}", "thresh(1,[".repeat(depth), "])".repeat(depth))
+            }
+
+            let policy = SpendPolicy::from_str(&nested(64)).unwrap();
+            assert_eq!(policy.to_string(), nested(64));
+
+            let err = SpendPolicy::from_str(&nested(64 + 1)).unwrap_err();
+            assert!(err.to_string().contains("nested more than 64 levels deep"), "{}", err);
+            // far deeper nesting is rejected without exhausting the stack
+            SpendPolicy::from_str(&nested(100_000)).unwrap_err();
+        }
+
+        fn test_spend_policy_string_round_trip() {
+            let policies = vec![
+                SpendPolicy::Above(123),
+                SpendPolicy::After(u64::MAX),
+                SpendPolicy::PublicKey(test_keypair(1).public()),
+                SpendPolicy::Hash(Hash256([1u8; 32])),
+                SpendPolicy::anyone_can_spend(),
+                SpendPolicy::After(100).opacify(),
+                SpendPolicy::unlock_condition(vec![test_keypair(1).public(), test_keypair(2).public()], 10, 2),
+                SpendPolicy::UnlockConditions(UnlockCondition {
+                    unlock_keys: vec![],
+                    timelock: 0,
+                    signatures_required: 0,
+                }),
+            ];
+            for policy in policies {
+                assert_eq!(SpendPolicy::from_str(&policy.to_string()).unwrap(), policy);
+            }
+
+            assert_eq!(SpendPolicy::anyone_can_spend().to_string(), "thresh(0,[])");
+            assert_eq!(SpendPolicy::Above(123).to_string(), "above(123)");
+        }
+
+        fn test_spend_policy_string_unlock_conditions() {
+            let public_key = test_keypair(1).public();
+            let policy = SpendPolicy::unlock_condition(vec![public_key.clone()], 0, 1);
+            assert_eq!(policy.to_string(), format!("uc(0,[ed25519:{}],1)", hex::encode(public_key.as_bytes())));
+        }
+
+
+        fn test_spend_policy_satisfaction_paths_atomic_swap() {
+            let alice = test_keypair(1).public();
+            let bob = test_keypair(2).public();
+            let preimage = Preimage([7u8; 32]);
+            let hash = preimage.compute_hash();
+            let policy = SpendPolicy::atomic_swap(&alice, &bob, 1_700_000_000, &hash);
+
+            let paths = policy.satisfaction_paths();
+            assert_eq!(paths.len(), 2);
+
+            assert_eq!(paths[0].public_keys, vec![alice.clone()]);
+            assert_eq!(paths[0].hashes, vec![hash.clone()]);
+            assert_eq!(paths[0].signatures_required(), 1);
+            assert_eq!(paths[0].preimages_required(), 1);
+            assert_eq!(paths[0].min_timestamp, 0);
+            assert!(paths[0].is_unlocked(0, 0));
+
+            assert_eq!(paths[1].public_keys, vec![bob.clone()]);
+            assert!(paths[1].hashes.is_empty());
+            assert_eq!(paths[1].min_timestamp, 1_700_000_001);
+            assert!(!paths[1].is_unlocked(0, 1_700_000_000));
+            assert!(paths[1].is_unlocked(0, 1_700_000_001));
+
+            assert_eq!(policy.public_keys(), vec![alice.clone(), bob.clone()]);
+            assert_eq!(policy.hashes(), vec![hash]);
+
+            assert!(policy.is_satisfiable_with(&[alice.clone()], &[preimage]));
+            assert!(!policy.is_satisfiable_with(&[alice], &[]));
+            assert!(policy.is_satisfiable_with(&[bob], &[]));
+        }
+
+        fn test_spend_policy_satisfaction_paths_threshold() {
+            let keys: Vec<PublicKey> = (1..=3).map(|i| test_keypair(i).public()).collect();
+            let policy = SpendPolicy::Threshold {
+                n: 2,
+                of: keys.iter().cloned().map(SpendPolicy::PublicKey).collect(),
+            };
+
+            let paths = policy.satisfaction_paths();
+            assert_eq!(paths.len(), 3);
+            assert!(paths.iter().all(|path| path.signatures_required() == 2));
+            assert_eq!(paths[0].public_keys, vec![keys[0].clone(), keys[1].clone()]);
+
+            assert!(policy.is_satisfiable_with(&keys[1..], &[]));
+            assert!(!policy.is_satisfiable_with(&keys[..1], &[]));
+        }
+
+        fn test_spend_policy_satisfaction_paths_unsatisfiable() {
+            let public_key = test_keypair(1).public();
+
+            // opaque sub-policies can never be satisfied
+            let opaque = SpendPolicy::Threshold {
+                n: 2,
+                of: vec![SpendPolicy::PublicKey(public_key.clone()), SpendPolicy::After(1).opacify()],
+            };
+            assert!(opaque.satisfaction_paths().is_empty());
+            assert!(!opaque.is_satisfiable_with(&[public_key.clone()], &[]));
+
+            // n larger than the number of sub-policies
+            let threshold = SpendPolicy::Threshold {
+                n: 2,
+                of: vec![SpendPolicy::PublicKey(public_key.clone())],
+            };
+            assert!(threshold.satisfaction_paths().is_empty());
+
+            // UnlockConditions are only valid at the root of a policy
+            let nested = SpendPolicy::Threshold {
+                n: 1,
+                of: vec![SpendPolicy::unlock_condition(vec![public_key], 0, 1)],
+            };
+            assert!(nested.satisfaction_paths().is_empty());
+
+            assert_eq!(SpendPolicy::anyone_can_spend().satisfaction_paths().len(), 1);
+            assert!(SpendPolicy::anyone_can_spend().is_satisfiable_with(&[], &[]));
+        }
+
+        fn test_spend_policy_satisfaction_paths_unlock_conditions() {
+            let keys: Vec<PublicKey> = (1..=2).map(|i| test_keypair(i).public()).collect();
+            let policy = SpendPolicy::unlock_condition(keys.clone(), 100, 1);
+
+            let paths = policy.satisfaction_paths();
+            assert_eq!(paths.len(), 2);
+            assert!(paths.iter().all(|path| path.min_height == 100));
+            assert!(!paths[0].is_unlocked(99, 0));
+            assert!(paths[0].is_unlocked(100, 0));
+            assert_eq!(policy.public_keys(), keys);
+            assert!(policy.hashes().is_empty());
+
+            let non_standard = SpendPolicy::UnlockConditions(UnlockCondition {
+                unlock_keys: vec![UnlockKey::NonStandard {
+                    algorithm: Specifier::Unknown,
+                    public_key: vec![],
+                }],
+                timelock: 0,
+                signatures_required: 0,
+            });
+            assert!(non_standard.satisfaction_paths().is_empty());
+        }
+
+        fn test_spend_policy_string_invalid() {
+            let public_key = hex::encode(test_keypair(1).public().as_bytes());
+            let invalid = [
+                "".to_string(),
+                "above()".to_string(),
+                "above(1".to_string(),
+                "above(1))".to_string(),
+                "above(-1)".to_string(),
+                "thresh(256,[])".to_string(),
+                "thresh(1,[above(1),])".to_string(),
+                format!("pk({})", public_key),
+                format!("pk(0x{}00)", public_key),
+                "h(0x00)".to_string(),
+                "unknown(1)".to_string(),
+            ];
+            for input in invalid.iter() {
+                assert!(SpendPolicy::from_str(input).is_err(), "parsed invalid policy {:?}", input);
+            }
+        }
     }
 }
@@ -0,0 +1,208 @@
+//! An ApiClient serving canned chain state so ApiClientHelpers and the RefundWatcher can be exercised
+//! without a walletd instance.
+use crate::transport::client::{ApiClient, ApiClientError, ApiClientHelpers, Body, EndpointSchema};
+use crate::transport::endpoints::{AddressBalanceResponse, ConsensusTipstateResponse, ConsensusUpdatesResponse,
+                                  SiaApiRequest, TxpoolBroadcastRequest, TxpoolTransactionsResponse};
+use crate::types::{Address, ApiApplyUpdate, Block, BlockId, ChainIndex, Currency, Event, EventDataWrapper, EventType,
+                   Hash256, SiacoinElement, SiacoinOutputId, StateElement, Update, V2BlockData, V2Transaction};
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use serde_json::Value as JsonValue;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Default)]
+pub struct StubChain {
+    pub height: u64,
+    pub median_timestamp: u64,
+    pub siafund_tax_revenue: Option<Currency>,
+    pub utxos: Vec<SiacoinElement>,
+    pub txpool: Vec<V2Transaction>,
+    /// confirmed transactions and the height they were confirmed at
+    pub confirmed: Vec<(u64, V2Transaction)>,
+    pub broadcast: Vec<V2Transaction>,
+    /// the addresses whose events were requested, in order
+    pub event_queries: Vec<Address>,
+}
+
+impl StubChain {
+    /// confirm `tx` in the next block, spending its inputs and creating its outputs
+    pub fn confirm(&mut self, tx: V2Transaction) {
+        let spent: Vec<SiacoinOutputId> = tx.siacoin_inputs.iter().map(|input| input.parent.id.clone()).collect();
+        self.utxos.retain(|utxo| !spent.contains(&utxo.id));
+        self.txpool.retain(|pooled| pooled.txid() != tx.txid());
+        self.height += 1;
+
+        let txid = tx.txid();
+        for (index, output) in tx.siacoin_outputs.iter().enumerate() {
+            self.utxos.push(SiacoinElement {
+                id: SiacoinOutputId::new(txid.clone(), index as u32),
+                state_element: StateElement {
+                    leaf_index: self.utxos.len() as u64,
+                    merkle_proof: vec![],
+                },
+                siacoin_output: output.clone(),
+                maturity_height: 0,
+            });
+        }
+        self.confirmed.push((self.height, tx));
+    }
+
+    fn event(&self, height: u64, tx: &V2Transaction) -> Event {
+        Event {
+            id: tx.txid(),
+            index: ChainIndex {
+                height,
+                id: BlockId(Hash256::default()),
+            },
+            confirmations: self.height - height + 1,
+            timestamp: Utc.timestamp_opt(self.median_timestamp as i64, 0).unwrap(),
+            maturity_height: height,
+            event_type: EventType::V2Transaction,
+            data: EventDataWrapper::V2Transaction(tx.clone()),
+            relevant: None,
+        }
+    }
+}
+
+/// Serves each endpoint used by the helpers from a StubChain. Broadcast transactions are recorded
+/// and added to the txpool.
+#[derive(Clone, Default)]
+pub struct StubClient(Arc<Mutex<StubChain>>);
+
+impl StubClient {
+    pub fn chain(&self) -> MutexGuard<'_, StubChain> { self.0.lock().unwrap() }
+}
+
+fn not_found(path: &str) -> ApiClientError {
+    ApiClientError::UnexpectedHttpStatus {
+        status: http::StatusCode::NOT_FOUND,
+        body: path.to_string(),
+    }
+}
+
+#[async_trait]
+impl ApiClient for StubClient {
+    type Request = EndpointSchema;
+    type Response = JsonValue;
+    type Conf = ();
+
+    async fn new(_conf: Self::Conf) -> Result<Self, ApiClientError> { Ok(StubClient::default()) }
+
+    fn process_schema(&self, schema: EndpointSchema) -> Result<Self::Request, ApiClientError> { Ok(schema) }
+
+    async fn execute_request(&self, request: Self::Request) -> Result<Self::Response, ApiClientError> {
+        let mut chain = self.chain();
+        let tip = ChainIndex {
+            height: chain.height,
+            id: BlockId(Hash256::default()),
+        };
+        let path_param = |name: &str| request.path_params.as_ref().unwrap()[name].clone();
+        let response = match request.path_schema.as_str() {
+            "api/consensus/tip" | "api/consensus/index/{height}" => serde_json::to_value(tip)?,
+            "api/consensus/tipstate" => serde_json::to_value(ConsensusTipstateResponse {
+                index: tip,
+                prev_timestamps: vec![Utc.timestamp_opt(chain.median_timestamp as i64, 0).unwrap(); 11],
+                siafund_tax_revenue: chain.siafund_tax_revenue,
+            })?,
+            "api/addresses/{address}/outputs/siacoin" => {
+                let address: Address = path_param("address").parse().unwrap();
+                let utxos: Vec<&SiacoinElement> = chain
+                    .utxos
+                    .iter()
+                    .filter(|utxo| utxo.siacoin_output.address == address)
+                    .collect();
+                serde_json::to_value(utxos)?
+            },
+            "api/addresses/{address}/balance" => {
+                let address: Address = path_param("address").parse().unwrap();
+                let siacoins = chain
+                    .utxos
+                    .iter()
+                    .filter(|utxo| utxo.siacoin_output.address == address)
+                    .map(|utxo| *utxo.siacoin_output.value)
+                    .sum();
+                serde_json::to_value(AddressBalanceResponse {
+                    siacoins: Currency(siacoins),
+                    immature_siacoins: Currency::ZERO,
+                })?
+            },
+            "api/addresses/{address}/events" => {
+                let address: Address = path_param("address").parse().unwrap();
+                chain.event_queries.push(address.clone());
+                let events: Vec<Event> = chain
+                    .confirmed
+                    .iter()
+                    .filter(|(_, tx)| {
+                        tx.siacoin_outputs.iter().any(|output| output.address == address)
+                            || tx
+                                .siacoin_inputs
+                                .iter()
+                                .any(|input| input.parent.siacoin_output.address == address)
+                    })
+                    .map(|(height, tx)| chain.event(*height, tx))
+                    .collect();
+                serde_json::to_value(events)?
+            },
+            "api/events/{txid}" => {
+                let txid: Hash256 = path_param("txid").parse().unwrap();
+                let event = chain
+                    .confirmed
+                    .iter()
+                    .find(|(_, tx)| tx.txid() == txid)
+                    .map(|(height, tx)| chain.event(*height, tx))
+                    .ok_or_else(|| not_found(&request.path_schema))?;
+                serde_json::to_value(event)?
+            },
+            "api/txpool/transactions" => serde_json::to_value(TxpoolTransactionsResponse {
+                v2transactions: chain.txpool.clone(),
+                ..Default::default()
+            })?,
+            "api/txpool/broadcast" => {
+                let broadcast: TxpoolBroadcastRequest = match request.body {
+                    Body::Utf8(body) => serde_json::from_str(&body)?,
+                    _ => panic!("unexpected broadcast body"),
+                };
+                chain.txpool.extend(broadcast.v2transactions.clone());
+                chain.broadcast.extend(broadcast.v2transactions);
+                JsonValue::Null
+            },
+            "api/consensus/updates/{height}::{hash}" => {
+                let applied = chain
+                    .confirmed
+                    .iter()
+                    .map(|(height, tx)| ApiApplyUpdate {
+                        update: Update {
+                            spent: tx
+                                .siacoin_inputs
+                                .iter()
+                                .map(|input| input.parent.id.0.clone())
+                                .collect(),
+                        },
+                        block: Block {
+                            v2: V2BlockData {
+                                height: *height,
+                                commitment: Hash256::default(),
+                                transactions: vec![tx.clone()],
+                            },
+                        },
+                    })
+                    .collect();
+                serde_json::to_value(ConsensusUpdatesResponse { applied })?
+            },
+            path => panic!("unexpected request {}", path),
+        };
+        Ok(response)
+    }
+
+    async fn dispatcher<R: SiaApiRequest>(&self, request: R) -> Result<R::Response, ApiClientError> {
+        let request = self.to_data_request(request)?;
+        let response = self.execute_request(request).await?;
+        match R::is_empty_response() {
+            Some(empty) => Ok(empty),
+            None => Ok(serde_json::from_value(response)?),
+        }
+    }
+}
+
+#[async_trait]
+impl ApiClientHelpers for StubClient {}
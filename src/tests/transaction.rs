@@ -1,13 +1,167 @@
 #[cfg(test)]
 mod test {
-    use crate::encoding::Encoder;
-    use crate::types::{Address, Attestation, Currency, CurrencyVersion, FileContractRevisionV2, Hash256, Keypair,
-                       Preimage, PublicKey, SatisfiedPolicy, SiacoinElement, SiacoinInputV1, SiacoinInputV2,
-                       SiacoinOutput, SiacoinOutputId, SiacoinOutputVersion, Signature, SpendPolicy, StateElement,
-                       UnlockCondition, V2FileContract, V2FileContractElement, V2Transaction};
+    use crate::encoding::{Decoder, Encodable, Encoder};
+    use crate::types::{Address, Attestation, BlockId, ChainIndex, ChainIndexElement, CoveredFields, Currency,
+                       CurrencyVersion, FeePolicy, FileContractRevisionV2, Hash256, Keypair, Leaf, Preimage,
+                       PublicKey, ReplayPrefix, ResolutionType, SatisfiedPolicy, SiacoinElement, SiacoinInputV1,
+                       SiacoinInputV2, SiacoinOutput, SiacoinOutputId, SiacoinOutputVersion, SiafundElement,
+                       SiafundOutput, SiafundOutputId, Signature, SpendPolicy, StateElement, TransactionSignature,
+                       UnlockCondition, V1SignatureError, V1Transaction, V1TransactionBuilder, V2FileContract,
+                       V2FileContractElement, V2FileContractFinalization, V2FileContractRenewal,
+                       V2FileContractResolution, V2FileContractResolutionWrapper, V2StorageProof, V2Transaction,
+                       V2TransactionBuilder, V2TransactionBuilderError, V2TransactionFull};
     use std::convert::TryFrom;
     use std::str::FromStr;
 
+    fn test_v2_file_contract() -> V2FileContract {
+        let pubkey0 = PublicKey::from_bytes(
+            &hex::decode("0102030000000000000000000000000000000000000000000000000000000000").unwrap(),
+        )
+        .unwrap();
+        let pubkey1 = PublicKey::from_bytes(
+            &hex::decode("06C87838297B7BB16AB23946C99DFDF77FF834E35DB07D71E9B1D2B01A11E96D").unwrap(),
+        )
+        .unwrap();
+
+        let sig0 = Signature::try_from(
+            hex::decode("105641BF4AE119CB15617FC9658BEE5D448E2CC27C9BC3369F4BA5D0E1C3D01EBCB21B669A7B7A17CF8457189EAA657C41D4A2E6F9E0F25D0996D3A17170F309").unwrap()).unwrap();
+        let sig1 = Signature::try_from(
+            hex::decode("0734761D562958F6A82819474171F05A40163901513E5858BFF9E4BD9CAFB04DEF0D6D345BACE7D14E50C5C523433B411C7D7E1618BE010A63C55C34A2DEE70A").unwrap()).unwrap();
+
+        V2FileContract {
+            capacity: 0,
+            filesize: 1,
+            file_merkle_root: Hash256::default(),
+            proof_height: 1,
+            expiration_height: 1,
+            renter_output: SiacoinOutput {
+                value: 1u64.into(),
+                address: Address::standard_address_v1(&pubkey0),
+            },
+            host_output: SiacoinOutput {
+                value: 1u64.into(),
+                address: Address::standard_address_v1(&pubkey1),
+            },
+            missed_host_value: 1u64.into(),
+            total_collateral: 1u64.into(),
+            renter_public_key: pubkey0,
+            host_public_key: pubkey1,
+            revision_number: 1,
+            renter_signature: sig0,
+            host_signature: sig1,
+        }
+    }
+
+    fn test_v2_file_contract_resolution(resolution: V2FileContractResolutionWrapper) -> V2FileContractResolution {
+        let resolution_type = match resolution {
+            V2FileContractResolutionWrapper::Finalization(_) => ResolutionType::Finalization,
+            V2FileContractResolutionWrapper::Renewal(_) => ResolutionType::Renewal,
+            V2FileContractResolutionWrapper::StorageProof(_) => ResolutionType::StorageProof,
+            V2FileContractResolutionWrapper::Expiration => ResolutionType::Expiration,
+        };
+
+        V2FileContractResolution {
+            parent: V2FileContractElement {
+                id: Hash256::from_str("0707070000000000000000000000000000000000000000000000000000000000")
+                    .unwrap()
+                    .into(),
+                state_element: StateElement {
+                    leaf_index: 1,
+                    merkle_proof: vec![
+                        Hash256::from_str("0405060000000000000000000000000000000000000000000000000000000000").unwrap(),
+                        Hash256::from_str("0708090000000000000000000000000000000000000000000000000000000000").unwrap(),
+                    ],
+                },
+                v2_file_contract: test_v2_file_contract(),
+            },
+            resolution_type,
+            resolution,
+        }
+    }
+
+    fn test_v2_storage_proof() -> V2StorageProof {
+        V2StorageProof {
+            proof_index: ChainIndexElement {
+                state_element: StateElement {
+                    leaf_index: 2,
+                    merkle_proof: vec![Hash256::from_str(
+                        "0a0b0c0000000000000000000000000000000000000000000000000000000000",
+                    )
+                    .unwrap()],
+                },
+                chain_index: ChainIndex {
+                    height: 10,
+                    id: BlockId(
+                        Hash256::from_str("0d0e0f0000000000000000000000000000000000000000000000000000000000").unwrap(),
+                    ),
+                },
+            },
+            leaf: Leaf([7u8; 64]),
+            proof: vec![Hash256::from_str("1011120000000000000000000000000000000000000000000000000000000000").unwrap()],
+        }
+    }
+
+    /// the encoding of a resolution must be its parent, a type prefix and then the resolution itself
+    fn assert_resolution_layout(resolution: &V2FileContractResolution, type_prefix: u8) {
+        let mut expected = Encoder::default();
+        resolution.parent.encode(&mut expected);
+        expected.write_u8(type_prefix);
+        resolution.resolution.encode(&mut expected);
+
+        let mut encoder = Encoder::default();
+        resolution.encode(&mut encoder);
+        assert_eq!(encoder.buffer, expected.buffer);
+    }
+
+    fn test_v1_keypair(seed: u8) -> Keypair {
+        let mut private = [0u8; 32];
+        private[0] = seed;
+        Keypair::from_private_bytes(&private).unwrap()
+    }
+
+    /// a v1 transaction spending a 2-of-2 multisig UnlockCondition
+    fn test_v1_transaction(keypairs: &[&Keypair]) -> V1Transaction {
+        let unlock_condition = UnlockCondition::new(keypairs.iter().map(|kp| kp.public()).collect(), 0, 2);
+        V1Transaction {
+            siacoin_inputs: vec![SiacoinInputV1 {
+                parent_id: Hash256::from_str("0405060000000000000000000000000000000000000000000000000000000000")
+                    .unwrap()
+                    .into(),
+                unlock_condition,
+            }],
+            siacoin_outputs: vec![SiacoinOutput {
+                value: 1u64.into(),
+                address: keypairs[0].public().v1_address(),
+            }],
+            miner_fees: vec![Currency::DEFAULT_FEE],
+            ..Default::default()
+        }
+    }
+
+    /// a builder spending a single input locked by `policy`
+    fn test_v2_builder(policy: &SpendPolicy) -> V2TransactionBuilder {
+        let parent = SiacoinElement {
+            id: SiacoinOutputId(Hash256([7u8; 32])),
+            state_element: StateElement {
+                leaf_index: 7,
+                merkle_proof: vec![],
+            },
+            siacoin_output: SiacoinOutput {
+                value: Currency::COIN,
+                address: policy.address(),
+            },
+            maturity_height: 0,
+        };
+        let mut builder = V2TransactionBuilder::new();
+        builder
+            .add_siacoin_input(parent, policy.clone())
+            .add_siacoin_output(SiacoinOutput {
+                value: Currency::COIN,
+                address: SpendPolicy::PublicKey(test_v1_keypair(4).public()).address(),
+            });
+        builder
+    }
+
     cross_target_tests! {
         fn test_siacoin_input_encode() {
             let public_key = PublicKey::from_bytes(
@@ -747,5 +901,550 @@ mod test {
             );
             assert_eq!(output_id, expected);
         }
+
+        fn test_v2_file_contract_resolution_encode_finalization() {
+            let resolution = test_v2_file_contract_resolution(V2FileContractResolutionWrapper::Finalization(Box::new(
+                V2FileContractFinalization(test_v2_file_contract()),
+            )));
+            assert_resolution_layout(&resolution, 2);
+
+            let hash = Encoder::encode_and_hash(&resolution);
+            // FIXME add to rust_port_test.go and confirm
+            let expected = Hash256::from_str("b92616ac6e90f7a4835de77106a7b47b60c7b50e991dc35ae3266706ef5a7f42").unwrap();
+            assert_eq!(hash, expected);
+        }
+
+        fn test_v2_file_contract_resolution_encode_renewal() {
+            let renewal = V2FileContractRenewal {
+                final_revision: test_v2_file_contract(),
+                new_contract: V2FileContract {
+                    revision_number: 0,
+                    ..test_v2_file_contract()
+                },
+                renter_rollover: 2u64.into(),
+                host_rollover: 3u64.into(),
+                renter_signature: Signature::default(),
+                host_signature: Signature::default(),
+            };
+            let resolution = test_v2_file_contract_resolution(V2FileContractResolutionWrapper::Renewal(Box::new(renewal)));
+            assert_resolution_layout(&resolution, 0);
+
+            let hash = Encoder::encode_and_hash(&resolution);
+            // FIXME add to rust_port_test.go and confirm
+            let expected = Hash256::from_str("14c167f8d954148134f8b68210714cc393e12be4ade5536578ac305c5cbe9d3f").unwrap();
+            assert_eq!(hash, expected);
+        }
+
+        fn test_v2_file_contract_resolution_encode_storage_proof() {
+            let resolution =
+                test_v2_file_contract_resolution(V2FileContractResolutionWrapper::StorageProof(test_v2_storage_proof()));
+            assert_resolution_layout(&resolution, 1);
+
+            let hash = Encoder::encode_and_hash(&resolution);
+            // FIXME add to rust_port_test.go and confirm
+            let expected = Hash256::from_str("2129e8e220799d13628bbccc72367ac9a3c68f0f2fb6ca1c61e44402c42df4d3").unwrap();
+            assert_eq!(hash, expected);
+        }
+
+        fn test_v2_file_contract_resolution_encode_expiration() {
+            let resolution = test_v2_file_contract_resolution(V2FileContractResolutionWrapper::Expiration);
+            assert_resolution_layout(&resolution, 3);
+
+            // an expiration has no data of its own
+            let mut expected = Encoder::default();
+            resolution.parent.encode(&mut expected);
+            expected.write_u8(3);
+            assert_eq!(Encoder::encode_and_hash(&resolution), expected.hash());
+        }
+
+        fn test_v2_transaction_txid_with_resolutions() {
+            let mut tx = V2Transaction {
+                file_contract_resolutions: vec![
+                    test_v2_file_contract_resolution(V2FileContractResolutionWrapper::Finalization(Box::new(
+                        V2FileContractFinalization(test_v2_file_contract()),
+                    ))),
+                    test_v2_file_contract_resolution(V2FileContractResolutionWrapper::StorageProof(
+                        test_v2_storage_proof(),
+                    )),
+                    test_v2_file_contract_resolution(V2FileContractResolutionWrapper::Expiration),
+                ],
+                ..Default::default()
+            };
+            let txid = tx.txid();
+            let sig_hash = tx.input_sig_hash();
+
+            let mut encoder = Encoder::default();
+            V2TransactionFull(tx.clone()).encode(&mut encoder);
+            let decoded: V2TransactionFull = Decoder::decode_all(&encoder.buffer).unwrap();
+            assert_eq!(decoded.0, tx);
+            // FIXME add to rust_port_test.go and confirm
+            assert_eq!(txid, Hash256::from_str("7c583ab2bdd3da9537ba6a570bbe488390e7aca8775483603b69b0868dbe51fd").unwrap());
+
+            // signatures and storage proof merkle proofs are not covered by the txid or sig hash
+            if let V2FileContractResolutionWrapper::Finalization(f) = &mut tx.file_contract_resolutions[0].resolution {
+                f.0.renter_signature = Signature::default();
+            }
+            if let V2FileContractResolutionWrapper::StorageProof(sp) = &mut tx.file_contract_resolutions[1].resolution {
+                sp.proof_index.state_element.merkle_proof.clear();
+            }
+            assert_eq!(tx.txid(), txid);
+            assert_eq!(tx.input_sig_hash(), sig_hash);
+
+            // the parent is only committed to by its id
+            tx.file_contract_resolutions[2].parent.state_element.leaf_index = 99;
+            assert_eq!(tx.txid(), txid);
+        }
+
+        fn test_v1_transaction_sign_and_verify() {
+            let alice = test_v1_keypair(1);
+            let bob = test_v1_keypair(2);
+            let mut tx = test_v1_transaction(&[&alice, &bob]);
+
+            tx.sign_simple(ReplayPrefix::AsicHardfork, vec![&alice, &bob]);
+            assert_eq!(tx.signatures.len(), 2);
+            assert_eq!(tx.signatures[1].public_key_index, 1);
+            tx.verify_signature(ReplayPrefix::AsicHardfork, 0).unwrap();
+            tx.verify_signature(ReplayPrefix::AsicHardfork, 1).unwrap();
+
+            // whole transaction signatures are bound to the replay prefix
+            match tx.verify_signature(ReplayPrefix::V2Hardfork, 0) {
+                Err(V1SignatureError::InvalidSignature(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            tx.siacoin_outputs[0].value = 2u64.into();
+            match tx.verify_signature(ReplayPrefix::AsicHardfork, 0) {
+                Err(V1SignatureError::InvalidSignature(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_v1_transaction_sign_simple_signatures_required() {
+            let alice = test_v1_keypair(1);
+            let bob = test_v1_keypair(2);
+            let carol = test_v1_keypair(3);
+            let mut tx = test_v1_transaction(&[&alice, &bob, &carol]);
+
+            // the UnlockCondition requires 2 of 3 signatures, carol's is not needed
+            tx.sign_simple(ReplayPrefix::AsicHardfork, vec![&alice, &bob, &carol]);
+            assert_eq!(tx.signatures.len(), 2);
+            assert_eq!(tx.signatures[0].public_key_index, 0);
+            assert_eq!(tx.signatures[1].public_key_index, 1);
+
+            // signing again adds nothing
+            tx.sign_simple(ReplayPrefix::AsicHardfork, vec![&carol]);
+            assert_eq!(tx.signatures.len(), 2);
+
+            // a keypair that already signed is not counted twice
+            let mut tx = test_v1_transaction(&[&alice, &bob, &carol]);
+            tx.sign_simple(ReplayPrefix::AsicHardfork, vec![&alice, &alice]);
+            assert_eq!(tx.signatures.len(), 1);
+            tx.sign_simple(ReplayPrefix::AsicHardfork, vec![&alice, &carol]);
+            assert_eq!(tx.signatures.len(), 2);
+            assert_eq!(tx.signatures[1].public_key_index, 2);
+            tx.verify_signature(ReplayPrefix::AsicHardfork, 1).unwrap();
+        }
+
+        fn test_v1_transaction_sign_input_key_not_found() {
+            let alice = test_v1_keypair(1);
+            let bob = test_v1_keypair(2);
+            let mut tx = test_v1_transaction(&[&alice]);
+            let parent_id = tx.siacoin_inputs[0].parent_id.0.clone();
+
+            match tx.sign_input(ReplayPrefix::AsicHardfork, &parent_id, &bob) {
+                Err(V1SignatureError::KeyNotFound(id)) => assert_eq!(id, parent_id),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(tx.signatures.is_empty());
+        }
+
+        fn test_v1_transaction_partial_signature() {
+            let alice = test_v1_keypair(1);
+            let mut tx = test_v1_transaction(&[&alice]);
+            let covered_fields = CoveredFields {
+                siacoin_inputs: vec![0],
+                siacoin_outputs: vec![0],
+                ..Default::default()
+            };
+
+            let sig_hash = tx.partial_sig_hash(ReplayPrefix::AsicHardfork, &covered_fields).unwrap();
+            tx.signatures.push(TransactionSignature {
+                parent_id: tx.siacoin_inputs[0].parent_id.0.clone(),
+                public_key_index: 0,
+                timelock: 0,
+                covered_fields,
+                signature: alice.sign(&sig_hash.0).into(),
+            });
+            tx.verify_signature(ReplayPrefix::AsicHardfork, 0).unwrap();
+
+            // fields that are not covered can change without invalidating the signature
+            tx.miner_fees = vec![Currency::ZERO];
+            tx.verify_signature(ReplayPrefix::AsicHardfork, 0).unwrap();
+        }
+
+        fn test_v1_transaction_partial_sig_hash_out_of_bounds() {
+            let alice = test_v1_keypair(1);
+            let tx = test_v1_transaction(&[&alice]);
+            let covered_fields = CoveredFields {
+                siacoin_outputs: vec![5],
+                ..Default::default()
+            };
+
+            match tx.partial_sig_hash(ReplayPrefix::AsicHardfork, &covered_fields) {
+                Err(V1SignatureError::CoveredFieldIndex {
+                    field: "siacoin_outputs",
+                    index: 5,
+                    len: 1,
+                }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_v1_transaction_builder_multisig() {
+            let keypairs = [test_v1_keypair(1), test_v1_keypair(2), test_v1_keypair(3)];
+            let unlock_condition = UnlockCondition::new(keypairs.iter().map(|kp| kp.public()).collect(), 100, 2);
+            let address = unlock_condition.address();
+            let siacoin_parent = SiacoinOutputId(
+                Hash256::from_str("0405060000000000000000000000000000000000000000000000000000000000").unwrap(),
+            );
+            let siafund_parent = SiafundOutputId(
+                Hash256::from_str("0708090000000000000000000000000000000000000000000000000000000000").unwrap(),
+            );
+
+            let mut builder = V1TransactionBuilder::new();
+            builder
+                .add_siacoin_input(siacoin_parent, unlock_condition.clone())
+                .add_siacoin_output((address.clone(), Currency::COIN).into())
+                .add_siafund_input(siafund_parent, unlock_condition, address.clone())
+                .add_siafund_output(SiafundOutput { value: 1, address })
+                .add_miner_fee(Currency::DEFAULT_FEE)
+                .add_arbitrary_data(vec![1, 2, 3]);
+
+            // signatures can be collected over multiple calls
+            builder.sign_simple(ReplayPrefix::AsicHardfork, vec![&keypairs[0]]);
+            assert_eq!(builder.signatures.len(), 2);
+            builder.sign_simple(ReplayPrefix::AsicHardfork, vec![&keypairs[0], &keypairs[1], &keypairs[2]]);
+
+            // each input requires exactly 2 signatures
+            let tx = builder.build();
+            assert_eq!(tx.signatures.len(), 4);
+            for (i, sig) in tx.signatures.iter().enumerate() {
+                assert_eq!(sig.timelock, 100);
+                assert!(sig.public_key_index < 2);
+                tx.verify_signature(ReplayPrefix::AsicHardfork, i).unwrap();
+            }
+        }
+
+        fn test_v1_transaction_encode_decode() {
+            let alice = test_v1_keypair(1);
+            let mut builder = V1TransactionBuilder::new();
+            builder
+                .siacoin_inputs(test_v1_transaction(&[&alice]).siacoin_inputs)
+                .add_siacoin_output((alice.public().v1_address(), Currency::COIN).into())
+                .add_arbitrary_data(vec![4, 5, 6])
+                .sign_simple(ReplayPrefix::AsicHardfork, vec![&alice]);
+            let tx = builder.build();
+
+            let mut encoder = Encoder::default();
+            tx.encode(&mut encoder);
+            assert_eq!(builder.weight(), encoder.buffer.len() as u64);
+
+            let decoded: V1Transaction = Decoder::decode_all(&encoder.buffer).unwrap();
+            assert_eq!(decoded, tx);
+            assert_eq!(decoded.txid(), tx.txid());
+        }
+
+        fn test_replay_prefix_from_height() {
+            let from_height = |height| ReplayPrefix::from_height(height, 179_000, 298_000, 526_000);
+            assert_eq!(from_height(10), ReplayPrefix::PreAsicHardfork);
+            assert_eq!(from_height(179_000), ReplayPrefix::AsicHardfork);
+            assert_eq!(from_height(297_999), ReplayPrefix::AsicHardfork);
+            assert_eq!(from_height(298_000), ReplayPrefix::FoundationHardfork);
+            assert_eq!(from_height(526_000), ReplayPrefix::V2Hardfork);
+
+            assert_eq!(ReplayPrefix::PreAsicHardfork.prefix_byte(), None);
+            assert_eq!(ReplayPrefix::AsicHardfork.prefix_byte(), Some(0));
+            assert_eq!(ReplayPrefix::FoundationHardfork.prefix_byte(), Some(1));
+            assert_eq!(ReplayPrefix::V2Hardfork.prefix_byte(), Some(2));
+        }
+
+        fn test_v1_sig_hash_replay_prefix() {
+            let alice = test_v1_keypair(1);
+            let bob = test_v1_keypair(2);
+            let tx = test_v1_transaction(&[&alice, &bob]);
+            let covered_fields = CoveredFields {
+                siacoin_inputs: vec![0],
+                ..Default::default()
+            };
+
+            // each covered input is preceded by the era's prefix byte, if any
+            for replay_prefix in [
+                ReplayPrefix::PreAsicHardfork,
+                ReplayPrefix::AsicHardfork,
+                ReplayPrefix::FoundationHardfork,
+                ReplayPrefix::V2Hardfork,
+            ] {
+                let mut expected = Encoder::default();
+                if let Some(prefix) = replay_prefix.prefix_byte() {
+                    expected.write_u8(prefix);
+                }
+                tx.siacoin_inputs[0].encode(&mut expected);
+                assert_eq!(tx.partial_sig_hash(replay_prefix, &covered_fields).unwrap(), expected.hash());
+            }
+
+            let parent_id = tx.siacoin_inputs[0].parent_id.0.clone();
+            let whole_sig_hash = |replay_prefix| tx.whole_sig_hash(replay_prefix, &parent_id, 0, 0, &[]).unwrap();
+            // FIXME add to rust_port_test.go and confirm
+            assert_eq!(whole_sig_hash(ReplayPrefix::PreAsicHardfork), Hash256::from_str("23cc667747ef43ac96c57dda38705a5f697340a45bb408c3548a190bd39ae778").unwrap());
+            assert_eq!(whole_sig_hash(ReplayPrefix::AsicHardfork), Hash256::from_str("be6235f3544255fa1d5a2130a2895d4a8250ab4787b3893e5879a4a45d2d62e1").unwrap());
+            assert_eq!(whole_sig_hash(ReplayPrefix::FoundationHardfork), Hash256::from_str("88b93d77291562225594a726d34dd62093f956a216e03c30e71cbc36533c506b").unwrap());
+            assert_eq!(whole_sig_hash(ReplayPrefix::V2Hardfork), Hash256::from_str("a9afeaa3a2506822f4c9309f3da1577d5ad2f6bcf7c8cd2158726f05d07bdb4d").unwrap());
+        }
+
+        fn test_v2_transaction_builder_sign_policies_multisig() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_v1_keypair).collect();
+            let policy = SpendPolicy::threshold(2, keypairs.iter().map(|kp| SpendPolicy::PublicKey(kp.public())).collect());
+            let mut builder = test_v2_builder(&policy);
+
+            let unsatisfied = builder.sign_policies(vec![&keypairs[0], &keypairs[2]], vec![], 0, 0);
+            assert!(unsatisfied.is_empty());
+
+            let tx = builder.build();
+            let satisfied = &tx.siacoin_inputs[0].satisfied_policy;
+            let expected_policy = SpendPolicy::threshold(
+                2,
+                vec![
+                    SpendPolicy::PublicKey(keypairs[0].public()),
+                    SpendPolicy::PublicKey(keypairs[1].public()).opacify(),
+                    SpendPolicy::PublicKey(keypairs[2].public()),
+                ],
+            );
+            assert_eq!(satisfied.policy, expected_policy);
+            assert_eq!(satisfied.policy.address(), policy.address());
+            assert_eq!(satisfied.signatures.len(), 2);
+            satisfied.verify(0, 0, &tx.input_sig_hash()).unwrap();
+        }
+
+        fn test_v2_transaction_builder_sign_policies_multisig_unsatisfied() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_v1_keypair).collect();
+            let policy = SpendPolicy::threshold(2, keypairs.iter().map(|kp| SpendPolicy::PublicKey(kp.public())).collect());
+            let mut builder = test_v2_builder(&policy);
+
+            let unsatisfied = builder.sign_policies(vec![&keypairs[1]], vec![], 0, 0);
+            assert_eq!(unsatisfied.siacoin_inputs, vec![0]);
+            assert_eq!(builder.siacoin_inputs[0].satisfied_policy.policy, policy);
+            assert!(builder.siacoin_inputs[0].satisfied_policy.signatures.is_empty());
+        }
+
+        fn test_v2_transaction_builder_sign_policies_atomic_swap() {
+            let success = test_v1_keypair(1);
+            let refund = test_v1_keypair(2);
+            let secret = Preimage([9u8; 32]);
+            let policy = SpendPolicy::atomic_swap(&success.public(), &refund.public(), 100, &secret.compute_hash());
+
+            // the refund path is unavailable until the lock time has passed
+            let mut builder = test_v2_builder(&policy);
+            let unsatisfied = builder.sign_policies(vec![&refund], vec![], 0, 100);
+            assert_eq!(unsatisfied.siacoin_inputs, vec![0]);
+
+            let unsatisfied = builder.sign_policies(vec![&refund], vec![], 0, 101);
+            assert!(unsatisfied.is_empty());
+            let tx = builder.build();
+            tx.siacoin_inputs[0]
+                .satisfied_policy
+                .verify(0, 101, &tx.input_sig_hash())
+                .unwrap();
+
+            let mut builder = test_v2_builder(&policy);
+            let unsatisfied = builder.sign_policies(vec![&success], vec![secret.clone()], 0, 0);
+            assert!(unsatisfied.is_empty());
+            let tx = builder.build();
+            let satisfied = &tx.siacoin_inputs[0].satisfied_policy;
+            assert_eq!(satisfied.preimages, vec![secret]);
+            assert_eq!(satisfied.policy.address(), policy.address());
+            satisfied.verify(0, 0, &tx.input_sig_hash()).unwrap();
+        }
+
+        fn test_v2_transaction_builder_sign_policies_unlock_condition() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_v1_keypair).collect();
+            let policy = SpendPolicy::unlock_condition(keypairs.iter().map(|kp| kp.public()).collect(), 5, 2);
+
+            // the first `signatures_required` keys must sign
+            let mut builder = test_v2_builder(&policy);
+            let unsatisfied = builder.sign_policies(vec![&keypairs[0], &keypairs[2]], vec![], 5, 0);
+            assert_eq!(unsatisfied.siacoin_inputs, vec![0]);
+
+            let unsatisfied = builder.sign_policies(vec![&keypairs[0], &keypairs[1]], vec![], 4, 0);
+            assert_eq!(unsatisfied.siacoin_inputs, vec![0]);
+
+            let unsatisfied = builder.sign_policies(vec![&keypairs[0], &keypairs[1]], vec![], 5, 0);
+            assert!(unsatisfied.is_empty());
+            let tx = builder.build();
+            tx.siacoin_inputs[0]
+                .satisfied_policy
+                .verify(5, 0, &tx.input_sig_hash())
+                .unwrap();
+        }
+
+        fn test_spend_policy_max_satisfied_size() {
+            let keypairs: Vec<Keypair> = (1..=3).map(test_v1_keypair).collect();
+            let secret = Preimage([9u8; 32]);
+            let policies = vec![
+                SpendPolicy::PublicKey(keypairs[0].public()),
+                SpendPolicy::threshold(2, keypairs.iter().map(|kp| SpendPolicy::PublicKey(kp.public())).collect()),
+                SpendPolicy::atomic_swap(&keypairs[0].public(), &keypairs[1].public(), 100, &secret.compute_hash()),
+                SpendPolicy::unlock_condition(keypairs.iter().map(|kp| kp.public()).collect(), 0, 3),
+            ];
+            for policy in policies {
+                let mut builder = test_v2_builder(&policy);
+                assert!(builder.sign_policies(keypairs.iter().collect(), vec![secret.clone()], 0, 101).is_empty());
+                let tx = builder.build();
+
+                let mut encoder = Encoder::default();
+                tx.siacoin_inputs[0].satisfied_policy.encode(&mut encoder);
+                assert!(policy.max_satisfied_size() >= encoder.buffer.len() as u64, "{:?}", policy);
+                assert!(builder.satisfied_weight() >= tx.weight());
+            }
+
+            // a lone PublicKey policy has exactly one valid satisfaction
+            let policy = SpendPolicy::PublicKey(keypairs[0].public());
+            let mut builder = test_v2_builder(&policy);
+            builder.sign_policies(vec![&keypairs[0]], vec![], 0, 0);
+            assert_eq!(builder.satisfied_weight(), builder.build().weight());
+        }
+
+        fn test_v2_transaction_builder_apply_fee_policy() {
+            let keypair = test_v1_keypair(1);
+            let policy = SpendPolicy::PublicKey(keypair.public());
+            let rate = Currency(1_000_000_000_000_000_000);
+            let mut builder = test_v2_builder(&policy);
+            builder.siacoin_outputs[0].value = Currency(*Currency::COIN / 2);
+            builder.add_siacoin_output((policy.address(), Currency(*Currency::COIN / 2)).into());
+            builder.fee_policy = Some(FeePolicy::HastingsPerByte(rate));
+
+            builder.apply_fee_policy(&policy.address()).unwrap();
+            assert!(builder.sign_policies(vec![&keypair], vec![], 0, 0).is_empty());
+            let tx = builder.build();
+
+            assert_eq!(*tx.miner_fee, *rate * builder.satisfied_weight() as u128);
+            assert!(*tx.miner_fee >= *rate * tx.weight() as u128);
+            assert_eq!(*tx.siacoin_outputs[1].value, *Currency::COIN / 2 - *tx.miner_fee);
+            crate::validation::validate_v2_transaction(&tx, &crate::validation::ValidationContext::new(0, 0)).unwrap();
+        }
+
+        fn test_v2_transaction_builder_apply_fee_policy_dust_change() {
+            let keypair = test_v1_keypair(1);
+            let policy = SpendPolicy::PublicKey(keypair.public());
+            let mut builder = test_v2_builder(&policy);
+            builder.siacoin_outputs[0].value = Currency(*Currency::COIN - *Currency::DEFAULT_FEE - 1);
+            builder.add_siacoin_output((policy.address(), Currency(*Currency::DEFAULT_FEE + 1)).into());
+            builder.fee_policy = Some(FeePolicy::HastingsFixed(Currency::DEFAULT_FEE));
+
+            // the 1 hasting left over is dust
+            builder.apply_fee_policy(&policy.address()).unwrap();
+            assert_eq!(builder.siacoin_outputs.len(), 1);
+            assert_eq!(builder.miner_fee, Currency(*Currency::DEFAULT_FEE + 1));
+        }
+
+        fn test_v2_transaction_builder_apply_fee_policy_insufficient_change() {
+            let keypair = test_v1_keypair(1);
+            let policy = SpendPolicy::PublicKey(keypair.public());
+            let mut builder = test_v2_builder(&policy);
+            builder.fee_policy = Some(FeePolicy::HastingsFixed(Currency::DEFAULT_FEE));
+
+            match builder.apply_fee_policy(&policy.address()) {
+                Err(V2TransactionBuilderError::ChangeOutputNotFound(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            builder.add_siacoin_output((policy.address(), Currency(1)).into());
+            match builder.apply_fee_policy(&policy.address()) {
+                Err(V2TransactionBuilderError::InsufficientChange { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            builder.siacoin_outputs[1].value = Currency(u128::MAX);
+            builder.miner_fee = Currency::DEFAULT_FEE;
+            match builder.apply_fee_policy(&policy.address()) {
+                Err(V2TransactionBuilderError::ChangeOverflow { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_v2_transaction_builder_siafunds() {
+            let keypair = test_v1_keypair(1);
+            let policy = SpendPolicy::PublicKey(keypair.public());
+            let recipient = SpendPolicy::PublicKey(test_v1_keypair(2).public()).address();
+            let parent = SiafundElement {
+                id: SiafundOutputId(Hash256([3u8; 32])),
+                state_element: StateElement {
+                    leaf_index: 3,
+                    merkle_proof: vec![],
+                },
+                siafund_output: SiafundOutput {
+                    value: 10,
+                    address: policy.address(),
+                },
+                claim_start: Currency::ZERO,
+            };
+
+            let mut builder = V2TransactionBuilder::new();
+            builder
+                .add_siafund_input(parent, policy.address(), policy.clone())
+                .add_siafund_output(SiafundOutput {
+                    value: 7,
+                    address: recipient,
+                })
+                .add_siafund_output(SiafundOutput {
+                    value: 3,
+                    address: policy.address(),
+                })
+                .sign_simple(vec![&keypair]);
+            let tx = builder.build();
+
+            assert_eq!(tx.siafund_inputs[0].claim_address, policy.address());
+            assert_eq!(tx.siafund_inputs[0].satisfied_policy.signatures.len(), 1);
+            crate::validation::validate_v2_transaction(&tx, &crate::validation::ValidationContext::new(0, 0)).unwrap();
+        }
+
+        fn test_siafund_element_claim() {
+            let element = SiafundElement {
+                id: SiafundOutputId(Hash256([3u8; 32])),
+                state_element: StateElement {
+                    leaf_index: 3,
+                    merkle_proof: vec![],
+                },
+                siafund_output: SiafundOutput {
+                    value: 25,
+                    address: SpendPolicy::anyone_can_spend().address(),
+                },
+                claim_start: Currency(1_000_000),
+            };
+            let claim_address = SpendPolicy::PublicKey(test_v1_keypair(1).public()).address();
+
+            // 2_000_000 hastings accrued since claim_start, 200 per siafund
+            let claim = element.claim_element(&Currency(3_000_000), claim_address.clone(), 100);
+            assert_eq!(claim.siacoin_output.value, Currency(5000));
+            assert_eq!(claim.siacoin_output.address, claim_address);
+            assert_eq!(claim.maturity_height, 245);
+            assert_eq!(claim.id, element.id.v2_claim_output_id());
+            assert_eq!(claim.state_element.leaf_index, StateElement::UNASSIGNED_LEAF_INDEX);
+
+            // revenue that does not divide evenly among all siafunds is not paid out
+            assert_eq!(element.claim_value(&Currency(1_009_999)), Currency::ZERO);
+            assert_eq!(element.claim_value(&Currency(1_010_000)), Currency(25));
+        }
+
+        fn test_preimage_debug_redacted() {
+            let preimage = Preimage([7u8; 32]);
+            assert_eq!(format!("{:?}", preimage), "Preimage(<redacted>)");
+
+            let satisfied = SatisfiedPolicy {
+                policy: SpendPolicy::Hash(preimage.compute_hash()),
+                signatures: vec![],
+                preimages: vec![preimage],
+            };
+            assert!(!format!("{:?}", satisfied).contains(&format!("{:?}", [7u8; 32])));
+        }
     }
 }
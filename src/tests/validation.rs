@@ -0,0 +1,189 @@
+#[cfg(test)]
+mod test {
+    use crate::types::{Currency, Keypair, SiacoinElement, SiacoinOutput, SiacoinOutputId, SpendPolicy,
+                       SpendPolicyVerifyError, StateElement, V2Transaction, V2TransactionBuilder};
+    use crate::validation::{validate_v2_transaction, ValidationContext, ValidationError};
+
+    fn test_keypair(seed: u8) -> Keypair {
+        let mut private = [0u8; 32];
+        private[0] = seed;
+        Keypair::from_private_bytes(&private).unwrap()
+    }
+
+    fn test_siacoin_element(keypair: &Keypair, id_byte: u8, value: Currency) -> SiacoinElement {
+        let mut id = [0u8; 32];
+        id[0] = id_byte;
+        SiacoinElement {
+            id: SiacoinOutputId(crate::types::Hash256(id)),
+            state_element: StateElement {
+                leaf_index: id_byte as u64,
+                merkle_proof: vec![],
+            },
+            siacoin_output: SiacoinOutput {
+                value,
+                address: SpendPolicy::PublicKey(keypair.public()).address(),
+            },
+            maturity_height: 0,
+        }
+    }
+
+    /// 2 coin input paying 1 coin to a random address, 0.99 coin change and a 0.01 coin fee
+    fn test_builder(keypair: &Keypair) -> V2TransactionBuilder {
+        let policy = SpendPolicy::PublicKey(keypair.public());
+        let mut builder = V2TransactionBuilder::new();
+        builder
+            .add_siacoin_input(
+                test_siacoin_element(keypair, 1, Currency(2 * *Currency::COIN)),
+                policy.clone(),
+            )
+            .add_siacoin_output(SiacoinOutput {
+                value: Currency::COIN,
+                address: SpendPolicy::PublicKey(test_keypair(2).public()).address(),
+            })
+            .add_siacoin_output(SiacoinOutput {
+                value: Currency(*Currency::COIN / 100 * 99),
+                address: policy.address(),
+            })
+            .miner_fee(Currency(*Currency::COIN / 100));
+        builder
+    }
+
+    fn sign(builder: &mut V2TransactionBuilder, keypair: &Keypair) -> V2Transaction {
+        builder.sign_simple(vec![keypair]).build()
+    }
+
+    cross_target_tests! {
+        fn test_validate_v2_transaction_valid() {
+            let keypair = test_keypair(1);
+            let tx = sign(&mut test_builder(&keypair), &keypair);
+
+            validate_v2_transaction(&tx, &ValidationContext::new(10, 0)).unwrap();
+        }
+
+        fn test_validate_v2_transaction_imbalance() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            builder.miner_fee(Currency(*Currency::COIN / 50));
+            let tx = sign(&mut builder, &keypair);
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::SiacoinImbalance { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_duplicate_input() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            let input = builder.siacoin_inputs[0].clone();
+            builder.siacoin_inputs.push(input);
+            let tx = sign(&mut builder, &keypair);
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::DuplicateSiacoinInput(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_zero_value_output() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            builder.add_siacoin_output(SiacoinOutput {
+                value: Currency::ZERO,
+                address: SpendPolicy::PublicKey(keypair.public()).address(),
+            });
+            let tx = sign(&mut builder, &keypair);
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::ZeroValueSiacoinOutput(2)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_currency_overflow() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            builder.add_siacoin_output(SiacoinOutput {
+                value: Currency(u128::MAX),
+                address: SpendPolicy::PublicKey(keypair.public()).address(),
+            });
+            let tx = sign(&mut builder, &keypair);
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::CurrencyOverflow(_)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_immature_input() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            builder.siacoin_inputs[0].parent.maturity_height = 11;
+            let tx = sign(&mut builder, &keypair);
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::ImmatureSiacoinInput { index: 0, maturity_height: 11 }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_wrong_address() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            builder.siacoin_inputs[0].parent.siacoin_output.address =
+                SpendPolicy::PublicKey(test_keypair(2).public()).address();
+            let tx = sign(&mut builder, &keypair);
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::SiacoinInputAddress(0)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_unsigned() {
+            let keypair = test_keypair(1);
+            let tx = test_builder(&keypair).build();
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::SiacoinInputPolicy {
+                    index: 0,
+                    source: SpendPolicyVerifyError::InvalidSignature(_),
+                }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_signed_by_wrong_key() {
+            let keypair = test_keypair(1);
+            let mut builder = test_builder(&keypair);
+            let sig_hash = builder.input_sig_hash();
+            builder.siacoin_inputs[0]
+                .satisfied_policy
+                .signatures
+                .push(test_keypair(2).sign(&sig_hash.0));
+            let tx = builder.build();
+
+            match validate_v2_transaction(&tx, &ValidationContext::new(10, 0)) {
+                Err(ValidationError::SiacoinInputPolicy {
+                    index: 0,
+                    source: SpendPolicyVerifyError::InvalidSignature(_),
+                }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        fn test_validate_v2_transaction_weight() {
+            let keypair = test_keypair(1);
+            let tx = sign(&mut test_builder(&keypair), &keypair);
+            let ctx = ValidationContext {
+                max_weight: tx.weight() - 1,
+                ..ValidationContext::new(10, 0)
+            };
+
+            match validate_v2_transaction(&tx, &ctx) {
+                Err(ValidationError::Weight { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+}
@@ -10,7 +10,8 @@ use url::Url;
 #[cfg(target_arch = "wasm32")] pub mod wasm;
 
 mod helpers;
-pub use helpers::{ApiClientHelpers, HelperError};
+pub use helpers::{ApiClientHelpers, DiscoveredKey, FindSwapSecretError, FundTxError, GetSiafundClaimError,
+                  HelperError, SelectUtxosError, ValidateSwapFundingError};
 
 // FIXME remove these client specific error types
 #[cfg(not(target_arch = "wasm32"))]
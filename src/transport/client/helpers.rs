@@ -1,12 +1,18 @@
 use super::{ApiClient, ApiClientError};
+use crate::atomic_swap::{AtomicSwapError, SwapParams};
+use crate::coin_selection::{select_siafunds, CoinSelectionError, CoinSelector, LargestFirst, Selection,
+                            SelectionParams};
+use crate::seed::Seed;
 use crate::transport::endpoints::{AddressBalanceRequest, AddressBalanceResponse, AddressesEventsRequest,
                                   ConsensusIndexRequest, ConsensusTipRequest, ConsensusTipstateRequest,
                                   ConsensusTipstateResponse, ConsensusUpdatesRequest, ConsensusUpdatesResponse,
-                                  GetAddressUtxosRequest, GetEventRequest, TxpoolBroadcastRequest,
-                                  TxpoolTransactionsRequest};
-use crate::types::{Address, Currency, Event, EventDataWrapper, Hash256, PublicKey, SiacoinElement, SiacoinOutputId,
-                   SpendPolicy, TransactionId, V2Transaction, V2TransactionBuilder};
+                                  GetAddressSiafundUtxosRequest, GetAddressUtxosRequest, GetEventRequest,
+                                  TxpoolBroadcastRequest, TxpoolTransactionsRequest};
+use crate::types::{Address, Currency, Event, EventDataWrapper, Hash256, Preimage, PublicKey, SiacoinElement,
+                   SiacoinOutputId, SiafundElement, SiafundOutput, SpendPolicy, TransactionId, V1Transaction,
+                   V2Transaction, V2TransactionBuilder, V2TransactionBuilderError};
 use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -31,6 +37,16 @@ pub enum HelperError {
     UpdatesSinceHeight(#[from] UpdatesSinceHeightError),
     #[error("ApiClientHelpers::find_where_utxo_spent failed: {0}")]
     FindWhereUtxoSpent(#[from] FindWhereUtxoSpentError),
+    #[error("ApiClientHelpers::find_swap_secret failed: {0}")]
+    FindSwapSecret(#[from] FindSwapSecretError),
+    #[error("ApiClientHelpers::validate_swap_funding failed: {0}")]
+    ValidateSwapFunding(#[from] ValidateSwapFundingError),
+    #[error("ApiClientHelpers::get_siafund_claim failed: {0}")]
+    GetSiafundClaim(#[from] GetSiafundClaimError),
+    #[error("ApiClientHelpers::discover_seed_addresses failed: {0}")]
+    DiscoverSeedAddresses(ApiClientError),
+    #[error("ApiClientHelpers::fund_tx_multi_source failed: {0}")]
+    FundTx(#[from] FundTxError),
 }
 
 #[derive(Debug, Error)]
@@ -65,6 +81,20 @@ pub enum SelectUtxosError {
     Funding { available: Currency, required: Currency },
     #[error("ApiClientHelpers::select_unspent_outputs: failed to fetch UTXOs {0}")]
     FetchUtxos(#[from] ApiClientError),
+    #[error("ApiClientHelpers::select_unspent_outputs: {0}")]
+    CoinSelection(#[from] CoinSelectionError),
+}
+
+#[derive(Debug, Error)]
+pub enum FundTxError {
+    #[error("ApiClientHelpers::fund_tx_multi_source: no funding sources provided")]
+    NoSources,
+    #[error("ApiClientHelpers::fund_tx_multi_source: failed to fetch UTXOs {0}")]
+    FetchUtxos(#[from] ApiClientError),
+    #[error("ApiClientHelpers::fund_tx_multi_source: {0}")]
+    CoinSelection(#[from] CoinSelectionError),
+    #[error("ApiClientHelpers::fund_tx_multi_source: failed to apply fee policy: {0}")]
+    ApplyFeePolicy(#[from] V2TransactionBuilderError),
 }
 
 #[derive(Debug, Error)]
@@ -79,6 +109,25 @@ pub enum GetMedianTimestampError {
     TimestampVecLen(ConsensusTipstateResponse),
 }
 
+#[derive(Debug, Error)]
+pub enum GetSiafundClaimError {
+    #[error("ApiClientHelpers::get_siafund_claim: failed to fetch consensus tipstate: {0}")]
+    FetchTipstate(#[from] ApiClientError),
+    #[error("ApiClientHelpers::get_siafund_claim: consensus tipstate has no siafund tax revenue: {0:?}")]
+    MissingTaxRevenue(ConsensusTipstateResponse),
+}
+
+/// A seed derived key found to have been used by `ApiClientHelpers::discover_seed_addresses`
+#[derive(Clone, Debug)]
+pub struct DiscoveredKey {
+    pub index: u64,
+    pub public_key: PublicKey,
+    /// The balance of `public_key.address()`, None if the address has no events
+    pub balance: Option<AddressBalanceResponse>,
+    /// The balance of `public_key.v1_address()`, None if the address has no events
+    pub v1_balance: Option<AddressBalanceResponse>,
+}
+
 /// Helper methods for the ApiClient trait
 /// These generally provide higher level functionality than the base ApiClient trait
 /// This crate is focused on catering to the Komodo Defi Framework integration
@@ -106,9 +155,23 @@ pub trait ApiClientHelpers: ApiClient {
         .await
     }
 
+    async fn get_unspent_siafund_outputs(
+        &self,
+        address: &Address,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<SiafundElement>, ApiClientError> {
+        self.dispatcher(GetAddressSiafundUtxosRequest {
+            address: address.clone(),
+            limit,
+            offset,
+        })
+        .await
+    }
+
     /// Fetches unspent outputs for the given address and attempts to select a subset of outputs
     /// whose total value is at least `total_amount`. The outputs are sorted from largest to smallest to minimize
-    /// the number of outputs selected. The function returns a vector of the selected outputs and the difference between
+    /// the number of outputs selected, see `coin_selection::LargestFirst`. The function returns a vector of the selected outputs and the difference between
     /// the total value of the selected outputs and the required amount, aka the change.
     /// # Arguments
     ///
@@ -125,36 +188,42 @@ pub trait ApiClientHelpers: ApiClient {
         address: &Address,
         total_amount: Currency,
     ) -> Result<(Vec<SiacoinElement>, Currency), HelperError> {
-        let mut unspent_outputs = self
+        let unspent_outputs = self
             .get_unspent_outputs(address, None, None)
             .await
             .map_err(SelectUtxosError::FetchUtxos)?;
 
-        // Sort outputs from largest to smallest
-        unspent_outputs.sort_by(|a, b| b.siacoin_output.value.0.cmp(&a.siacoin_output.value.0));
-
-        let mut selected = Vec::new();
-        let mut selected_amount = Currency::ZERO;
-
-        // Select outputs until the total amount is reached
-        for output in unspent_outputs {
-            selected_amount += output.siacoin_output.value;
-            selected.push(output);
-
-            if selected_amount >= total_amount {
-                break;
-            }
-        }
+        let params = SelectionParams {
+            target: total_amount,
+            ..Default::default()
+        };
+        let selection = LargestFirst.select(unspent_outputs, &params).map_err(|e| match e {
+            CoinSelectionError::InsufficientFunds { available, required } => {
+                SelectUtxosError::Funding { available, required }
+            },
+            e => SelectUtxosError::CoinSelection(e),
+        })?;
+
+        Ok((selection.inputs, selection.change))
+    }
 
-        if selected_amount < total_amount {
-            return Err(SelectUtxosError::Funding {
-                available: selected_amount,
-                required: total_amount,
-            })?;
-        }
-        let change = selected_amount - total_amount;
+    /// Fetches unspent outputs for the given address and selects from them with the given strategy.
+    /// See `coin_selection` for the available strategies and `SelectionParams::from_builder` to derive
+    /// the params from a transaction builder.
+    async fn select_unspent_outputs_with(
+        &self,
+        address: &Address,
+        selector: &(dyn CoinSelector + Sync),
+        params: &SelectionParams,
+    ) -> Result<Selection, HelperError> {
+        let unspent_outputs = self
+            .get_unspent_outputs(address, None, None)
+            .await
+            .map_err(SelectUtxosError::FetchUtxos)?;
 
-        Ok((selected, change))
+        Ok(selector
+            .select(unspent_outputs, params)
+            .map_err(SelectUtxosError::CoinSelection)?)
     }
 
     /// Fund a transaction with utxos from the given address.
@@ -162,7 +231,8 @@ pub trait ApiClientHelpers: ApiClient {
     /// Assumes no file contracts or resolutions. This is a helper designed for Komodo DeFi Framework.
     /// Adds inputs from the given address until the total amount from outputs and miner_fee is reached.
     /// Adds the change amount to the transaction outputs
-    /// See `select_unspent_outputs` for more details on UTXO selection.
+    /// If the builder has a `fee_policy`, miner_fee is calculated from the weight of the satisfied
+    /// transaction. See `fund_tx_multi_source` for more details.
     /// # Arguments
     /// * `tx_builder` - A mutable reference to a `V2TransactionBuilder.
     /// * `public_key` - The public key of the address to spend utxos from.
@@ -170,31 +240,138 @@ pub trait ApiClientHelpers: ApiClient {
     /// * `Ok(())` - The transaction builder has been successfully funded
     /// * `Err(ApiClientHelpersError)` - An error is returned if the available outputs cannot meet
     ///     the required amount or a transport error is encountered.
-    // Alright TODO - move V2TransactionBuilder to a separate module then move this logic to a
-    // method of V2TransactionBuilder to allow chaining. It was included here because V2TransactionBuilder
-    // is currently inside the transaction module which is generally meant for consensnus related types.
-    // It would not be appropriate to include ApiClient-related code in transaction.rs
+    ///     As before fees were considered, insufficient funds and failing to fetch UTXOs are reported as
+    ///     `SelectUtxosError::Funding` and `SelectUtxosError::FetchUtxos`. `available` is now the value
+    ///     of the utxos net of the fee of spending each of them and `required` includes the miner fee.
     async fn fund_tx_single_source(
         &self,
         tx_builder: &mut V2TransactionBuilder,
         public_key: &PublicKey,
     ) -> Result<(), HelperError> {
-        let address = public_key.address();
-        let outputs_total: Currency = tx_builder.siacoin_outputs.iter().map(|output| output.value).sum();
+        self.fund_tx_multi_source(
+            tx_builder,
+            &[SpendPolicy::PublicKey(public_key.clone())],
+            &public_key.address(),
+            &LargestFirst,
+        )
+        .await
+        .map_err(|e| match e {
+            HelperError::FundTx(FundTxError::CoinSelection(CoinSelectionError::InsufficientFunds {
+                available,
+                required,
+            })) => SelectUtxosError::Funding { available, required }.into(),
+            HelperError::FundTx(FundTxError::FetchUtxos(e)) => SelectUtxosError::FetchUtxos(e).into(),
+            e => e,
+        })
+    }
 
-        // select utxos from public key's address that total at least the sum of outputs and miner fee
-        let (selected_utxos, change) = self
-            .select_unspent_outputs(&address, outputs_total + tx_builder.miner_fee)
-            .await?;
+    /// Fund a transaction with utxos from any of the given sources.
+    /// Each source is the SpendPolicy of an address the wallet can spend from, eg, a PublicKey policy
+    /// for a v2 address or an UnlockConditions policy for a v1 address. Each selected utxo is added as
+    /// an input with the policy of the source it was fetched from, ready to be signed with
+    /// `V2TransactionBuilder::sign_policies`.
+    /// This should be used only after all outputs have been added to the builder.
+    /// Assumes no file contracts or resolutions.
+    /// # Arguments
+    /// * `tx_builder` - A mutable reference to a `V2TransactionBuilder`.
+    /// * `sources` - The policies of the addresses to spend utxos from.
+    /// * `change_address` - The address any change is sent to.
+    /// * `selector` - The coin selection strategy. See `coin_selection`.
+    /// # Fees
+    /// If the builder has a `fee_policy`, the fee of each input is estimated from the largest of the
+    /// sources' policies and miner_fee is set accordingly. If a change output is created, the fee is
+    /// then recalculated from the exact inputs via `V2TransactionBuilder::apply_fee_policy`.
+    /// Otherwise, the builder's current miner_fee is paid.
+    async fn fund_tx_multi_source(
+        &self,
+        tx_builder: &mut V2TransactionBuilder,
+        sources: &[SpendPolicy],
+        change_address: &Address,
+        selector: &(dyn CoinSelector + Sync),
+    ) -> Result<(), HelperError> {
+        // fetch the utxos of every source, remembering which policy can spend each of them
+        let mut policies = HashMap::new();
+        let mut unspent_outputs = Vec::new();
+        let mut fetched_addresses = HashSet::new();
+        for policy in sources {
+            let address = policy.address();
+            if !fetched_addresses.insert(address.clone()) {
+                continue;
+            }
+            let utxos = self
+                .get_unspent_outputs(&address, None, None)
+                .await
+                .map_err(FundTxError::FetchUtxos)?;
+            for utxo in utxos {
+                policies.insert(utxo.id.clone(), policy.clone());
+                unspent_outputs.push(utxo);
+            }
+        }
 
-        // add selected utxos as inputs to the transaction
-        for utxo in &selected_utxos {
-            tx_builder.add_siacoin_input(utxo.clone(), SpendPolicy::PublicKey(public_key.clone()));
+        // the input fee is estimated from the largest policy so every input is accounted for
+        let largest_policy = sources
+            .iter()
+            .max_by_key(|policy| policy.max_satisfied_size())
+            .ok_or(FundTxError::NoSources)?;
+        let params = SelectionParams::from_builder(tx_builder, largest_policy);
+        let selection = selector
+            .select(unspent_outputs, &params)
+            .map_err(FundTxError::CoinSelection)?;
+
+        for utxo in selection.inputs {
+            let policy = policies[&utxo.id].clone();
+            tx_builder.add_siacoin_input(utxo, policy);
+        }
+        tx_builder.miner_fee = selection.fee;
+        if selection.change > Currency::ZERO {
+            tx_builder.add_siacoin_output((change_address.clone(), selection.change).into());
+            tx_builder
+                .apply_fee_policy(change_address)
+                .map_err(FundTxError::ApplyFeePolicy)?;
         }
 
-        if change > Currency::DUST {
-            // add change as an output
-            tx_builder.add_siacoin_output((address, change).into());
+        Ok(())
+    }
+
+    /// Fetches siafund unspent outputs for the given address and selects enough of them to cover
+    /// `amount` siafunds. Returns the selected outputs and the change. See `coin_selection::select_siafunds`.
+    async fn select_unspent_siafund_outputs(
+        &self,
+        address: &Address,
+        amount: u64,
+    ) -> Result<(Vec<SiafundElement>, u64), HelperError> {
+        let unspent_outputs = self
+            .get_unspent_siafund_outputs(address, None, None)
+            .await
+            .map_err(SelectUtxosError::FetchUtxos)?;
+
+        Ok(select_siafunds(unspent_outputs, amount).map_err(SelectUtxosError::CoinSelection)?)
+    }
+
+    /// Fund the siafund outputs of a transaction with siafund utxos from the address of `source`.
+    /// Siacoins accrued by the spent siafunds are claimed to `claim_address` and any siafund change
+    /// is sent to `change_address`.
+    /// Siafund inputs add weight to the transaction so this should be called before funding the miner
+    /// fee with `fund_tx_single_source` or `fund_tx_multi_source`.
+    /// Assumes the builder has no siafund inputs yet.
+    async fn fund_tx_siafunds(
+        &self,
+        tx_builder: &mut V2TransactionBuilder,
+        source: &SpendPolicy,
+        claim_address: &Address,
+        change_address: &Address,
+    ) -> Result<(), HelperError> {
+        let amount = tx_builder.siafund_outputs.iter().map(|output| output.value).sum();
+        let (selected_utxos, change) = self.select_unspent_siafund_outputs(&source.address(), amount).await?;
+
+        for utxo in selected_utxos {
+            tx_builder.add_siafund_input(utxo, claim_address.clone(), source.clone());
+        }
+        if change > 0 {
+            tx_builder.add_siafund_output(SiafundOutput {
+                value: change,
+                address: change_address.clone(),
+            });
         }
 
         Ok(())
@@ -269,6 +446,54 @@ pub trait ApiClientHelpers: ApiClient {
         self.dispatcher(request).await.map_err(HelperError::GetAddressEvents)
     }
 
+    /// Derive keys from `seed` in order, starting at index 0, and check both the v2 and v1 address of
+    /// each key for events. Discovery stops once `gap_limit` consecutive keys have no events on either
+    /// address. Returns the used keys along with the balance of each used address.
+    async fn discover_seed_addresses(&self, seed: &Seed, gap_limit: u64) -> Result<Vec<DiscoveredKey>, HelperError> {
+        let mut discovered = Vec::new();
+        let mut unused = 0;
+        let mut index = 0;
+        while unused < gap_limit {
+            let public_key = seed.keypair(index).public();
+            let mut balances = Vec::with_capacity(2);
+            for address in [public_key.address(), public_key.v1_address()] {
+                let events = self
+                    .dispatcher(AddressesEventsRequest {
+                        address: address.clone(),
+                        limit: Some(1),
+                        offset: None,
+                    })
+                    .await
+                    .map_err(HelperError::DiscoverSeedAddresses)?;
+                if events.is_empty() {
+                    balances.push(None);
+                    continue;
+                }
+                let balance = self
+                    .address_balance(address)
+                    .await
+                    .map_err(HelperError::DiscoverSeedAddresses)?;
+                balances.push(Some(balance));
+            }
+
+            let v1_balance = balances.pop().flatten();
+            let balance = balances.pop().flatten();
+            if balance.is_none() && v1_balance.is_none() {
+                unused += 1;
+            } else {
+                unused = 0;
+                discovered.push(DiscoveredKey {
+                    index,
+                    public_key,
+                    balance,
+                    v1_balance,
+                });
+            }
+            index += 1;
+        }
+        Ok(discovered)
+    }
+
     /// Fetch a v2 transaction from the blockchain
     // FIXME Alright - this should return a Result<Option<V2Transaction>, HelperError> to allow for
     // logic to handle the case where the transaction is not found in the blockchain
@@ -315,6 +540,26 @@ pub trait ApiClientHelpers: ApiClient {
         Ok(median_timestamp.timestamp() as u64)
     }
 
+    /// Predict the claim paid to `claim_address` if `element` is spent in the next block.
+    /// The returned SiacoinElement's value is the siacoins owed to the siafund holder and its
+    /// maturity height is the earliest height at which the claim can be spent.
+    async fn get_siafund_claim(
+        &self,
+        element: &SiafundElement,
+        claim_address: Address,
+    ) -> Result<SiacoinElement, HelperError> {
+        let tipstate = self
+            .dispatcher(ConsensusTipstateRequest)
+            .await
+            .map_err(GetSiafundClaimError::FetchTipstate)?;
+
+        let siafund_tax_revenue = match &tipstate.siafund_tax_revenue {
+            Some(siafund_tax_revenue) => siafund_tax_revenue,
+            None => return Err(GetSiafundClaimError::MissingTaxRevenue(tipstate))?,
+        };
+        Ok(element.claim_element(siafund_tax_revenue, claim_address, tipstate.index.height))
+    }
+
     async fn broadcast_transaction(&self, tx: &V2Transaction) -> Result<(), HelperError> {
         let request = TxpoolBroadcastRequest {
             transactions: vec![],
@@ -325,6 +570,17 @@ pub trait ApiClientHelpers: ApiClient {
         Ok(())
     }
 
+    /// Broadcast a v1 transaction. walletd rejects v1 transactions after the v2 require height.
+    async fn broadcast_v1_transaction(&self, tx: &V1Transaction) -> Result<(), HelperError> {
+        let request = TxpoolBroadcastRequest {
+            transactions: vec![tx.clone()],
+            v2transactions: vec![],
+        };
+
+        self.dispatcher(request).await.map_err(HelperError::BroadcastTx)?;
+        Ok(())
+    }
+
     async fn get_consensus_updates_since_height(
         &self,
         begin_height: u64,
@@ -387,6 +643,78 @@ pub trait ApiClientHelpers: ApiClient {
 
         Ok(Some(tx))
     }
+
+    /// Validate that the transaction `txid` funds the HTLC of `params`, see `validate_swap_funding_tx`
+    async fn validate_swap_funding(
+        &self,
+        params: &SwapParams,
+        txid: &TransactionId,
+    ) -> Result<SiacoinElement, HelperError> {
+        let tx = self.get_transaction(txid).await?;
+        self.validate_swap_funding_tx(params, &tx).await
+    }
+
+    /// Validate a counterparty's HTLC funding transaction against the agreed swap params.
+    /// `tx` must pay exactly `params.amount` to the address of `params.policy()`. The HTLC output
+    /// must also be confirmed and unspent. Returns the confirmed SiacoinElement, ready to be
+    /// redeemed with `SwapParams::redeem_transaction`.
+    async fn validate_swap_funding_tx(
+        &self,
+        params: &SwapParams,
+        tx: &V2Transaction,
+    ) -> Result<SiacoinElement, HelperError> {
+        let output_id = params
+            .find_htlc_output(tx)
+            .map_err(ValidateSwapFundingError::Mismatch)?;
+
+        // only confirmed outputs are returned so this also checks the funding tx is mined
+        let htlc_element = self
+            .get_unspent_outputs(&params.address(), None, None)
+            .await
+            .map_err(ValidateSwapFundingError::FetchUtxos)?
+            .into_iter()
+            .find(|element| element.id == output_id)
+            .ok_or(ValidateSwapFundingError::NotConfirmed(output_id))?;
+        Ok(htlc_element)
+    }
+
+    /// Find the secret revealed when the HTLC output of `params` was redeemed.
+    /// The mempool is checked first so the secret is available before the redeem transaction is
+    /// confirmed. Otherwise, the blockchain is scanned from `begin_height`, see `find_where_utxo_spent`.
+    /// Returns Ok(None) if the HTLC output has not been spent. Fails if it was spent by a refund.
+    async fn find_swap_secret(
+        &self,
+        params: &SwapParams,
+        htlc_output_id: &SiacoinOutputId,
+        begin_height: u64,
+    ) -> Result<Option<Preimage>, HelperError> {
+        let spends_htlc = |tx: &V2Transaction| tx.siacoin_inputs.iter().any(|input| input.parent.id == *htlc_output_id);
+
+        let unconfirmed_spend = self
+            .dispatcher(TxpoolTransactionsRequest)
+            .await
+            .map_err(FindSwapSecretError::FetchMempool)?
+            .v2transactions
+            .into_iter()
+            .find(spends_htlc);
+
+        let spend_tx = match unconfirmed_spend {
+            Some(tx) => tx,
+            None => match self
+                .find_where_utxo_spent(htlc_output_id, begin_height)
+                .await
+                .map_err(|e| FindSwapSecretError::FindSpend(Box::new(e)))?
+            {
+                Some(tx) => tx,
+                None => return Ok(None),
+            },
+        };
+
+        let secret = params
+            .extract_secret(&spend_tx, htlc_output_id)
+            .map_err(FindSwapSecretError::ExtractSecret)?;
+        Ok(Some(secret))
+    }
 }
 
 #[derive(Debug, Error)]
@@ -398,6 +726,27 @@ pub enum FindWhereUtxoSpentError {
     SpendNotInBlock { id: SiacoinOutputId },
 }
 
+#[derive(Debug, Error)]
+pub enum FindSwapSecretError {
+    #[error("ApiClientHelpers::find_swap_secret: failed to fetch mempool {0}")]
+    FetchMempool(ApiClientError),
+    // Boxed to allow HelperError to be held within itself
+    #[error("ApiClientHelpers::find_swap_secret: failed to find HTLC spend {0}")]
+    FindSpend(Box<HelperError>),
+    #[error("ApiClientHelpers::find_swap_secret: {0}")]
+    ExtractSecret(#[from] AtomicSwapError),
+}
+
+#[derive(Debug, Error)]
+pub enum ValidateSwapFundingError {
+    #[error("ApiClientHelpers::validate_swap_funding: {0}")]
+    Mismatch(#[from] AtomicSwapError),
+    #[error("ApiClientHelpers::validate_swap_funding: failed to fetch HTLC utxos {0}")]
+    FetchUtxos(ApiClientError),
+    #[error("ApiClientHelpers::validate_swap_funding: HTLC output {0} is unconfirmed or already spent")]
+    NotConfirmed(SiacoinOutputId),
+}
+
 #[derive(Debug, Error)]
 pub enum UpdatesSinceHeightError {
     #[error("ApiClientHelpers::get_consensus_updates_since_height: failed to fetch ChainIndex {0}")]
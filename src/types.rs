@@ -1,5 +1,5 @@
 use crate::blake2b_internal::standard_unlock_hash;
-use crate::encoding::{Encodable, Encoder};
+use crate::encoding::{Decodable, Decoder, DecoderError, Encodable, Encoder};
 use blake2b_simd::Params;
 use chrono::{DateTime, Utc};
 use derive_more::{Display, From, Into};
@@ -26,6 +26,9 @@ pub use spend_policy::*;
 mod transaction;
 pub use transaction::*;
 
+mod partially_signed;
+pub use partially_signed::*;
+
 mod specifier;
 pub use specifier::*;
 
@@ -37,7 +40,7 @@ const ADDRESS_CHECKSUM_LENGTH: usize = 6;
 
 // TODO this could probably include the checksum within the data type
 // generating the checksum on the fly is how Sia Go does this however
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct Address(pub Hash256);
 
 impl Serialize for Address {
@@ -89,6 +92,10 @@ impl Encodable for Address {
     fn encode(&self, encoder: &mut Encoder) { self.0.encode(encoder) }
 }
 
+impl Decodable for Address {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(Address(Hash256::decode(decoder)?)) }
+}
+
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let bytes = self.0 .0.as_ref();
@@ -158,6 +165,15 @@ impl Encodable for ChainIndex {
     }
 }
 
+impl Decodable for ChainIndex {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(ChainIndex {
+            height: decoder.read_u64()?,
+            id: BlockId(Hash256::decode(decoder)?),
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EventV1Transaction {
@@ -284,11 +300,3 @@ pub struct EventV2ContractResolution {
     pub siacoin_element: SiacoinElement,
     pub missed: Option<bool>,
 }
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ChainIndexElement {
-    #[serde(flatten)]
-    state_element: StateElement,
-    chain_index: ChainIndex,
-}
@@ -14,7 +14,7 @@ pub enum Hash256Error {
     #[error("Hash256::TryFrom<&[u8]> invalid slice length: expected 32 byte slice, found {0:?}")]
     InvalidSliceLength(Vec<u8>),
 }
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Hash256(pub [u8; 32]);
 
 impl Serialize for Hash256 {
@@ -1,10 +1,14 @@
 use curve25519_dalek::edwards::CompressedEdwardsY;
 use ed25519_dalek::{ExpandedSecretKey, PublicKey as Ed25519PublicKey, SecretKey,
                     SignatureError as Ed25519SignatureError, Signer, Verifier, SECRET_KEY_LENGTH};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 use thiserror::Error;
+use zeroize::Zeroizing;
 
 use crate::types::{Address, Signature, SpendPolicy};
 
@@ -35,6 +39,13 @@ impl Signer<Signature> for Keypair {
 }
 
 impl Keypair {
+    /// Generate a new keypair from the operating system's CSPRNG
+    pub fn generate() -> Self {
+        let mut bytes = Zeroizing::new([0u8; SECRET_KEY_LENGTH]);
+        OsRng.fill_bytes(bytes.as_mut());
+        Keypair::from_private_bytes(bytes.as_ref()).expect("any 32 bytes is a valid ed25519 private key")
+    }
+
     pub fn from_private_bytes(bytes: &[u8]) -> Result<Self, KeypairError> {
         let secret = SecretKey::from_bytes(bytes)?;
         let public = PublicKey(Ed25519PublicKey::from(&secret));
@@ -51,14 +62,33 @@ impl Keypair {
 
     pub fn public(&self) -> PublicKey { self.public.clone() }
 
-    pub fn private(&self) -> [u8; SECRET_KEY_LENGTH] { self.private.0.to_bytes() }
+    /// A copy of the private key that is zeroized when dropped
+    pub fn private(&self) -> Zeroizing<[u8; SECRET_KEY_LENGTH]> { Zeroizing::new(self.private.0.to_bytes()) }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair")
+            .field("public", &self.public)
+            .field("private", &self.private)
+            .finish()
+    }
 }
 
+/// ed25519_dalek::SecretKey zeroizes itself when dropped
 struct PrivateKey(SecretKey);
 
-#[derive(Clone, Debug, PartialEq)]
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("PrivateKey(<redacted>)") }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PublicKey(pub Ed25519PublicKey);
 
+impl Hash for PublicKey {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.0.as_bytes().hash(state) }
+}
+
 #[derive(Debug, Error)]
 pub enum PublicKeyError {
     #[error("invalid public key length: expected 32 byte hex string prefixed with 'ed25519:', found {0}")]
@@ -188,3 +218,27 @@ impl fmt::Display for PublicKey {
 impl fmt::LowerHex for PublicKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", hex::encode(self.as_bytes())) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    cross_target_tests! {
+        fn test_generate() {
+            let a = Keypair::generate();
+            let b = Keypair::generate();
+            assert_ne!(a.public(), b.public());
+
+            let keypair = Keypair::from_private_bytes(a.private().as_ref()).unwrap();
+            assert_eq!(keypair.public(), a.public());
+        }
+
+        fn test_debug_redacted() {
+            let keypair = Keypair::from_private_bytes(&[1u8; 32]).unwrap();
+            let debug = format!("{:?}", keypair);
+            assert!(debug.contains("PrivateKey(<redacted>)"));
+            assert!(!debug.contains(&hex::encode([1u8; 32])));
+            assert!(!debug.contains(&format!("{:?}", [1u8; 32])));
+        }
+    }
+}
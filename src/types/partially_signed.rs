@@ -0,0 +1,218 @@
+use crate::types::{Hash256, Keypair, Preimage, PublicKey, PublicKeyError, SatisfiedPolicy, Signature, SpendPolicy,
+                   UnsatisfiedInputs, V2Transaction, V2TransactionBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PartiallySignedError {
+    #[error("PartiallySignedV2Transaction::add_signature: {0} is not required by any input")]
+    SignatureNotRequired(Box<PublicKey>),
+    #[error("PartiallySignedV2Transaction::add_signature: invalid signature for {public_key}: {source}")]
+    InvalidSignature {
+        public_key: Box<PublicKey>,
+        source: PublicKeyError,
+    },
+    #[error("PartiallySignedV2Transaction::add_preimage: no input requires a preimage of {0}")]
+    PreimageNotRequired(Hash256),
+    #[error("PartiallySignedV2Transaction::merge: input sig hash {found} does not match {expected}")]
+    SigHashMismatch { expected: Hash256, found: Hash256 },
+    #[error("PartiallySignedV2Transaction::merge: input policies do not match")]
+    PolicyMismatch,
+    #[error("PartiallySignedV2Transaction::finalize: inputs are not satisfied {0:?}")]
+    Unsatisfied(UnsatisfiedInputs),
+}
+
+/// The signing state of a single input of a PartiallySignedV2Transaction
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartiallySignedInput {
+    pub policy: SpendPolicy,
+    /// signatures over the transaction's input sig hash from the public keys within `policy`
+    #[serde(default)]
+    pub signatures: HashMap<PublicKey, Signature>,
+    /// a slot for each Hash policy within `policy`, None until the preimage is provided
+    #[serde(default)]
+    pub preimages: HashMap<Hash256, Option<Preimage>>,
+}
+
+impl PartiallySignedInput {
+    fn new(policy: &SpendPolicy) -> Self {
+        PartiallySignedInput {
+            policy: policy.clone(),
+            signatures: HashMap::new(),
+            preimages: policy.hashes().into_iter().map(|hash| (hash, None)).collect(),
+        }
+    }
+
+    fn requires_public_key(&self, public_key: &PublicKey) -> bool { self.policy.public_keys().contains(public_key) }
+
+    fn satisfy(&self, height: u64, median_timestamp: u64, sig_hash: &Hash256) -> Option<SatisfiedPolicy> {
+        let preimages: Vec<Preimage> = self.preimages.values().flatten().cloned().collect();
+        let satisfied =
+            SatisfiedPolicy::with_signatures(&self.policy, height, median_timestamp, &self.signatures, &preimages)?;
+        satisfied.verify(height, median_timestamp, sig_hash).ok()?;
+        Some(satisfied)
+    }
+}
+
+/// A V2 transaction that is awaiting signatures or preimages from one or more parties.
+///
+/// The initiator constructs the transaction with V2TransactionBuilder and wraps it. The serialized
+/// container is then passed to each cosigner who adds their signatures against the input sig hash.
+/// Copies signed independently can be merged. Once every input is satisfied, `finalize` produces
+/// the V2Transaction ready for broadcast.
+///
+/// The builder must not be modified after wrapping it because any change to the transaction
+/// invalidates the signatures already collected.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartiallySignedV2Transaction {
+    pub builder: V2TransactionBuilder,
+    pub siacoin_inputs: Vec<PartiallySignedInput>,
+    pub siafund_inputs: Vec<PartiallySignedInput>,
+}
+
+impl PartiallySignedV2Transaction {
+    /// Wrap a builder whose inputs were added with the policies required to spend them.
+    /// Any signatures or preimages already present in the builder's inputs are ignored.
+    pub fn new(builder: V2TransactionBuilder) -> Self {
+        let siacoin_inputs = builder
+            .siacoin_inputs
+            .iter()
+            .map(|si| PartiallySignedInput::new(&si.satisfied_policy.policy))
+            .collect();
+        let siafund_inputs = builder
+            .siafund_inputs
+            .iter()
+            .map(|si| PartiallySignedInput::new(&si.satisfied_policy.policy))
+            .collect();
+        PartiallySignedV2Transaction {
+            builder,
+            siacoin_inputs,
+            siafund_inputs,
+        }
+    }
+
+    /// The hash every input signature must be produced over
+    pub fn sig_hash(&self) -> Hash256 { self.builder.input_sig_hash() }
+
+    fn inputs_mut(&mut self) -> impl Iterator<Item = &mut PartiallySignedInput> {
+        self.siacoin_inputs.iter_mut().chain(self.siafund_inputs.iter_mut())
+    }
+
+    /// Sign every input whose policy includes the keypair's public key
+    pub fn sign(&mut self, keypair: &Keypair) -> Result<&mut Self, PartiallySignedError> {
+        let signature = keypair.sign(&self.sig_hash().0);
+        self.add_signature(&keypair.public(), signature)
+    }
+
+    /// Add a signature produced elsewhere, eg, by a hardware wallet, to every input whose policy includes
+    /// `public_key`. The signature is verified against the input sig hash before it is accepted.
+    pub fn add_signature(
+        &mut self,
+        public_key: &PublicKey,
+        signature: Signature,
+    ) -> Result<&mut Self, PartiallySignedError> {
+        public_key
+            .verify(&self.sig_hash().0, &signature)
+            .map_err(|source| PartiallySignedError::InvalidSignature {
+                public_key: Box::new(public_key.clone()),
+                source,
+            })?;
+
+        let mut required = false;
+        for input in self.inputs_mut() {
+            if input.requires_public_key(public_key) {
+                input.signatures.insert(public_key.clone(), signature.clone());
+                required = true;
+            }
+        }
+        if !required {
+            return Err(PartiallySignedError::SignatureNotRequired(Box::new(public_key.clone())));
+        }
+        Ok(self)
+    }
+
+    /// Fill every pending preimage slot matching the hash of `preimage`
+    pub fn add_preimage(&mut self, preimage: Preimage) -> Result<&mut Self, PartiallySignedError> {
+        let hash = preimage.compute_hash();
+        let mut required = false;
+        for input in self.inputs_mut() {
+            if let Some(slot) = input.preimages.get_mut(&hash) {
+                *slot = Some(preimage.clone());
+                required = true;
+            }
+        }
+        if !required {
+            return Err(PartiallySignedError::PreimageNotRequired(hash));
+        }
+        Ok(self)
+    }
+
+    /// Merge the signatures and preimages collected in another copy of the same transaction
+    pub fn merge(&mut self, other: &PartiallySignedV2Transaction) -> Result<&mut Self, PartiallySignedError> {
+        let expected = self.sig_hash();
+        let found = other.sig_hash();
+        if expected != found {
+            return Err(PartiallySignedError::SigHashMismatch { expected, found });
+        }
+        let policies = |inputs: &[PartiallySignedInput]| inputs.iter().map(|i| i.policy.clone()).collect::<Vec<_>>();
+        if policies(&self.siacoin_inputs) != policies(&other.siacoin_inputs)
+            || policies(&self.siafund_inputs) != policies(&other.siafund_inputs)
+        {
+            return Err(PartiallySignedError::PolicyMismatch);
+        }
+
+        for input in other.siacoin_inputs.iter().chain(other.siafund_inputs.iter()) {
+            for (public_key, signature) in &input.signatures {
+                self.add_signature(public_key, signature.clone())?;
+            }
+            for preimage in input.preimages.values().flatten() {
+                self.add_preimage(preimage.clone())?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// The inputs that cannot yet be satisfied at the given height and median timestamp
+    pub fn unsatisfied(&self, height: u64, median_timestamp: u64) -> UnsatisfiedInputs {
+        let sig_hash = self.sig_hash();
+        let unsatisfied = |inputs: &[PartiallySignedInput]| {
+            inputs
+                .iter()
+                .enumerate()
+                .filter(|(_, input)| input.satisfy(height, median_timestamp, &sig_hash).is_none())
+                .map(|(index, _)| index)
+                .collect()
+        };
+        UnsatisfiedInputs {
+            siacoin_inputs: unsatisfied(&self.siacoin_inputs),
+            siafund_inputs: unsatisfied(&self.siafund_inputs),
+        }
+    }
+
+    /// Produce the signed V2Transaction. `height` and `median_timestamp` should be those of the block
+    /// the transaction is expected to be included in.
+    pub fn finalize(&self, height: u64, median_timestamp: u64) -> Result<V2Transaction, PartiallySignedError> {
+        let sig_hash = self.sig_hash();
+        let mut builder = self.builder.clone();
+        let mut unsatisfied = UnsatisfiedInputs::default();
+        for (index, (si, input)) in builder.siacoin_inputs.iter_mut().zip(&self.siacoin_inputs).enumerate() {
+            match input.satisfy(height, median_timestamp, &sig_hash) {
+                Some(satisfied) => si.satisfied_policy = satisfied,
+                None => unsatisfied.siacoin_inputs.push(index),
+            }
+        }
+        for (index, (si, input)) in builder.siafund_inputs.iter_mut().zip(&self.siafund_inputs).enumerate() {
+            match input.satisfy(height, median_timestamp, &sig_hash) {
+                Some(satisfied) => si.satisfied_policy = satisfied,
+                None => unsatisfied.siafund_inputs.push(index),
+            }
+        }
+        if !unsatisfied.is_empty() {
+            return Err(PartiallySignedError::Unsatisfied(unsatisfied));
+        }
+        Ok(builder.build())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::encoding::{Encodable, Encoder};
+use crate::encoding::{Decodable, Decoder, DecoderError, Encodable, Encoder};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::str::FromStr;
@@ -48,6 +48,10 @@ impl Encodable for Specifier {
     fn encode(&self, encoder: &mut Encoder) { encoder.write_slice(self.as_bytes()); }
 }
 
+impl Decodable for Specifier {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(Specifier::from_bytes(&decoder.read_array()?)) }
+}
+
 impl Specifier {
     pub fn as_bytes(&self) -> &'static [u8; 16] {
         match self {
@@ -62,6 +66,20 @@ impl Specifier {
         }
     }
 
+    /// Any unrecognized specifier is mapped to Specifier::Unknown
+    pub fn from_bytes(bytes: &[u8; 16]) -> Self {
+        match *bytes {
+            ED25519 => Specifier::Ed25519,
+            SIACOIN_OUTPUT => Specifier::SiacoinOutput,
+            SIAFUND_OUTPUT => Specifier::SiafundOutput,
+            FILE_CONTRACT => Specifier::FileContract,
+            STORAGE_PROOF => Specifier::StorageProof,
+            FOUNDATION => Specifier::Foundation,
+            ENTROPY => Specifier::Entropy,
+            _ => Specifier::Unknown,
+        }
+    }
+
     pub fn from_str_expect(s: &str) -> Self { Specifier::from_str(s).expect("from_str cannot return Err") }
 
     pub fn to_str(&self) -> &'static str {
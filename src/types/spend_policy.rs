@@ -1,17 +1,29 @@
 use crate::blake2b_internal::{public_key_leaf, sigs_required_leaf, standard_unlock_hash, timelock_leaf, Accumulator};
-use crate::encoding::{Encodable, Encoder};
-use crate::types::{Address, Hash256, PublicKey, Specifier};
-use nom::bytes::complete::{take_until, take_while, take_while_m_n};
-use nom::character::complete::char;
+use crate::encoding::{Decodable, Decoder, DecoderError, Encodable, Encoder};
+use crate::types::{Address, Hash256, Preimage, PublicKey, Signature, Specifier};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until, take_while, take_while_m_n};
+use nom::character::complete::{char, digit1};
 use nom::combinator::all_consuming;
-use nom::combinator::map_res;
+use nom::combinator::{map, map_res};
+use nom::error::ErrorKind;
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, preceded, tuple};
 use nom::IResult;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::slice::Iter;
 use std::str::FromStr;
+use thiserror::Error;
 
 const POLICY_VERSION: u8 = 1u8;
 
+// Sia Go rejects policies with more than 1024 total nodes while decoding
+const MAX_POLICIES: usize = 1024;
+// the maximum nesting of thresholds accepted by `SpendPolicy::from_str`. Each level of nesting
+// costs several parser stack frames so this is far lower than `MAX_POLICIES`.
+const MAX_POLICY_DEPTH: usize = 64;
+
 /*
 The full representation of the atomic swap contract is as follows:
     SpendPolicy::Threshold {
@@ -118,6 +130,15 @@ impl Encodable for SpendPolicy {
     }
 }
 
+impl Decodable for SpendPolicy {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        match decoder.read_u8()? {
+            POLICY_VERSION => SpendPolicy::decode_wo_prefix(decoder),
+            version => Err(DecoderError::PolicyVersion(version)),
+        }
+    }
+}
+
 impl SpendPolicy {
     pub fn to_u8(&self) -> u8 {
         match self {
@@ -174,6 +195,35 @@ impl SpendPolicy {
         }
     }
 
+    pub fn decode_wo_prefix(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        fn rec(decoder: &mut Decoder, total_policies: &mut usize) -> Result<SpendPolicy, DecoderError> {
+            match decoder.read_u8()? {
+                1 => Ok(SpendPolicy::Above(decoder.read_u64()?)),
+                2 => Ok(SpendPolicy::After(decoder.read_u64()?)),
+                3 => Ok(SpendPolicy::PublicKey(PublicKey::decode(decoder)?)),
+                4 => Ok(SpendPolicy::Hash(Hash256::decode(decoder)?)),
+                5 => {
+                    let n = decoder.read_u8()?;
+                    let len = decoder.read_u8()? as usize;
+                    *total_policies += len;
+                    if *total_policies > MAX_POLICIES {
+                        return Err(DecoderError::PolicyTooComplex(MAX_POLICIES));
+                    }
+                    let of = (0..len)
+                        .map(|_| rec(decoder, total_policies))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(SpendPolicy::Threshold { n, of })
+                },
+                6 => Ok(SpendPolicy::Opaque(Address::decode(decoder)?)),
+                7 => Ok(SpendPolicy::UnlockConditions(UnlockCondition::decode(decoder)?)),
+                opcode => Err(DecoderError::PolicyOpcode(opcode)),
+            }
+        }
+
+        let mut total_policies = 1;
+        rec(decoder, &mut total_policies)
+    }
+
     pub fn address(&self) -> Address {
         if let SpendPolicy::UnlockConditions(unlock_condition) = self {
             return unlock_condition.address();
@@ -182,10 +232,18 @@ impl SpendPolicy {
         encoder.write_distinguisher("address");
 
         // if self is a threshold policy, we need to convert all of its subpolicies to opaque
+        // subpolicies that are already opaque are left as is so that opacifying a subpolicy does not
+        // change the address of its parent
         let new_policy = match self {
             SpendPolicy::Threshold { n, of } => SpendPolicy::Threshold {
                 n: *n,
-                of: of.iter().map(SpendPolicy::opaque).collect(),
+                of: of
+                    .iter()
+                    .map(|policy| match policy {
+                        SpendPolicy::Opaque(_) => policy.clone(),
+                        _ => SpendPolicy::opaque(policy),
+                    })
+                    .collect(),
             },
             _ => self.clone(),
         };
@@ -215,6 +273,427 @@ impl SpendPolicy {
     pub fn opacify(&self) -> Self { SpendPolicy::Opaque(self.address()) }
 }
 
+#[derive(Debug, Error)]
+pub enum SpendPolicyVerifyError {
+    #[error("SpendPolicy::verify: height {height} is not above {above}")]
+    HeightNotAbove { height: u64, above: u64 },
+    #[error("SpendPolicy::verify: median timestamp {median_timestamp} is not after {after}")]
+    TimestampNotAfter { median_timestamp: u64, after: u64 },
+    #[error("SpendPolicy::verify: missing or invalid signature for public key {0}")]
+    InvalidSignature(Box<PublicKey>),
+    #[error("SpendPolicy::verify: missing or invalid preimage for hash {0}")]
+    InvalidPreimage(Hash256),
+    #[error("SpendPolicy::verify: threshold not reached, {remaining} more satisfied policies required")]
+    ThresholdNotReached { remaining: u8 },
+    #[error("SpendPolicy::verify: opaque policy {0} cannot be satisfied")]
+    Opaque(Address),
+    #[error("SpendPolicy::verify: UnlockConditions cannot be sub-policies")]
+    UnlockConditionsSubPolicy,
+    #[error("SpendPolicy::verify: too many signatures required: {0} > 255")]
+    TooManySignaturesRequired(u64),
+    #[error("SpendPolicy::verify: unsupported UnlockKey {0:?}")]
+    UnsupportedUnlockKey(Box<UnlockKey>),
+    #[error("SpendPolicy::verify: superfluous signature(s)")]
+    SuperfluousSignatures,
+    #[error("SpendPolicy::verify: superfluous preimage(s)")]
+    SuperfluousPreimages,
+}
+
+impl SpendPolicyVerifyError {
+    /// A missing or invalid signature or preimage is fatal even within a Threshold because the
+    /// unsatisfied policy should have been opacified instead.
+    fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            SpendPolicyVerifyError::InvalidSignature(_) | SpendPolicyVerifyError::InvalidPreimage(_)
+        )
+    }
+}
+
+/// Walks a policy tree consuming signatures and preimages in the order their policies appear
+struct PolicyVerifier<'a> {
+    height: u64,
+    median_timestamp: u64,
+    sig_hash: &'a Hash256,
+    signatures: Iter<'a, Signature>,
+    preimages: Iter<'a, Preimage>,
+}
+
+impl<'a> PolicyVerifier<'a> {
+    fn verify(&mut self, policy: &SpendPolicy) -> Result<(), SpendPolicyVerifyError> {
+        match policy {
+            SpendPolicy::Above(above) if self.height >= *above => Ok(()),
+            SpendPolicy::Above(above) => Err(SpendPolicyVerifyError::HeightNotAbove {
+                height: self.height,
+                above: *above,
+            }),
+            SpendPolicy::After(after) if self.median_timestamp > *after => Ok(()),
+            SpendPolicy::After(after) => Err(SpendPolicyVerifyError::TimestampNotAfter {
+                median_timestamp: self.median_timestamp,
+                after: *after,
+            }),
+            SpendPolicy::PublicKey(public_key) => match self.signatures.next() {
+                Some(sig) if public_key.verify(&self.sig_hash.0, sig).is_ok() => Ok(()),
+                _ => Err(SpendPolicyVerifyError::InvalidSignature(Box::new(public_key.clone()))),
+            },
+            SpendPolicy::Hash(hash) => match self.preimages.next() {
+                Some(preimage) if &preimage.compute_hash() == hash => Ok(()),
+                _ => Err(SpendPolicyVerifyError::InvalidPreimage(hash.clone())),
+            },
+            SpendPolicy::Threshold { n, of } => {
+                let mut remaining = *n;
+                for (i, sub_policy) in of.iter().enumerate() {
+                    // stop once satisfied or when the remaining policies can no longer reach the threshold
+                    if remaining == 0 || of.len() - i < remaining as usize {
+                        break;
+                    }
+                    if let SpendPolicy::UnlockConditions(_) = sub_policy {
+                        return Err(SpendPolicyVerifyError::UnlockConditionsSubPolicy);
+                    }
+                    match self.verify(sub_policy) {
+                        Ok(()) => remaining -= 1,
+                        Err(e) if e.is_fatal() => return Err(e),
+                        Err(_) => (),
+                    }
+                }
+                match remaining {
+                    0 => Ok(()),
+                    remaining => Err(SpendPolicyVerifyError::ThresholdNotReached { remaining }),
+                }
+            },
+            SpendPolicy::Opaque(address) => Err(SpendPolicyVerifyError::Opaque(address.clone())),
+            SpendPolicy::UnlockConditions(uc) => {
+                self.verify(&SpendPolicy::Above(uc.timelock))?;
+                if uc.signatures_required > u8::MAX as u64 {
+                    return Err(SpendPolicyVerifyError::TooManySignaturesRequired(
+                        uc.signatures_required,
+                    ));
+                }
+                let of = uc
+                    .unlock_keys
+                    .iter()
+                    .map(|key| match key {
+                        UnlockKey::Ed25519(public_key) => Ok(SpendPolicy::PublicKey(public_key.clone())),
+                        _ => Err(SpendPolicyVerifyError::UnsupportedUnlockKey(Box::new(key.clone()))),
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.verify(&SpendPolicy::Threshold {
+                    n: uc.signatures_required as u8,
+                    of,
+                })
+            },
+        }
+    }
+}
+
+impl SpendPolicy {
+    /// Check that the provided signatures and preimages satisfy the policy at the given chain height
+    /// and median timestamp. Signatures and preimages are consumed in the order their PublicKey and
+    /// Hash policies appear in the tree and any left over are rejected.
+    // https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/types/policy.go#L94
+    pub fn verify(
+        &self,
+        height: u64,
+        median_timestamp: u64,
+        sig_hash: &Hash256,
+        signatures: &[Signature],
+        preimages: &[Preimage],
+    ) -> Result<(), SpendPolicyVerifyError> {
+        let mut verifier = PolicyVerifier {
+            height,
+            median_timestamp,
+            sig_hash,
+            signatures: signatures.iter(),
+            preimages: preimages.iter(),
+        };
+        verifier.verify(self)?;
+        if verifier.signatures.next().is_some() {
+            return Err(SpendPolicyVerifyError::SuperfluousSignatures);
+        }
+        if verifier.preimages.next().is_some() {
+            return Err(SpendPolicyVerifyError::SuperfluousPreimages);
+        }
+        Ok(())
+    }
+
+    /// An upper bound on the encoded size of any SatisfiedPolicy for this policy. Used to estimate
+    /// the weight of a transaction before its inputs are signed.
+    /// Threshold sub-policies that do not contribute are assumed to be opacified as done by
+    /// `SatisfiedPolicy::sign`.
+    pub fn max_satisfied_size(&self) -> u64 {
+        // the policy version prefix is only written at the root
+        1 + self.max_satisfied_size_wo_prefix()
+    }
+
+    fn max_satisfied_size_wo_prefix(&self) -> u64 {
+        const SIGNATURE_SIZE: u64 = 64;
+        const PREIMAGE_SIZE: u64 = 32;
+        // opcode and address
+        const OPAQUE_SIZE: u64 = 33;
+
+        let mut encoder = Encoder::default();
+        self.encode_wo_prefix(&mut encoder);
+        let policy_size = encoder.buffer.len() as u64;
+        match self {
+            SpendPolicy::Above(_) | SpendPolicy::After(_) | SpendPolicy::Opaque(_) => policy_size,
+            SpendPolicy::PublicKey(_) => policy_size + SIGNATURE_SIZE,
+            SpendPolicy::Hash(_) => policy_size + PREIMAGE_SIZE,
+            // SatisfiedPolicy encodes a signature for every key of an UnlockCondition
+            SpendPolicy::UnlockConditions(uc) => {
+                let keys = uc
+                    .unlock_keys
+                    .iter()
+                    .filter(|key| matches!(key, UnlockKey::Ed25519(_)))
+                    .count() as u64;
+                policy_size + keys * SIGNATURE_SIZE
+            },
+            SpendPolicy::Threshold { n, of } => {
+                // opcode, n and the number of sub-policies
+                let header = 3;
+                let mut extra: Vec<u64> = of
+                    .iter()
+                    .map(|p| p.max_satisfied_size_wo_prefix().saturating_sub(OPAQUE_SIZE))
+                    .collect();
+                extra.sort_unstable_by(|a, b| b.cmp(a));
+                header + OPAQUE_SIZE * of.len() as u64 + extra.iter().take(*n as usize).sum::<u64>()
+            },
+        }
+    }
+}
+
+/// One way of satisfying a SpendPolicy. See `SpendPolicy::satisfaction_paths`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SatisfactionPath {
+    /// The keys that must sign, in the order their signatures appear in the SatisfiedPolicy.
+    /// A key appears more than once if the policy requires multiple signatures from it.
+    pub public_keys: Vec<PublicKey>,
+    /// The hashes whose preimages must be revealed, in the order the preimages appear in the SatisfiedPolicy
+    pub hashes: Vec<Hash256>,
+    /// The lowest block height at which the path can be satisfied
+    pub min_height: u64,
+    /// The lowest median timestamp at which the path can be satisfied
+    pub min_timestamp: u64,
+}
+
+impl SatisfactionPath {
+    fn unconditional() -> Self {
+        SatisfactionPath {
+            public_keys: vec![],
+            hashes: vec![],
+            min_height: 0,
+            min_timestamp: 0,
+        }
+    }
+
+    fn merge(&self, other: &SatisfactionPath) -> SatisfactionPath {
+        SatisfactionPath {
+            public_keys: self.public_keys.iter().chain(&other.public_keys).cloned().collect(),
+            hashes: self.hashes.iter().chain(&other.hashes).cloned().collect(),
+            min_height: self.min_height.max(other.min_height),
+            min_timestamp: self.min_timestamp.max(other.min_timestamp),
+        }
+    }
+
+    pub fn signatures_required(&self) -> usize { self.public_keys.len() }
+
+    pub fn preimages_required(&self) -> usize { self.hashes.len() }
+
+    /// Whether the height and time locks of the path have expired
+    pub fn is_unlocked(&self, height: u64, median_timestamp: u64) -> bool {
+        height >= self.min_height && median_timestamp >= self.min_timestamp
+    }
+
+    /// Whether every required signature and preimage can be provided, ignoring height and time locks
+    pub fn is_satisfiable_with(&self, public_keys: &[PublicKey], preimages: &[Preimage]) -> bool {
+        let hashes: Vec<Hash256> = preimages.iter().map(Preimage::compute_hash).collect();
+        self.public_keys.iter().all(|pk| public_keys.contains(pk)) && self.hashes.iter().all(|h| hashes.contains(h))
+    }
+}
+
+impl SpendPolicy {
+    /// Every public key that may sign for this policy, including the ed25519 keys of UnlockConditions
+    pub fn public_keys(&self) -> Vec<PublicKey> {
+        let mut public_keys = Vec::new();
+        self.walk(&mut |policy| match policy {
+            SpendPolicy::PublicKey(public_key) => public_keys.push(public_key.clone()),
+            SpendPolicy::UnlockConditions(uc) => {
+                for key in &uc.unlock_keys {
+                    if let UnlockKey::Ed25519(public_key) = key {
+                        public_keys.push(public_key.clone());
+                    }
+                }
+            },
+            _ => (),
+        });
+        let mut unique = Vec::with_capacity(public_keys.len());
+        for public_key in public_keys {
+            if !unique.contains(&public_key) {
+                unique.push(public_key);
+            }
+        }
+        unique
+    }
+
+    /// Every hash lock within this policy
+    pub fn hashes(&self) -> Vec<Hash256> {
+        let mut hashes: Vec<Hash256> = Vec::new();
+        self.walk(&mut |policy| {
+            if let SpendPolicy::Hash(hash) = policy {
+                if !hashes.contains(hash) {
+                    hashes.push(hash.clone());
+                }
+            }
+        });
+        hashes
+    }
+
+    fn walk<'a>(&'a self, f: &mut impl FnMut(&'a SpendPolicy)) {
+        f(self);
+        if let SpendPolicy::Threshold { of, .. } = self {
+            for sub_policy in of {
+                sub_policy.walk(f);
+            }
+        }
+    }
+
+    /// Every minimal combination of signatures, preimages and locks that satisfies this policy as
+    /// evaluated by `SpendPolicy::verify`. Opaque sub-policies cannot be satisfied and contribute no paths.
+    /// An empty result means the policy can never be satisfied.
+    ///
+    /// The number of paths grows combinatorially with the size of threshold policies so this is
+    /// intended for the policies of individual addresses rather than arbitrary untrusted input.
+    pub fn satisfaction_paths(&self) -> Vec<SatisfactionPath> {
+        let mut paths = Vec::new();
+        for path in self.satisfaction_paths_rec() {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    fn satisfaction_paths_rec(&self) -> Vec<SatisfactionPath> {
+        match self {
+            SpendPolicy::Above(height) => vec![SatisfactionPath {
+                min_height: *height,
+                ..SatisfactionPath::unconditional()
+            }],
+            SpendPolicy::After(time) => vec![SatisfactionPath {
+                min_timestamp: time.saturating_add(1),
+                ..SatisfactionPath::unconditional()
+            }],
+            SpendPolicy::PublicKey(public_key) => vec![SatisfactionPath {
+                public_keys: vec![public_key.clone()],
+                ..SatisfactionPath::unconditional()
+            }],
+            SpendPolicy::Hash(hash) => vec![SatisfactionPath {
+                hashes: vec![hash.clone()],
+                ..SatisfactionPath::unconditional()
+            }],
+            SpendPolicy::Opaque(_) => vec![],
+            SpendPolicy::Threshold { n, of } => {
+                // UnlockConditions are only valid at the root, a threshold containing them always fails
+                if of
+                    .iter()
+                    .any(|sub_policy| matches!(sub_policy, SpendPolicy::UnlockConditions(_)))
+                {
+                    return vec![];
+                }
+                let children: Vec<Vec<SatisfactionPath>> = of
+                    .iter()
+                    .map(|sub_policy| sub_policy.satisfaction_paths_rec())
+                    .collect();
+                threshold_paths(&children, *n as usize)
+            },
+            SpendPolicy::UnlockConditions(uc) => {
+                if uc.signatures_required > u8::MAX as u64 {
+                    return vec![];
+                }
+                let mut children = Vec::with_capacity(uc.unlock_keys.len());
+                for key in &uc.unlock_keys {
+                    match key {
+                        UnlockKey::Ed25519(public_key) => children.push(vec![SatisfactionPath {
+                            public_keys: vec![public_key.clone()],
+                            ..SatisfactionPath::unconditional()
+                        }]),
+                        UnlockKey::NonStandard { .. } => return vec![],
+                    }
+                }
+                let lock = SatisfactionPath {
+                    min_height: uc.timelock,
+                    ..SatisfactionPath::unconditional()
+                };
+                threshold_paths(&children, uc.signatures_required as usize)
+                    .iter()
+                    .map(|path| lock.merge(path))
+                    .collect()
+            },
+        }
+    }
+
+    /// Whether any path of the policy can be satisfied by the given keys and preimages, ignoring
+    /// height and time locks
+    pub fn is_satisfiable_with(&self, public_keys: &[PublicKey], preimages: &[Preimage]) -> bool {
+        self.satisfaction_paths()
+            .iter()
+            .any(|path| path.is_satisfiable_with(public_keys, preimages))
+    }
+}
+
+/// The paths satisfying `n` of the sub-policies whose paths are `children`.
+/// Every combination of `n` children is merged, preserving the order of the sub-policies.
+fn threshold_paths(children: &[Vec<SatisfactionPath>], n: usize) -> Vec<SatisfactionPath> {
+    if n == 0 {
+        return vec![SatisfactionPath::unconditional()];
+    }
+    let mut paths = Vec::new();
+    for (i, child) in children.iter().enumerate() {
+        // every path that includes child i as the first of the n chosen children
+        for rest in threshold_paths(&children[i + 1..], n - 1) {
+            for path in child {
+                paths.push(path.merge(&rest));
+            }
+        }
+    }
+    paths
+}
+
+/// The condition that must be met before the refund path of an HTLC SpendPolicy can be used.
+/// See `SpendPolicy::atomic_swap_with_lock`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RefundLock {
+    /// The median timestamp must be greater than the given timestamp, `SpendPolicy::After`
+    Time(u64),
+    /// The block height must be at least the given height, `SpendPolicy::Above`
+    Height(u64),
+    /// Both the `Height` and `Time` conditions must be met
+    HeightAndTime { height: u64, time: u64 },
+}
+
+impl RefundLock {
+    fn policies(&self) -> Vec<SpendPolicy> {
+        match self {
+            RefundLock::Time(time) => vec![SpendPolicy::After(*time)],
+            RefundLock::Height(height) => vec![SpendPolicy::Above(*height)],
+            RefundLock::HeightAndTime { height, time } => vec![SpendPolicy::Above(*height), SpendPolicy::After(*time)],
+        }
+    }
+
+    /// Whether the refund path is valid in a block at `height` with `median_timestamp`.
+    /// These should be those of the block the refund is expected to be included in.
+    pub fn is_expired(&self, height: u64, median_timestamp: u64) -> bool {
+        match self {
+            RefundLock::Time(time) => median_timestamp > *time,
+            RefundLock::Height(lock_height) => height >= *lock_height,
+            RefundLock::HeightAndTime {
+                height: lock_height,
+                time,
+            } => height >= *lock_height && median_timestamp > *time,
+        }
+    }
+}
+
 impl SpendPolicy {
     /// Create a HTLC SpendPolicy.
     /// Arguments:
@@ -229,7 +708,128 @@ impl SpendPolicy {
         lock_time: u64,
         secret_hash: &Hash256,
     ) -> Self {
-        let policy_after = SpendPolicy::After(lock_time);
+        Self::atomic_swap_with_lock(
+            success_public_key,
+            refund_public_key,
+            &RefundLock::Time(lock_time),
+            secret_hash,
+        )
+    }
+
+    pub fn atomic_swap_success(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_time: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap(success_public_key, refund_public_key, lock_time, secret_hash).opacify_htlc_path(1)
+    }
+
+    pub fn atomic_swap_refund(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_time: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap(success_public_key, refund_public_key, lock_time, secret_hash).opacify_htlc_path(0)
+    }
+
+    /// Create a HTLC SpendPolicy whose refund path is locked until the block height reaches
+    /// `lock_height`. Unlike `atomic_swap`, the lock does not depend on the median timestamp rules.
+    pub fn atomic_swap_height(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_height: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap_with_lock(
+            success_public_key,
+            refund_public_key,
+            &RefundLock::Height(lock_height),
+            secret_hash,
+        )
+    }
+
+    pub fn atomic_swap_height_success(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_height: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap_height(success_public_key, refund_public_key, lock_height, secret_hash).opacify_htlc_path(1)
+    }
+
+    pub fn atomic_swap_height_refund(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_height: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap_height(success_public_key, refund_public_key, lock_height, secret_hash).opacify_htlc_path(0)
+    }
+
+    /// Create a HTLC SpendPolicy whose refund path is locked until both the block height reaches
+    /// `lock_height` and the median timestamp is greater than `lock_time`
+    pub fn atomic_swap_height_and_time(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_height: u64,
+        lock_time: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap_with_lock(
+            success_public_key,
+            refund_public_key,
+            &RefundLock::HeightAndTime {
+                height: lock_height,
+                time: lock_time,
+            },
+            secret_hash,
+        )
+    }
+
+    pub fn atomic_swap_height_and_time_success(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_height: u64,
+        lock_time: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap_height_and_time(
+            success_public_key,
+            refund_public_key,
+            lock_height,
+            lock_time,
+            secret_hash,
+        )
+        .opacify_htlc_path(1)
+    }
+
+    pub fn atomic_swap_height_and_time_refund(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        lock_height: u64,
+        lock_time: u64,
+        secret_hash: &Hash256,
+    ) -> Self {
+        Self::atomic_swap_height_and_time(
+            success_public_key,
+            refund_public_key,
+            lock_height,
+            lock_time,
+            secret_hash,
+        )
+        .opacify_htlc_path(0)
+    }
+
+    /// Create a HTLC SpendPolicy with any of the supported refund locks. The refund path requires a
+    /// signature from `refund_public_key` and every condition of `refund_lock`.
+    pub fn atomic_swap_with_lock(
+        success_public_key: &PublicKey,
+        refund_public_key: &PublicKey,
+        refund_lock: &RefundLock,
+        secret_hash: &Hash256,
+    ) -> Self {
         let policy_hash = SpendPolicy::Hash(secret_hash.clone());
 
         let policy_success = SpendPolicy::Threshold {
@@ -237,9 +837,11 @@ impl SpendPolicy {
             of: vec![SpendPolicy::PublicKey(success_public_key.clone()), policy_hash],
         };
 
+        let mut refund_policies = vec![SpendPolicy::PublicKey(refund_public_key.clone())];
+        refund_policies.extend(refund_lock.policies());
         let policy_refund = SpendPolicy::Threshold {
-            n: 2,
-            of: vec![SpendPolicy::PublicKey(refund_public_key.clone()), policy_after],
+            n: refund_policies.len() as u8,
+            of: refund_policies,
         };
 
         SpendPolicy::Threshold {
@@ -248,30 +850,33 @@ impl SpendPolicy {
         }
     }
 
-    pub fn atomic_swap_success(
+    /// The HTLC policy revealing only the success path
+    pub fn atomic_swap_with_lock_success(
         success_public_key: &PublicKey,
         refund_public_key: &PublicKey,
-        lock_time: u64,
+        refund_lock: &RefundLock,
         secret_hash: &Hash256,
     ) -> Self {
-        match Self::atomic_swap(success_public_key, refund_public_key, lock_time, secret_hash) {
-            SpendPolicy::Threshold { n, mut of } => {
-                of[1] = of[1].opacify();
-                SpendPolicy::Threshold { n, of }
-            },
-            _ => unreachable!(),
-        }
+        Self::atomic_swap_with_lock(success_public_key, refund_public_key, refund_lock, secret_hash)
+            .opacify_htlc_path(1)
     }
 
-    pub fn atomic_swap_refund(
+    /// The HTLC policy revealing only the refund path
+    pub fn atomic_swap_with_lock_refund(
         success_public_key: &PublicKey,
         refund_public_key: &PublicKey,
-        lock_time: u64,
+        refund_lock: &RefundLock,
         secret_hash: &Hash256,
     ) -> Self {
-        match Self::atomic_swap(success_public_key, refund_public_key, lock_time, secret_hash) {
+        Self::atomic_swap_with_lock(success_public_key, refund_public_key, refund_lock, secret_hash)
+            .opacify_htlc_path(0)
+    }
+
+    /// Opacify the path of an HTLC policy at `index`, revealing the other
+    fn opacify_htlc_path(self, index: usize) -> Self {
+        match self {
             SpendPolicy::Threshold { n, mut of } => {
-                of[0] = of[0].opacify();
+                of[index] = of[index].opacify();
                 SpendPolicy::Threshold { n, of }
             },
             _ => unreachable!(),
@@ -338,19 +943,15 @@ fn parse_unlock_key(input: &str) -> IResult<&str, UnlockKey> {
     match specifier {
         Specifier::Ed25519 => {
             let (input, public_key) = map_res(
-                all_consuming(map_res(
-                    take_while_m_n(64, 64, |c: char| c.is_ascii_hexdigit()),
-                    hex::decode,
-                )),
+                map_res(take_while_m_n(64, 64, |c: char| c.is_ascii_hexdigit()), hex::decode),
                 |bytes: Vec<u8>| PublicKey::from_bytes(&bytes),
             )(input)?;
             Ok((input, UnlockKey::Ed25519(public_key)))
         },
         _ => {
-            let (input, public_key) =
-                all_consuming(map_res(take_while(|c: char| c.is_ascii_hexdigit()), |hex_str: &str| {
-                    hex::decode(hex_str)
-                }))(input)?;
+            let (input, public_key) = map_res(take_while(|c: char| c.is_ascii_hexdigit()), |hex_str: &str| {
+                hex::decode(hex_str)
+            })(input)?;
             Ok((input, UnlockKey::NonStandard {
                 algorithm: specifier,
                 public_key,
@@ -384,10 +985,136 @@ impl fmt::Display for UnlockKey {
     }
 }
 
+/// Parse a 0x prefixed 32 byte hex string as used by the SpendPolicy string syntax
+fn parse_hex_32(input: &str) -> IResult<&str, [u8; 32]> {
+    map_res(
+        preceded(tag("0x"), take_while_m_n(64, 64, |c: char| c.is_ascii_hexdigit())),
+        |hex_str: &str| {
+            let mut bytes = [0u8; 32];
+            hex::decode_to_slice(hex_str, &mut bytes).map(|_| bytes)
+        },
+    )(input)
+}
+
+fn parse_u64(input: &str) -> IResult<&str, u64> { map_res(digit1, u64::from_str)(input) }
+
+/// Parse a policy nested `depth` thresholds deep. Nesting is limited to `MAX_POLICY_DEPTH` so
+/// untrusted input cannot exhaust the stack.
+fn parse_spend_policy(input: &str, depth: usize) -> IResult<&str, SpendPolicy> {
+    if depth > MAX_POLICY_DEPTH {
+        return Err(nom::Err::Failure(nom::error::Error::new(input, ErrorKind::TooLarge)));
+    }
+    alt((
+        map(delimited(tag("above("), parse_u64, char(')')), SpendPolicy::Above),
+        map(delimited(tag("after("), parse_u64, char(')')), SpendPolicy::After),
+        map_res(delimited(tag("pk("), parse_hex_32, char(')')), |bytes| {
+            PublicKey::from_bytes(&bytes).map(SpendPolicy::PublicKey)
+        }),
+        map(delimited(tag("h("), parse_hex_32, char(')')), |bytes| {
+            SpendPolicy::Hash(Hash256(bytes))
+        }),
+        map(
+            delimited(
+                tag("thresh("),
+                tuple((
+                    map_res(digit1, u8::from_str),
+                    tag(",["),
+                    separated_list0(char(','), |input| parse_spend_policy(input, depth + 1)),
+                )),
+                tag("])"),
+            ),
+            |(n, _, of)| SpendPolicy::Threshold { n, of },
+        ),
+        map(delimited(tag("opaque("), parse_hex_32, char(')')), |bytes| {
+            SpendPolicy::Opaque(Address(Hash256(bytes)))
+        }),
+        map(
+            delimited(
+                tag("uc("),
+                tuple((
+                    parse_u64,
+                    tag(",["),
+                    separated_list0(char(','), parse_unlock_key),
+                    tag("],"),
+                    parse_u64,
+                )),
+                char(')'),
+            ),
+            |(timelock, _, unlock_keys, _, signatures_required)| {
+                SpendPolicy::UnlockConditions(UnlockCondition {
+                    unlock_keys,
+                    timelock,
+                    signatures_required,
+                })
+            },
+        ),
+    ))(input)
+}
+
+#[derive(Debug, Error)]
+#[error("SpendPolicy::from_str: failed to parse policy: {0}")]
+pub struct SpendPolicyParseError(pub String);
+
+/// Parse the string syntax used by Sia Go, eg, `thresh(1,[pk(0x...),above(100)])`
+impl FromStr for SpendPolicy {
+    type Err = SpendPolicyParseError;
+
+    fn from_str(input: &str) -> Result<SpendPolicy, Self::Err> {
+        match all_consuming(|input| parse_spend_policy(input, 0))(input) {
+            Ok((_, policy)) => Ok(policy),
+            Err(nom::Err::Failure(e)) if e.code == ErrorKind::TooLarge => Err(SpendPolicyParseError(format!(
+                "policy is nested more than {} levels deep",
+                MAX_POLICY_DEPTH
+            ))),
+            Err(e) => Err(SpendPolicyParseError(e.to_string())),
+        }
+    }
+}
+
+/// Display the policy in the string syntax used by Sia Go
+/// <https://github.com/SiaFoundation/core/blob/00682daf422864b250b6bc750d4229dd76a8632d/types/policy.go>
+impl fmt::Display for SpendPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpendPolicy::Above(height) => write!(f, "above({})", height),
+            SpendPolicy::After(time) => write!(f, "after({})", time),
+            SpendPolicy::PublicKey(public_key) => write!(f, "pk(0x{})", hex::encode(public_key.as_bytes())),
+            SpendPolicy::Hash(hash) => write!(f, "h(0x{})", hex::encode(hash.0)),
+            SpendPolicy::Threshold { n, of } => {
+                write!(f, "thresh({},[", n)?;
+                for (i, policy) in of.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", policy)?;
+                }
+                f.write_str("])")
+            },
+            SpendPolicy::Opaque(address) => write!(f, "opaque(0x{})", hex::encode(address.0 .0)),
+            SpendPolicy::UnlockConditions(uc) => {
+                write!(f, "uc({},[", uc.timelock)?;
+                for (i, unlock_key) in uc.unlock_keys.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", unlock_key)?;
+                }
+                write!(f, "],{})", uc.signatures_required)
+            },
+        }
+    }
+}
+
 impl Encodable for PublicKey {
     fn encode(&self, encoder: &mut Encoder) { encoder.write_slice(&self.to_bytes()); }
 }
 
+impl Decodable for PublicKey {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(PublicKey::from_bytes(decoder.read_slice(32)?)?)
+    }
+}
+
 impl Encodable for UnlockKey {
     fn encode(&self, encoder: &mut Encoder) {
         match self {
@@ -404,6 +1131,17 @@ impl Encodable for UnlockKey {
         }
     }
 }
+impl Decodable for UnlockKey {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let algorithm = Specifier::decode(decoder)?;
+        let public_key = decoder.read_len_prefixed_bytes()?;
+        match algorithm {
+            Specifier::Ed25519 if public_key.len() == 32 => Ok(UnlockKey::Ed25519(PublicKey::from_bytes(&public_key)?)),
+            _ => Ok(UnlockKey::NonStandard { algorithm, public_key }),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UnlockCondition {
@@ -424,6 +1162,16 @@ impl Encodable for UnlockCondition {
     }
 }
 
+impl Decodable for UnlockCondition {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(UnlockCondition {
+            timelock: decoder.read_u64()?,
+            unlock_keys: decoder.read_len_prefixed_vec()?,
+            signatures_required: decoder.read_u64()?,
+        })
+    }
+}
+
 impl UnlockCondition {
     pub fn new(pubkeys: Vec<PublicKey>, timelock: u64, signatures_required: u64) -> Self {
         let unlock_keys = pubkeys.into_iter().map(UnlockKey::Ed25519).collect();
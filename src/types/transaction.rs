@@ -1,17 +1,28 @@
-use crate::encoding::{Encodable, Encoder};
-use crate::types::{Address, ChainIndex, Hash256, Keypair, PublicKey, Signature, SpendPolicy, UnlockCondition,
-                   UnlockKey};
+use crate::encoding::{Decodable, Decoder, DecoderError, Encodable, Encoder};
+use crate::types::{Address, ChainIndex, Hash256, Keypair, PublicKey, PublicKeyError, Signature, SignatureError,
+                   SpendPolicy, SpendPolicyVerifyError, UnlockCondition, UnlockKey};
 use crate::utils::deserialize_null_as_empty_vec;
 use base64::{engine::general_purpose::STANDARD as base64, Engine as _};
 use derive_more::{Add, AddAssign, Deref, Display, Div, DivAssign, From, Into, Mul, MulAssign, Sub, SubAssign, Sum};
+use ed25519_dalek::Signature as Ed25519Signature;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::str::FromStr;
 use thiserror::Error;
+use zeroize::Zeroize;
 
 const V2_REPLAY_PREFIX: u8 = 2;
+const V2_TRANSACTION_VERSION: u8 = 2;
+
+/// The total number of siafunds in existence
+pub const SIAFUND_COUNT: u64 = 10_000;
+
+/// The number of blocks a miner payout or siafund claim output must wait before it can be spent
+pub const MATURITY_DELAY: u64 = 144;
 
 /// A currency amount in the Sia network represented in Hastings, the smallest unit of currency.
 /// 1 SC = 10^24 Hastings
@@ -54,6 +65,20 @@ impl Currency {
     /// A default fee amount for transactions
     /// FIXME This is a placeholder value until testing is complete
     pub const DEFAULT_FEE: Currency = Currency(10000000000000000000);
+
+    /// Decode the v1 encoding of Currency, a length-prefixed big-endian integer
+    pub fn decode_v1(decoder: &mut Decoder) -> Result<Currency, DecoderError> {
+        let len = decoder.read_prefix()?;
+        if len > 16 {
+            return Err(DecoderError::CurrencyOverflow(len));
+        }
+        let mut buffer = [0u8; 16];
+        buffer[16 - len..].copy_from_slice(decoder.read_slice(len)?);
+        Ok(Currency(u128::from_be_bytes(buffer)))
+    }
+
+    /// Decode the v2 encoding of Currency, a 16 byte little-endian integer
+    pub fn decode_v2(decoder: &mut Decoder) -> Result<Currency, DecoderError> { Ok(Currency(decoder.read_u128()?)) }
 }
 
 // TODO does this also need to be able to deserialize from an integer?
@@ -117,7 +142,7 @@ impl<'a> Encodable for CurrencyVersion<'a> {
                 // Trim leading zero bytes from the buffer
                 let trimmed_buf = match buffer.iter().position(|&x| x != 0) {
                     Some(index) => &buffer[index..],
-                    None => &buffer[..], // In case all bytes are zero
+                    None => &buffer[16..], // zero is encoded as an empty slice
                 };
                 encoder.write_len_prefixed_bytes(trimmed_buf);
             },
@@ -130,9 +155,18 @@ impl<'a> Encodable for CurrencyVersion<'a> {
 
 /// Preimage is a 32-byte array representing the preimage of a hash used in Sia's SpendPolicy::Hash
 /// Used to allow HLTC-style hashlock contracts in Sia
-#[derive(Clone, Debug, Default, PartialEq, From, Into)]
+/// Preimages are secrets until revealed on chain so the bytes are zeroized on drop and omitted from Debug
+#[derive(Clone, Default, PartialEq, From, Into)]
 pub struct Preimage(pub [u8; 32]);
 
+impl Drop for Preimage {
+    fn drop(&mut self) { self.0.zeroize() }
+}
+
+impl fmt::Debug for Preimage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("Preimage(<redacted>)") }
+}
+
 impl Serialize for Preimage {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -190,6 +224,11 @@ impl From<Preimage> for Vec<u8> {
     fn from(preimage: Preimage) -> Self { preimage.0.to_vec() }
 }
 
+impl Preimage {
+    /// The sha256 hash of the preimage as used by SpendPolicy::Hash
+    pub fn compute_hash(&self) -> Hash256 { Hash256(Sha256::digest(&self.0).into()) }
+}
+
 impl TryFrom<&[u8]> for Preimage {
     type Error = PreimageError;
 
@@ -219,6 +258,146 @@ impl Encodable for Signature {
     fn encode(&self, encoder: &mut Encoder) { encoder.write_slice(&self.to_bytes()); }
 }
 
+impl Decodable for Signature {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(Signature(Ed25519Signature::try_from(decoder.read_slice(64)?)?))
+    }
+}
+
+impl SatisfiedPolicy {
+    /// Check that the signatures and preimages satisfy the policy. See SpendPolicy::verify
+    pub fn verify(&self, height: u64, median_timestamp: u64, sig_hash: &Hash256) -> Result<(), SpendPolicyVerifyError> {
+        self.policy
+            .verify(height, median_timestamp, sig_hash, &self.signatures, &self.preimages)
+    }
+
+    /// Attempt to satisfy `policy` with the provided keypairs and preimages at the given chain height
+    /// and median timestamp. Signatures and preimages are placed in the order SpendPolicy::verify
+    /// consumes them. Threshold sub-policies that do not contribute are replaced with their Opaque
+    /// equivalent so the resulting policy still hashes to the same address.
+    /// Returns None if the policy cannot be satisfied.
+    pub fn sign(
+        policy: &SpendPolicy,
+        height: u64,
+        median_timestamp: u64,
+        sig_hash: &Hash256,
+        keypairs: &[&Keypair],
+        preimages: &[Preimage],
+    ) -> Option<SatisfiedPolicy> {
+        let signature = |public_key: &PublicKey| {
+            keypairs
+                .iter()
+                .find(|keypair| &keypair.public() == public_key)
+                .map(|keypair| keypair.sign(&sig_hash.0))
+        };
+        let signer = PolicySigner {
+            height,
+            median_timestamp,
+            signature: &signature,
+            preimages,
+        };
+        signer.sign(policy)
+    }
+
+    /// Like `SatisfiedPolicy::sign` but using signatures collected ahead of time, eg, from cosigners.
+    /// The signatures are assumed to be over the transaction's input sig hash.
+    pub fn with_signatures(
+        policy: &SpendPolicy,
+        height: u64,
+        median_timestamp: u64,
+        signatures: &HashMap<PublicKey, Signature>,
+        preimages: &[Preimage],
+    ) -> Option<SatisfiedPolicy> {
+        let signature = |public_key: &PublicKey| signatures.get(public_key).cloned();
+        let signer = PolicySigner {
+            height,
+            median_timestamp,
+            signature: &signature,
+            preimages,
+        };
+        signer.sign(policy)
+    }
+}
+
+/// Walks a policy tree producing the SatisfiedPolicy that PolicyVerifier would accept
+struct PolicySigner<'a> {
+    height: u64,
+    median_timestamp: u64,
+    signature: &'a dyn Fn(&PublicKey) -> Option<Signature>,
+    preimages: &'a [Preimage],
+}
+
+impl<'a> PolicySigner<'a> {
+    fn sign(&self, policy: &SpendPolicy) -> Option<SatisfiedPolicy> {
+        let mut satisfied = SatisfiedPolicy {
+            policy: policy.clone(),
+            signatures: Vec::new(),
+            preimages: Vec::new(),
+        };
+        match policy {
+            SpendPolicy::Above(above) if self.height >= *above => (),
+            SpendPolicy::After(after) if self.median_timestamp > *after => (),
+            SpendPolicy::Above(_) | SpendPolicy::After(_) | SpendPolicy::Opaque(_) => return None,
+            SpendPolicy::PublicKey(public_key) => satisfied.signatures.push((self.signature)(public_key)?),
+            SpendPolicy::Hash(hash) => satisfied.preimages.push(
+                self.preimages
+                    .iter()
+                    .find(|preimage| &preimage.compute_hash() == hash)?
+                    .clone(),
+            ),
+            SpendPolicy::Threshold { n, of } => {
+                let mut remaining = *n;
+                let mut sub_policies = Vec::with_capacity(of.len());
+                for sub_policy in of {
+                    let sub_satisfied = match sub_policy {
+                        SpendPolicy::UnlockConditions(_) => None,
+                        _ if remaining == 0 => None,
+                        _ => self.sign(sub_policy),
+                    };
+                    match sub_satisfied {
+                        Some(sub_satisfied) => {
+                            remaining -= 1;
+                            satisfied.signatures.extend(sub_satisfied.signatures);
+                            satisfied.preimages.extend(sub_satisfied.preimages);
+                            sub_policies.push(sub_satisfied.policy);
+                        },
+                        None => sub_policies.push(sub_policy.opacify()),
+                    }
+                }
+                if remaining > 0 {
+                    return None;
+                }
+                satisfied.policy = SpendPolicy::Threshold {
+                    n: *n,
+                    of: sub_policies,
+                };
+            },
+            // UnlockConditions keys cannot be opacified so the first `signatures_required` keys must sign
+            SpendPolicy::UnlockConditions(uc) => {
+                if self.height < uc.timelock || uc.signatures_required > u8::MAX as u64 {
+                    return None;
+                }
+                let public_keys = uc
+                    .unlock_keys
+                    .iter()
+                    .map(|key| match key {
+                        UnlockKey::Ed25519(public_key) => Some(public_key),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                let required = uc.signatures_required as usize;
+                if public_keys.len() < required {
+                    return None;
+                }
+                for public_key in public_keys.into_iter().take(required) {
+                    satisfied.signatures.push((self.signature)(public_key)?);
+                }
+            },
+        }
+        Some(satisfied)
+    }
+}
+
 impl Encodable for SatisfiedPolicy {
     fn encode(&self, encoder: &mut Encoder) {
         self.policy.encode(encoder);
@@ -268,6 +447,43 @@ impl Encodable for SatisfiedPolicy {
     }
 }
 
+// Mirrors SatisfiedPolicy::encode; signatures and preimages are read in the order their
+// corresponding PublicKey and Hash policies appear within the policy tree.
+impl Decodable for SatisfiedPolicy {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let mut satisfied_policy = SatisfiedPolicy {
+            policy: SpendPolicy::decode(decoder)?,
+            signatures: Vec::new(),
+            preimages: Vec::new(),
+        };
+
+        fn rec(policy: &SpendPolicy, decoder: &mut Decoder, sp: &mut SatisfiedPolicy) -> Result<(), DecoderError> {
+            match policy {
+                SpendPolicy::PublicKey(_) => sp.signatures.push(Signature::decode(decoder)?),
+                SpendPolicy::Hash(_) => sp.preimages.push(Preimage(decoder.read_array()?)),
+                SpendPolicy::Threshold { n: _, of } => {
+                    for p in of {
+                        rec(p, decoder, sp)?;
+                    }
+                },
+                SpendPolicy::UnlockConditions(uc) => {
+                    for unlock_key in &uc.unlock_keys {
+                        if let UnlockKey::Ed25519(_) = unlock_key {
+                            sp.signatures.push(Signature::decode(decoder)?);
+                        }
+                    }
+                },
+                _ => {},
+            }
+            Ok(())
+        }
+
+        let policy = satisfied_policy.policy.clone();
+        rec(&policy, decoder, &mut satisfied_policy)?;
+        Ok(satisfied_policy)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct StateElement {
@@ -280,6 +496,11 @@ pub struct StateElement {
 // merkle_proof was previously Option<Vec<Hash256>> because Walletd can return null for this field
 // Test unintialized slice (ie, null) vs empty slice - do they encode the same?
 // the following encoding assumes that they do encode the same
+impl StateElement {
+    /// The leaf index of an element that has not yet been added to the state accumulator
+    pub const UNASSIGNED_LEAF_INDEX: u64 = 10101010101010101010;
+}
+
 impl Encodable for StateElement {
     fn encode(&self, encoder: &mut Encoder) {
         encoder.write_u64(self.leaf_index);
@@ -291,10 +512,19 @@ impl Encodable for StateElement {
     }
 }
 
+impl Decodable for StateElement {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(StateElement {
+            leaf_index: decoder.read_u64()?,
+            merkle_proof: decoder.read_len_prefixed_vec()?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SiafundElement {
-    #[serde(rename = "ID")]
+    #[serde(alias = "ID")]
     pub id: SiafundOutputId,
     pub state_element: StateElement,
     pub siafund_output: SiafundOutput,
@@ -304,11 +534,53 @@ pub struct SiafundElement {
 impl Encodable for SiafundElement {
     fn encode(&self, encoder: &mut Encoder) {
         self.state_element.encode(encoder);
+        self.id.encode(encoder);
         SiafundOutputVersion::V2(&self.siafund_output).encode(encoder);
         CurrencyVersion::V2(&self.claim_start).encode(encoder);
     }
 }
 
+impl SiafundElement {
+    /// The siacoins owed to the holder of this element if it were spent while the siafund pool's
+    /// cumulative tax revenue is `siafund_tax_revenue`. Each siafund earns an equal share of the
+    /// revenue accrued since `claim_start`.
+    pub fn claim_value(&self, siafund_tax_revenue: &Currency) -> Currency {
+        let accrued = siafund_tax_revenue.0.saturating_sub(self.claim_start.0);
+        Currency(accrued / SIAFUND_COUNT as u128 * self.siafund_output.value as u128)
+    }
+
+    /// Predict the immature SiacoinElement created when this element is spent by a transaction
+    /// included in the block following `height`. The element has not been added to the state
+    /// accumulator yet so its leaf index is `StateElement::UNASSIGNED_LEAF_INDEX`.
+    /// Ported from Sia Core's `ApplyV2Transaction`:
+    /// <https://github.com/SiaFoundation/core/blob/00682daf422864b250b6bc750d4229dd76a8632d/consensus/update.go#L534>
+    pub fn claim_element(&self, siafund_tax_revenue: &Currency, claim_address: Address, height: u64) -> SiacoinElement {
+        SiacoinElement {
+            id: self.id.v2_claim_output_id(),
+            state_element: StateElement {
+                leaf_index: StateElement::UNASSIGNED_LEAF_INDEX,
+                merkle_proof: vec![],
+            },
+            siacoin_output: SiacoinOutput {
+                value: self.claim_value(siafund_tax_revenue),
+                address: claim_address,
+            },
+            maturity_height: height + 1 + MATURITY_DELAY,
+        }
+    }
+}
+
+impl Decodable for SiafundElement {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiafundElement {
+            state_element: StateElement::decode(decoder)?,
+            id: SiafundOutputId::decode(decoder)?,
+            siafund_output: SiafundOutput::decode_v2(decoder)?,
+            claim_start: Currency::decode_v2(decoder)?,
+        })
+    }
+}
+
 /// As per, Sia Core a "SiacoinElement is a record of a SiacoinOutput within the state accumulator."
 /// This type is effectively a "UTXO" in Bitcoin terms.
 /// A SiacoinElement can be combined with a SatisfiedPolicy to create a SiacoinInputV2.
@@ -332,6 +604,17 @@ impl Encodable for SiacoinElement {
     }
 }
 
+impl Decodable for SiacoinElement {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiacoinElement {
+            state_element: StateElement::decode(decoder)?,
+            id: SiacoinOutputId::decode(decoder)?,
+            siacoin_output: SiacoinOutput::decode_v2(decoder)?,
+            maturity_height: decoder.read_u64()?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SiafundInputV2 {
@@ -348,6 +631,16 @@ impl Encodable for SiafundInputV2 {
     }
 }
 
+impl Decodable for SiafundInputV2 {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiafundInputV2 {
+            parent: SiafundElement::decode(decoder)?,
+            claim_address: Address::decode(decoder)?,
+            satisfied_policy: SatisfiedPolicy::decode(decoder)?,
+        })
+    }
+}
+
 // https://github.com/SiaFoundation/core/blob/6c19657baf738c6b730625288e9b5413f77aa659/types/types.go#L197-L198
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct SiacoinInputV1 {
@@ -364,6 +657,15 @@ impl Encodable for SiacoinInputV1 {
     }
 }
 
+impl Decodable for SiacoinInputV1 {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiacoinInputV1 {
+            parent_id: SiacoinOutputId::decode(decoder)?,
+            unlock_condition: UnlockCondition::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SiacoinInputV2 {
@@ -378,12 +680,39 @@ impl Encodable for SiacoinInputV2 {
     }
 }
 
+impl Decodable for SiacoinInputV2 {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiacoinInputV2 {
+            parent: SiacoinElement::decode(decoder)?,
+            satisfied_policy: SatisfiedPolicy::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct SiafundOutput {
     pub value: u64,
     pub address: Address,
 }
 
+impl SiafundOutput {
+    pub fn decode_v1(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        // Sia Go truncates the v1 Currency value to its lower 64 bits
+        let value = Currency::decode_v1(decoder)?.0 as u64;
+        let address = Address::decode(decoder)?;
+        // discard the legacy siad ClaimStart field
+        Currency::decode_v1(decoder)?;
+        Ok(SiafundOutput { value, address })
+    }
+
+    pub fn decode_v2(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiafundOutput {
+            value: decoder.read_u64()?,
+            address: Address::decode(decoder)?,
+        })
+    }
+}
+
 // SiafundOutput remains the same data structure between V1 and V2 however the encoding changes
 #[derive(Clone, Debug)]
 pub enum SiafundOutputVersion<'a> {
@@ -397,6 +726,8 @@ impl<'a> Encodable for SiafundOutputVersion<'a> {
             SiafundOutputVersion::V1(v1) => {
                 CurrencyVersion::V1(&Currency::from(v1.value)).encode(encoder);
                 v1.address.encode(encoder);
+                // legacy siad ClaimStart field, always zero
+                CurrencyVersion::V1(&Currency::ZERO).encode(encoder);
             },
             SiafundOutputVersion::V2(v2) => {
                 encoder.write_u64(v2.value);
@@ -433,7 +764,7 @@ impl<'a> Encodable for SiacoinOutputVersion<'a> {
 // making SiacoinOutputId::new more explicit.
 pub type TransactionId = Hash256;
 
-#[derive(Clone, Debug, PartialEq, From, Into, Deserialize, Serialize, Display, Default)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, From, Into, Deserialize, Serialize, Display, Default)]
 #[serde(transparent)]
 pub struct SiacoinOutputId(pub Hash256);
 
@@ -441,6 +772,10 @@ impl Encodable for SiacoinOutputId {
     fn encode(&self, encoder: &mut Encoder) { self.0.encode(encoder) }
 }
 
+impl Decodable for SiacoinOutputId {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(SiacoinOutputId(Hash256::decode(decoder)?)) }
+}
+
 impl SiacoinOutputId {
     pub fn new(txid: TransactionId, index: u32) -> Self {
         let mut encoder = Encoder::default();
@@ -451,7 +786,7 @@ impl SiacoinOutputId {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, From, Into, Deserialize, Serialize, Display)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, From, Into, Deserialize, Serialize, Display)]
 #[serde(transparent)]
 pub struct SiafundOutputId(pub Hash256);
 
@@ -459,6 +794,21 @@ impl Encodable for SiafundOutputId {
     fn encode(&self, encoder: &mut Encoder) { self.0.encode(encoder) }
 }
 
+impl Decodable for SiafundOutputId {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(SiafundOutputId(Hash256::decode(decoder)?)) }
+}
+
+impl SiafundOutputId {
+    /// The id of the siacoin output created to pay the claim of this siafund output when it is spent
+    /// by a V2Transaction
+    pub fn v2_claim_output_id(&self) -> SiacoinOutputId {
+        let mut encoder = Encoder::default();
+        encoder.write_distinguisher("id/v2siafundclaimoutput");
+        self.encode(&mut encoder);
+        SiacoinOutputId(encoder.hash())
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, From, Into, Deserialize, Serialize, Display)]
 #[serde(transparent)]
 pub struct FileContractID(pub Hash256);
@@ -467,12 +817,32 @@ impl Encodable for FileContractID {
     fn encode(&self, encoder: &mut Encoder) { self.0.encode(encoder) }
 }
 
+impl Decodable for FileContractID {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(FileContractID(Hash256::decode(decoder)?)) }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct SiacoinOutput {
     pub value: Currency,
     pub address: Address,
 }
 
+impl SiacoinOutput {
+    pub fn decode_v1(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiacoinOutput {
+            value: Currency::decode_v1(decoder)?,
+            address: Address::decode(decoder)?,
+        })
+    }
+
+    pub fn decode_v2(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiacoinOutput {
+            value: Currency::decode_v2(decoder)?,
+            address: Address::decode(decoder)?,
+        })
+    }
+}
+
 impl From<(Currency, Address)> for SiacoinOutput {
     fn from(tuple: (Currency, Address)) -> Self {
         SiacoinOutput {
@@ -508,6 +878,64 @@ pub struct CoveredFields {
     pub signatures: Vec<u64>,
 }
 
+impl CoveredFields {
+    /// CoveredFields of a signature covering the entire transaction excluding other signatures
+    pub fn whole_transaction() -> Self {
+        CoveredFields {
+            whole_transaction: true,
+            ..Default::default()
+        }
+    }
+
+    fn fields(&self) -> [&Vec<u64>; 10] {
+        [
+            &self.siacoin_inputs,
+            &self.siacoin_outputs,
+            &self.file_contracts,
+            &self.file_contract_revisions,
+            &self.storage_proofs,
+            &self.siafund_inputs,
+            &self.siafund_outputs,
+            &self.miner_fees,
+            &self.arbitrary_data,
+            &self.signatures,
+        ]
+    }
+}
+
+impl Encodable for CoveredFields {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_bool(self.whole_transaction);
+        for field in self.fields() {
+            encoder.write_u64(field.len() as u64);
+            field.iter().for_each(|i| encoder.write_u64(*i));
+        }
+    }
+}
+
+impl Decodable for CoveredFields {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        fn indices(decoder: &mut Decoder) -> Result<Vec<u64>, DecoderError> {
+            let len = decoder.read_prefix()?;
+            (0..len).map(|_| decoder.read_u64()).collect()
+        }
+
+        Ok(CoveredFields {
+            whole_transaction: decoder.read_bool()?,
+            siacoin_inputs: indices(decoder)?,
+            siacoin_outputs: indices(decoder)?,
+            file_contracts: indices(decoder)?,
+            file_contract_revisions: indices(decoder)?,
+            storage_proofs: indices(decoder)?,
+            siafund_inputs: indices(decoder)?,
+            siafund_outputs: indices(decoder)?,
+            miner_fees: indices(decoder)?,
+            arbitrary_data: indices(decoder)?,
+            signatures: indices(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransactionSignature {
@@ -521,9 +949,44 @@ pub struct TransactionSignature {
     pub signature: V1Signature,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
-#[serde(transparent)]
-pub struct V1Signature(Vec<u8>);
+impl Encodable for TransactionSignature {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.parent_id.encode(encoder);
+        encoder.write_u64(self.public_key_index);
+        encoder.write_u64(self.timelock);
+        self.covered_fields.encode(encoder);
+        encoder.write_len_prefixed_bytes(&self.signature.0);
+    }
+}
+
+impl Decodable for TransactionSignature {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(TransactionSignature {
+            parent_id: Hash256::decode(decoder)?,
+            public_key_index: decoder.read_u64()?,
+            timelock: decoder.read_u64()?,
+            covered_fields: CoveredFields::decode(decoder)?,
+            signature: V1Signature(decoder.read_len_prefixed_bytes()?),
+        })
+    }
+}
+
+/// The raw signature bytes of a v1 TransactionSignature, represented as a base64 string in JSON
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct V1Signature(pub Vec<u8>);
+
+impl From<Signature> for V1Signature {
+    fn from(signature: Signature) -> Self { V1Signature(signature.to_bytes().to_vec()) }
+}
+
+impl Serialize for V1Signature {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&base64.encode(&self.0))
+    }
+}
 
 impl<'de> Deserialize<'de> for V1Signature {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -585,6 +1048,35 @@ impl Encodable for FileContract {
     }
 }
 
+impl Decodable for FileContract {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let filesize = decoder.read_u64()?;
+        let file_merkle_root = Hash256::decode(decoder)?;
+        let window_start = decoder.read_u64()?;
+        let window_end = decoder.read_u64()?;
+        let payout = Currency::decode_v1(decoder)?;
+        let len = decoder.read_prefix()?;
+        let valid_proof_outputs = (0..len)
+            .map(|_| SiacoinOutput::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        let len = decoder.read_prefix()?;
+        let missed_proof_outputs = (0..len)
+            .map(|_| SiacoinOutput::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        Ok(FileContract {
+            filesize,
+            file_merkle_root,
+            window_start,
+            window_end,
+            payout,
+            valid_proof_outputs,
+            missed_proof_outputs,
+            unlock_hash: Hash256::decode(decoder)?,
+            revision_number: decoder.read_u64()?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct V2FileContract {
@@ -632,6 +1124,28 @@ impl Encodable for V2FileContract {
         self.host_signature.encode(encoder);
     }
 }
+
+impl Decodable for V2FileContract {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(V2FileContract {
+            capacity: decoder.read_u64()?,
+            filesize: decoder.read_u64()?,
+            file_merkle_root: Hash256::decode(decoder)?,
+            proof_height: decoder.read_u64()?,
+            expiration_height: decoder.read_u64()?,
+            renter_output: SiacoinOutput::decode_v2(decoder)?,
+            host_output: SiacoinOutput::decode_v2(decoder)?,
+            missed_host_value: Currency::decode_v2(decoder)?,
+            total_collateral: Currency::decode_v2(decoder)?,
+            renter_public_key: PublicKey::decode(decoder)?,
+            host_public_key: PublicKey::decode(decoder)?,
+            revision_number: decoder.read_u64()?,
+            renter_signature: Signature::decode(decoder)?,
+            host_signature: Signature::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct V2FileContractElement {
@@ -648,6 +1162,16 @@ impl Encodable for V2FileContractElement {
     }
 }
 
+impl Decodable for V2FileContractElement {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(V2FileContractElement {
+            state_element: StateElement::decode(decoder)?,
+            id: FileContractID::decode(decoder)?,
+            v2_file_contract: V2FileContract::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct FileContractRevisionV2 {
     pub parent: V2FileContractElement,
@@ -670,6 +1194,15 @@ impl Encodable for FileContractRevisionV2 {
     }
 }
 
+impl Decodable for FileContractRevisionV2 {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(FileContractRevisionV2 {
+            parent: V2FileContractElement::decode(decoder)?,
+            revision: V2FileContract::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Attestation {
@@ -688,6 +1221,17 @@ impl Encodable for Attestation {
     }
 }
 
+impl Decodable for Attestation {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(Attestation {
+            public_key: PublicKey::decode(decoder)?,
+            key: decoder.read_string()?,
+            value: decoder.read_len_prefixed_bytes()?,
+            signature: Signature::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(try_from = "String", into = "String")]
 pub struct Leaf(#[serde(with = "hex")] pub [u8; 64]);
@@ -706,6 +1250,10 @@ impl From<Leaf> for String {
     fn from(value: Leaf) -> Self { hex::encode(value.0) }
 }
 
+impl Decodable for Leaf {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> { Ok(Leaf(decoder.read_array()?)) }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct StorageProof {
     pub parent_id: FileContractID,
@@ -724,6 +1272,16 @@ impl Encodable for StorageProof {
     }
 }
 
+impl Decodable for StorageProof {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(StorageProof {
+            parent_id: FileContractID::decode(decoder)?,
+            leaf: Leaf::decode(decoder)?,
+            proof: decoder.read_len_prefixed_vec()?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct FileContractRevision {
     pub parent_id: FileContractID,
@@ -753,10 +1311,49 @@ impl Encodable for FileContractRevision {
     }
 }
 
+// FileContract.payout is not part of the FileContractRevision encoding and is decoded as zero
+impl Decodable for FileContractRevision {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let parent_id = FileContractID::decode(decoder)?;
+        let unlock_condition = UnlockCondition::decode(decoder)?;
+        let revision_number = decoder.read_u64()?;
+        let filesize = decoder.read_u64()?;
+        let file_merkle_root = Hash256::decode(decoder)?;
+        let window_start = decoder.read_u64()?;
+        let window_end = decoder.read_u64()?;
+        let len = decoder.read_prefix()?;
+        let valid_proof_outputs = (0..len)
+            .map(|_| SiacoinOutput::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        let len = decoder.read_prefix()?;
+        let missed_proof_outputs = (0..len)
+            .map(|_| SiacoinOutput::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        Ok(FileContractRevision {
+            parent_id,
+            unlock_condition,
+            file_contract: FileContract {
+                filesize,
+                file_merkle_root,
+                window_start,
+                window_end,
+                payout: Currency::ZERO,
+                valid_proof_outputs,
+                missed_proof_outputs,
+                unlock_hash: Hash256::decode(decoder)?,
+                revision_number,
+            },
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct SiafundInputV1 {
+    #[serde(rename = "parentID")]
     pub parent_id: SiafundOutputId,
+    #[serde(rename = "unlockConditions")]
     pub unlock_condition: UnlockCondition,
+    #[serde(rename = "claimAddress")]
     pub claim_address: Address,
 }
 
@@ -777,8 +1374,40 @@ pub struct V2FileContractResolution {
     pub resolution: V2FileContractResolutionWrapper,
 }
 
+// https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/types/encoding.go#L622
 impl Encodable for V2FileContractResolution {
-    fn encode(&self, _encoder: &mut Encoder) { todo!() }
+    fn encode(&self, encoder: &mut Encoder) {
+        self.parent.encode(encoder);
+        encoder.write_u8(self.resolution.type_prefix());
+        self.resolution.encode(encoder);
+    }
+}
+
+impl Decodable for V2FileContractResolution {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let parent = V2FileContractElement::decode(decoder)?;
+        let (resolution_type, resolution) = match decoder.read_u8()? {
+            0 => (
+                ResolutionType::Renewal,
+                V2FileContractResolutionWrapper::Renewal(Box::new(V2FileContractRenewal::decode(decoder)?)),
+            ),
+            1 => (
+                ResolutionType::StorageProof,
+                V2FileContractResolutionWrapper::StorageProof(V2StorageProof::decode(decoder)?),
+            ),
+            2 => (
+                ResolutionType::Finalization,
+                V2FileContractResolutionWrapper::Finalization(Box::new(V2FileContractFinalization::decode(decoder)?)),
+            ),
+            3 => (ResolutionType::Expiration, V2FileContractResolutionWrapper::Expiration),
+            prefix => return Err(DecoderError::ResolutionType(prefix)),
+        };
+        Ok(V2FileContractResolution {
+            parent,
+            resolution_type,
+            resolution,
+        })
+    }
 }
 
 impl<'de> Deserialize<'de> for V2FileContractResolution {
@@ -821,17 +1450,15 @@ impl<'de> Deserialize<'de> for V2FileContractResolution {
     }
 }
 
+// Encodes only the resolution itself. The type prefix is written by V2FileContractResolution and is
+// omitted entirely from the V2Transaction semantics encoding.
 impl Encodable for V2FileContractResolutionWrapper {
-    fn encode(&self, _encoder: &mut Encoder) {
-        todo!();
-    }
-}
-
-impl V2FileContractResolution {
-    fn with_nil_sigs(&self) -> V2FileContractResolution {
-        V2FileContractResolution {
-            resolution: self.resolution.with_nil_sigs(),
-            ..self.clone()
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            V2FileContractResolutionWrapper::Finalization(f) => f.encode(encoder),
+            V2FileContractResolutionWrapper::Renewal(r) => r.encode(encoder),
+            V2FileContractResolutionWrapper::StorageProof(s) => s.encode(encoder),
+            V2FileContractResolutionWrapper::Expiration => (),
         }
     }
 }
@@ -853,6 +1480,17 @@ where
 }
 
 impl V2FileContractResolutionWrapper {
+    /// prefix identifying the resolution type within the V2FileContractResolution encoding.
+    /// This follows the order of Go's type switch, not the declaration order of this enum.
+    fn type_prefix(&self) -> u8 {
+        match self {
+            V2FileContractResolutionWrapper::Renewal(_) => 0,
+            V2FileContractResolutionWrapper::StorageProof(_) => 1,
+            V2FileContractResolutionWrapper::Finalization(_) => 2,
+            V2FileContractResolutionWrapper::Expiration => 3,
+        }
+    }
+
     fn with_nil_sigs(&self) -> V2FileContractResolutionWrapper {
         match self {
             V2FileContractResolutionWrapper::Finalization(f) => {
@@ -881,15 +1519,21 @@ impl Encodable for V2FileContractFinalization {
     fn encode(&self, encoder: &mut Encoder) { self.0.encode(encoder); }
 }
 
+impl Decodable for V2FileContractFinalization {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(V2FileContractFinalization(V2FileContract::decode(decoder)?))
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct V2FileContractRenewal {
-    final_revision: V2FileContract,
-    new_contract: V2FileContract,
-    renter_rollover: Currency,
-    host_rollover: Currency,
-    renter_signature: Signature,
-    host_signature: Signature,
+    pub final_revision: V2FileContract,
+    pub new_contract: V2FileContract,
+    pub renter_rollover: Currency,
+    pub host_rollover: Currency,
+    pub renter_signature: Signature,
+    pub host_signature: Signature,
 }
 
 impl V2FileContractRenewal {
@@ -915,12 +1559,26 @@ impl Encodable for V2FileContractRenewal {
         self.host_signature.encode(encoder);
     }
 }
+
+impl Decodable for V2FileContractRenewal {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(V2FileContractRenewal {
+            final_revision: V2FileContract::decode(decoder)?,
+            new_contract: V2FileContract::decode(decoder)?,
+            renter_rollover: Currency::decode_v2(decoder)?,
+            host_rollover: Currency::decode_v2(decoder)?,
+            renter_signature: Signature::decode(decoder)?,
+            host_signature: Signature::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct V2StorageProof {
-    proof_index: ChainIndexElement,
-    leaf: Leaf,
-    proof: Vec<Hash256>,
+    pub proof_index: ChainIndexElement,
+    pub leaf: Leaf,
+    pub proof: Vec<Hash256>,
 }
 
 impl V2StorageProof {
@@ -950,6 +1608,16 @@ impl Encodable for V2StorageProof {
     }
 }
 
+impl Decodable for V2StorageProof {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(V2StorageProof {
+            proof_index: ChainIndexElement::decode(decoder)?,
+            leaf: Leaf::decode(decoder)?,
+            proof: decoder.read_len_prefixed_vec()?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ChainIndexElement {
@@ -966,6 +1634,15 @@ impl Encodable for ChainIndexElement {
     }
 }
 
+impl Decodable for ChainIndexElement {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(ChainIndexElement {
+            state_element: StateElement::decode(decoder)?,
+            chain_index: ChainIndex::decode(decoder)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct FileContractElementV1 {
     #[serde(flatten)]
@@ -986,16 +1663,50 @@ pub struct FileContractV1 {
     pub revision_number: u64,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
-#[serde(transparent)]
+/// Each entry is represented as a base64 string in JSON
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct V1ArbitraryData {
     pub data: Vec<Vec<u8>>,
 }
 
+impl Serialize for V1ArbitraryData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let encoded: Vec<String> = self.data.iter().map(|d| base64.encode(d)).collect();
+        encoded.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for V1ArbitraryData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| base64.decode(s))
+            .collect::<Result<_, _>>()
+            .map_err(serde::de::Error::custom)?;
+        Ok(V1ArbitraryData { data })
+    }
+}
+
 impl Encodable for V1ArbitraryData {
     fn encode(&self, encoder: &mut Encoder) {
         encoder.write_u64(self.data.len() as u64);
-        self.data.iter().for_each(|b| encoder.write_slice(b));
+        self.data.iter().for_each(|b| encoder.write_len_prefixed_bytes(b));
+    }
+}
+
+impl Decodable for V1ArbitraryData {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let len = decoder.read_prefix()?;
+        let data = (0..len)
+            .map(|_| decoder.read_len_prefixed_bytes())
+            .collect::<Result<_, _>>()?;
+        Ok(V1ArbitraryData { data })
     }
 }
 /*
@@ -1022,35 +1733,432 @@ pub struct V1Transaction {
     pub signatures: Vec<TransactionSignature>,
 }
 
-impl V1Transaction {
-    pub fn txid(&self) -> Hash256 { Encoder::encode_and_hash(&V1TransactionSansSigs(self.clone())) }
+// https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/types/encoding.go#L515
+impl Encodable for V1Transaction {
+    fn encode(&self, encoder: &mut Encoder) {
+        V1TransactionSansSigs(self.clone()).encode(encoder);
+        encoder.write_len_prefixed_vec(&self.signatures);
+    }
 }
 
-impl Encodable for SiafundInputV1 {
-    fn encode(&self, encoder: &mut Encoder) {
-        self.parent_id.encode(encoder);
-        self.unlock_condition.encode(encoder);
-        self.claim_address.encode(encoder);
+impl Decodable for V1Transaction {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let V1TransactionSansSigs(tx) = V1TransactionSansSigs::decode(decoder)?;
+        Ok(V1Transaction {
+            signatures: decoder.read_len_prefixed_vec()?,
+            ..tx
+        })
     }
 }
-// TODO possible this can just hold a ref to V1Transaction like CurrencyVersion
-#[derive(Clone, Debug, Default, Deref, Deserialize, Serialize)]
-pub struct V1TransactionSansSigs(V1Transaction);
 
-impl Encodable for V1TransactionSansSigs {
-    fn encode(&self, encoder: &mut Encoder) {
-        encoder.write_len_prefixed_vec(&self.siacoin_inputs);
+/// The replay protection prefix included in v1 sig hashes. This is equivalent to Sia Go's `State.replayPrefix()`
+/// and depends on the height of the block the transaction will be included in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayPrefix {
+    /// Before the ASIC hardfork no prefix is written
+    PreAsicHardfork,
+    AsicHardfork,
+    FoundationHardfork,
+    V2Hardfork,
+}
+
+impl ReplayPrefix {
+    pub fn from_height(
+        height: u64,
+        asic_hardfork_height: u64,
+        foundation_hardfork_height: u64,
+        v2_allow_height: u64,
+    ) -> Self {
+        if height >= v2_allow_height {
+            ReplayPrefix::V2Hardfork
+        } else if height >= foundation_hardfork_height {
+            ReplayPrefix::FoundationHardfork
+        } else if height >= asic_hardfork_height {
+            ReplayPrefix::AsicHardfork
+        } else {
+            ReplayPrefix::PreAsicHardfork
+        }
+    }
 
-        encoder.write_u64(self.siacoin_outputs.len() as u64);
-        for so in &self.siacoin_outputs {
-            SiacoinOutputVersion::V1(so).encode(encoder);
+    /// The byte written before each input in a sig hash, if any
+    pub fn prefix_byte(self) -> Option<u8> {
+        match self {
+            ReplayPrefix::PreAsicHardfork => None,
+            ReplayPrefix::AsicHardfork => Some(0),
+            ReplayPrefix::FoundationHardfork => Some(1),
+            ReplayPrefix::V2Hardfork => Some(2),
         }
-        encoder.write_len_prefixed_vec(&self.file_contracts);
-        encoder.write_len_prefixed_vec(&self.file_contract_revisions);
-        encoder.write_len_prefixed_vec(&self.storage_proofs);
-        encoder.write_len_prefixed_vec(&self.siafund_inputs);
+    }
 
-        encoder.write_u64(self.siafund_outputs.len() as u64);
+    fn encode(self, encoder: &mut Encoder) {
+        if let Some(prefix) = self.prefix_byte() {
+            encoder.write_u8(prefix);
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum V1SignatureError {
+    #[error("V1Transaction: covered {field} index {index} is out of bounds for length {len}")]
+    CoveredFieldIndex {
+        field: &'static str,
+        index: u64,
+        len: usize,
+    },
+    #[error("V1Transaction::verify_signature: signature index {index} is out of bounds for length {len}")]
+    SignatureIndex { index: usize, len: usize },
+    #[error("V1Transaction: no input or revision with parent id {0}")]
+    ParentNotFound(Hash256),
+    #[error("V1Transaction::sign_input: keypair is not part of the UnlockCondition of {0}")]
+    KeyNotFound(Hash256),
+    #[error("V1Transaction::verify_signature: public key index {index} is out of bounds for length {len}")]
+    PublicKeyIndex { index: u64, len: usize },
+    #[error("V1Transaction::verify_signature: public key at index {0} is not an ed25519 key")]
+    NonStandardKey(u64),
+    #[error("V1Transaction::verify_signature: failed to parse signature {0}")]
+    ParseSignature(#[from] SignatureError),
+    #[error("V1Transaction::verify_signature: invalid signature {0}")]
+    InvalidSignature(#[from] PublicKeyError),
+}
+
+/// the index of the keypair's public key within the UnlockCondition
+fn unlock_key_index(unlock_condition: &UnlockCondition, keypair: &Keypair) -> Option<u64> {
+    let public_key = keypair.public();
+    unlock_condition
+        .unlock_keys
+        .iter()
+        .position(|key| matches!(key, UnlockKey::Ed25519(pk) if pk == &public_key))
+        .map(|index| index as u64)
+}
+
+/// resolve covered field indices to the items they refer to
+fn covered<'a, T>(items: &'a [T], indices: &[u64], field: &'static str) -> Result<Vec<&'a T>, V1SignatureError> {
+    indices
+        .iter()
+        .map(|&index| {
+            items.get(index as usize).ok_or(V1SignatureError::CoveredFieldIndex {
+                field,
+                index,
+                len: items.len(),
+            })
+        })
+        .collect()
+}
+
+impl V1Transaction {
+    pub fn txid(&self) -> Hash256 { Encoder::encode_and_hash(&V1TransactionSansSigs(self.clone())) }
+
+    fn arbitrary_data(&self) -> &[Vec<u8>] {
+        self.arbitrary_data
+            .as_ref()
+            .map(|ad| ad.data.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The hash signed by a TransactionSignature with CoveredFields.whole_transaction set.
+    /// `covered_sigs` are the indices of other signatures included in the hash.
+    // https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/consensus/state.go#L441
+    pub fn whole_sig_hash(
+        &self,
+        replay_prefix: ReplayPrefix,
+        parent_id: &Hash256,
+        public_key_index: u64,
+        timelock: u64,
+        covered_sigs: &[u64],
+    ) -> Result<Hash256, V1SignatureError> {
+        let signatures = covered(&self.signatures, covered_sigs, "signatures")?;
+        Ok(self.whole_sig_hash_covering(replay_prefix, parent_id, public_key_index, timelock, &signatures))
+    }
+
+    fn whole_sig_hash_covering(
+        &self,
+        replay_prefix: ReplayPrefix,
+        parent_id: &Hash256,
+        public_key_index: u64,
+        timelock: u64,
+        signatures: &[&TransactionSignature],
+    ) -> Hash256 {
+        let mut encoder = Encoder::default();
+
+        encoder.write_u64(self.siacoin_inputs.len() as u64);
+        for si in &self.siacoin_inputs {
+            replay_prefix.encode(&mut encoder);
+            si.encode(&mut encoder);
+        }
+        encoder.write_u64(self.siacoin_outputs.len() as u64);
+        for so in &self.siacoin_outputs {
+            SiacoinOutputVersion::V1(so).encode(&mut encoder);
+        }
+        encoder.write_len_prefixed_vec(&self.file_contracts);
+        encoder.write_len_prefixed_vec(&self.file_contract_revisions);
+        encoder.write_len_prefixed_vec(&self.storage_proofs);
+        encoder.write_u64(self.siafund_inputs.len() as u64);
+        for si in &self.siafund_inputs {
+            replay_prefix.encode(&mut encoder);
+            si.encode(&mut encoder);
+        }
+        encoder.write_u64(self.siafund_outputs.len() as u64);
+        for so in &self.siafund_outputs {
+            SiafundOutputVersion::V1(so).encode(&mut encoder);
+        }
+        encoder.write_u64(self.miner_fees.len() as u64);
+        for fee in &self.miner_fees {
+            CurrencyVersion::V1(fee).encode(&mut encoder);
+        }
+        encoder.write_u64(self.arbitrary_data().len() as u64);
+        for data in self.arbitrary_data() {
+            encoder.write_len_prefixed_bytes(data);
+        }
+
+        parent_id.encode(&mut encoder);
+        encoder.write_u64(public_key_index);
+        encoder.write_u64(timelock);
+        for sig in signatures {
+            sig.encode(&mut encoder);
+        }
+        encoder.hash()
+    }
+
+    /// The hash signed by a TransactionSignature that only covers the fields specified by `covered_fields`
+    // https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/consensus/state.go#L497
+    pub fn partial_sig_hash(
+        &self,
+        replay_prefix: ReplayPrefix,
+        covered_fields: &CoveredFields,
+    ) -> Result<Hash256, V1SignatureError> {
+        let mut encoder = Encoder::default();
+
+        for si in covered(&self.siacoin_inputs, &covered_fields.siacoin_inputs, "siacoin_inputs")? {
+            replay_prefix.encode(&mut encoder);
+            si.encode(&mut encoder);
+        }
+        for so in covered(
+            &self.siacoin_outputs,
+            &covered_fields.siacoin_outputs,
+            "siacoin_outputs",
+        )? {
+            SiacoinOutputVersion::V1(so).encode(&mut encoder);
+        }
+        for fc in covered(&self.file_contracts, &covered_fields.file_contracts, "file_contracts")? {
+            fc.encode(&mut encoder);
+        }
+        for fcr in covered(
+            &self.file_contract_revisions,
+            &covered_fields.file_contract_revisions,
+            "file_contract_revisions",
+        )? {
+            fcr.encode(&mut encoder);
+        }
+        for sp in covered(&self.storage_proofs, &covered_fields.storage_proofs, "storage_proofs")? {
+            sp.encode(&mut encoder);
+        }
+        for si in covered(&self.siafund_inputs, &covered_fields.siafund_inputs, "siafund_inputs")? {
+            replay_prefix.encode(&mut encoder);
+            si.encode(&mut encoder);
+        }
+        for so in covered(
+            &self.siafund_outputs,
+            &covered_fields.siafund_outputs,
+            "siafund_outputs",
+        )? {
+            SiafundOutputVersion::V1(so).encode(&mut encoder);
+        }
+        for fee in covered(&self.miner_fees, &covered_fields.miner_fees, "miner_fees")? {
+            CurrencyVersion::V1(fee).encode(&mut encoder);
+        }
+        for data in covered(self.arbitrary_data(), &covered_fields.arbitrary_data, "arbitrary_data")? {
+            encoder.write_len_prefixed_bytes(data);
+        }
+        for sig in covered(&self.signatures, &covered_fields.signatures, "signatures")? {
+            sig.encode(&mut encoder);
+        }
+        Ok(encoder.hash())
+    }
+
+    /// The hash signed by `signature` according to its CoveredFields
+    pub fn sig_hash(
+        &self,
+        replay_prefix: ReplayPrefix,
+        signature: &TransactionSignature,
+    ) -> Result<Hash256, V1SignatureError> {
+        let covered_fields = &signature.covered_fields;
+        match covered_fields.whole_transaction {
+            true => self.whole_sig_hash(
+                replay_prefix,
+                &signature.parent_id,
+                signature.public_key_index,
+                signature.timelock,
+                &covered_fields.signatures,
+            ),
+            false => self.partial_sig_hash(replay_prefix, covered_fields),
+        }
+    }
+
+    /// Find the UnlockCondition of the siacoin input, siafund input or file contract revision spent by `parent_id`
+    pub fn unlock_condition(&self, parent_id: &Hash256) -> Option<&UnlockCondition> {
+        self.siacoin_inputs
+            .iter()
+            .find(|si| &si.parent_id.0 == parent_id)
+            .map(|si| &si.unlock_condition)
+            .or_else(|| {
+                self.siafund_inputs
+                    .iter()
+                    .find(|si| &si.parent_id.0 == parent_id)
+                    .map(|si| &si.unlock_condition)
+            })
+            .or_else(|| {
+                self.file_contract_revisions
+                    .iter()
+                    .find(|fcr| &fcr.parent_id.0 == parent_id)
+                    .map(|fcr| &fcr.unlock_condition)
+            })
+    }
+
+    /// Sign the input or revision spent by `parent_id` covering the whole transaction.
+    /// The keypair's public key must be one of the UnlockCondition's keys.
+    /// Any change to the transaction's fields other than `signatures` invalidates the signature.
+    pub fn sign_input(
+        &mut self,
+        replay_prefix: ReplayPrefix,
+        parent_id: &Hash256,
+        keypair: &Keypair,
+    ) -> Result<&mut Self, V1SignatureError> {
+        let unlock_condition = self
+            .unlock_condition(parent_id)
+            .ok_or_else(|| V1SignatureError::ParentNotFound(parent_id.clone()))?;
+        let public_key_index = unlock_key_index(unlock_condition, keypair)
+            .ok_or_else(|| V1SignatureError::KeyNotFound(parent_id.clone()))?;
+        let timelock = unlock_condition.timelock;
+
+        self.push_whole_signature(replay_prefix, parent_id, public_key_index, timelock, keypair);
+        Ok(self)
+    }
+
+    /// Sign every siacoin and siafund input whose UnlockCondition includes one of the provided keypairs.
+    /// Inputs are signed until their UnlockCondition's `signatures_required` is met; Sia consensus rejects
+    /// any additional signatures. Keypairs that have already signed an input are skipped.
+    pub fn sign_simple(&mut self, replay_prefix: ReplayPrefix, keypairs: Vec<&Keypair>) -> &mut Self {
+        let inputs: Vec<(Hash256, UnlockCondition)> = self
+            .siacoin_inputs
+            .iter()
+            .map(|si| (si.parent_id.0.clone(), si.unlock_condition.clone()))
+            .chain(
+                self.siafund_inputs
+                    .iter()
+                    .map(|si| (si.parent_id.0.clone(), si.unlock_condition.clone())),
+            )
+            .collect();
+        for (parent_id, unlock_condition) in inputs {
+            for keypair in &keypairs {
+                // inputs not spendable by this keypair are skipped
+                let public_key_index = match unlock_key_index(&unlock_condition, keypair) {
+                    Some(index) => index,
+                    None => continue,
+                };
+                let signed: Vec<u64> = self
+                    .signatures
+                    .iter()
+                    .filter(|sig| sig.parent_id == parent_id)
+                    .map(|sig| sig.public_key_index)
+                    .collect();
+                if signed.len() as u64 >= unlock_condition.signatures_required {
+                    break;
+                }
+                if !signed.contains(&public_key_index) {
+                    self.push_whole_signature(
+                        replay_prefix,
+                        &parent_id,
+                        public_key_index,
+                        unlock_condition.timelock,
+                        keypair,
+                    );
+                }
+            }
+        }
+        self
+    }
+
+    fn push_whole_signature(
+        &mut self,
+        replay_prefix: ReplayPrefix,
+        parent_id: &Hash256,
+        public_key_index: u64,
+        timelock: u64,
+        keypair: &Keypair,
+    ) {
+        let sig_hash = self.whole_sig_hash_covering(replay_prefix, parent_id, public_key_index, timelock, &[]);
+        self.signatures.push(TransactionSignature {
+            parent_id: parent_id.clone(),
+            public_key_index,
+            timelock,
+            covered_fields: CoveredFields::whole_transaction(),
+            signature: keypair.sign(&sig_hash.0).into(),
+        });
+    }
+
+    /// Verify the signature at `index` against the public key it references within the UnlockCondition of
+    /// its parent. Timelocks and signature counts are consensus rules and are not checked.
+    pub fn verify_signature(&self, replay_prefix: ReplayPrefix, index: usize) -> Result<(), V1SignatureError> {
+        let signature = self.signatures.get(index).ok_or(V1SignatureError::SignatureIndex {
+            index,
+            len: self.signatures.len(),
+        })?;
+        let unlock_condition = self
+            .unlock_condition(&signature.parent_id)
+            .ok_or_else(|| V1SignatureError::ParentNotFound(signature.parent_id.clone()))?;
+        let public_key = match unlock_condition.unlock_keys.get(signature.public_key_index as usize) {
+            Some(UnlockKey::Ed25519(public_key)) => public_key,
+            Some(UnlockKey::NonStandard { .. }) => {
+                return Err(V1SignatureError::NonStandardKey(signature.public_key_index))
+            },
+            None => {
+                return Err(V1SignatureError::PublicKeyIndex {
+                    index: signature.public_key_index,
+                    len: unlock_condition.unlock_keys.len(),
+                })
+            },
+        };
+        let sig_hash = self.sig_hash(replay_prefix, signature)?;
+        let sig = Signature::try_from(signature.signature.0.as_slice())?;
+        Ok(public_key.verify(&sig_hash.0, &sig)?)
+    }
+}
+
+impl Encodable for SiafundInputV1 {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.parent_id.encode(encoder);
+        self.unlock_condition.encode(encoder);
+        self.claim_address.encode(encoder);
+    }
+}
+
+impl Decodable for SiafundInputV1 {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(SiafundInputV1 {
+            parent_id: SiafundOutputId::decode(decoder)?,
+            unlock_condition: UnlockCondition::decode(decoder)?,
+            claim_address: Address::decode(decoder)?,
+        })
+    }
+}
+// TODO possible this can just hold a ref to V1Transaction like CurrencyVersion
+#[derive(Clone, Debug, Default, Deref, Deserialize, From, Into, PartialEq, Serialize)]
+pub struct V1TransactionSansSigs(pub V1Transaction);
+
+impl Encodable for V1TransactionSansSigs {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_len_prefixed_vec(&self.siacoin_inputs);
+
+        encoder.write_u64(self.siacoin_outputs.len() as u64);
+        for so in &self.siacoin_outputs {
+            SiacoinOutputVersion::V1(so).encode(encoder);
+        }
+        encoder.write_len_prefixed_vec(&self.file_contracts);
+        encoder.write_len_prefixed_vec(&self.file_contract_revisions);
+        encoder.write_len_prefixed_vec(&self.storage_proofs);
+        encoder.write_len_prefixed_vec(&self.siafund_inputs);
+
+        encoder.write_u64(self.siafund_outputs.len() as u64);
         for so in &self.siafund_outputs {
             SiafundOutputVersion::V1(so).encode(encoder);
         }
@@ -1067,6 +2175,42 @@ impl Encodable for V1TransactionSansSigs {
     }
 }
 
+impl Decodable for V1TransactionSansSigs {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let siacoin_inputs = decoder.read_len_prefixed_vec()?;
+        let len = decoder.read_prefix()?;
+        let siacoin_outputs = (0..len)
+            .map(|_| SiacoinOutput::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        let file_contracts = decoder.read_len_prefixed_vec()?;
+        let file_contract_revisions = decoder.read_len_prefixed_vec()?;
+        let storage_proofs = decoder.read_len_prefixed_vec()?;
+        let siafund_inputs = decoder.read_len_prefixed_vec()?;
+        let len = decoder.read_prefix()?;
+        let siafund_outputs = (0..len)
+            .map(|_| SiafundOutput::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        let len = decoder.read_prefix()?;
+        let miner_fees = (0..len)
+            .map(|_| Currency::decode_v1(decoder))
+            .collect::<Result<_, _>>()?;
+        let arbitrary_data = V1ArbitraryData::decode(decoder)?;
+
+        Ok(V1TransactionSansSigs(V1Transaction {
+            siacoin_inputs,
+            siacoin_outputs,
+            file_contracts,
+            file_contract_revisions,
+            storage_proofs,
+            siafund_inputs,
+            siafund_outputs,
+            miner_fees,
+            arbitrary_data: (!arbitrary_data.data.is_empty()).then_some(arbitrary_data),
+            signatures: Vec::new(),
+        }))
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 #[serde(default, rename_all = "camelCase")]
 pub struct V2Transaction {
@@ -1083,7 +2227,7 @@ pub struct V2Transaction {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub file_contract_revisions: Vec<FileContractRevisionV2>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub file_contract_resolutions: Vec<V2FileContractResolution>, // TODO needs Encodable trait
+    pub file_contract_resolutions: Vec<V2FileContractResolution>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub attestations: Vec<Attestation>,
     #[serde(skip_serializing_if = "ArbitraryData::is_empty")]
@@ -1117,6 +2261,52 @@ impl V2Transaction {
         self.encode(&mut encoder);
         encoder.hash()
     }
+
+    /// The consensus weight of the transaction. Input parents are excluded because they are
+    /// part of the consensus state rather than data added by the transaction.
+    // https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/consensus/state.go#L600
+    pub fn weight(&self) -> u64 {
+        self.weight_with(|satisfied_policy| {
+            let mut encoder = Encoder::default();
+            satisfied_policy.encode(&mut encoder);
+            encoder.buffer.len() as u64
+        })
+    }
+
+    /// The weight of the transaction with the size of each input's SatisfiedPolicy given by `policy_size`
+    pub(crate) fn weight_with(&self, policy_size: impl Fn(&SatisfiedPolicy) -> u64) -> u64 {
+        let mut encoder = Encoder::default();
+        for so in &self.siacoin_outputs {
+            SiacoinOutputVersion::V2(so).encode(&mut encoder);
+        }
+        for so in &self.siafund_outputs {
+            SiafundOutputVersion::V2(so).encode(&mut encoder);
+        }
+        for fc in &self.file_contracts {
+            fc.encode(&mut encoder);
+        }
+        for fcr in &self.file_contract_revisions {
+            fcr.revision.encode(&mut encoder);
+        }
+        for fcr in &self.file_contract_resolutions {
+            fcr.resolution.encode(&mut encoder);
+        }
+        for attestation in &self.attestations {
+            attestation.encode(&mut encoder);
+        }
+        self.arbitrary_data.encode(&mut encoder);
+        if let Some(address) = &self.new_foundation_address {
+            address.encode(&mut encoder);
+        }
+        let policies: u64 = self
+            .siacoin_inputs
+            .iter()
+            .map(|si| &si.satisfied_policy)
+            .chain(self.siafund_inputs.iter().map(|si| &si.satisfied_policy))
+            .map(policy_size)
+            .sum();
+        encoder.buffer.len() as u64 + policies
+    }
 }
 
 // this encoding corresponds to the Go implementation's "V2TransactionSemantics" rather than "V2Transaction"
@@ -1156,8 +2346,7 @@ impl Encodable for V2Transaction {
         encoder.write_u64(self.file_contract_resolutions.len() as u64);
         for fcr in &self.file_contract_resolutions {
             fcr.parent.id.encode(encoder);
-            fcr.with_nil_sigs().encode(encoder);
-            // FIXME .encode() leads to unimplemented!()
+            fcr.resolution.with_nil_sigs().encode(encoder);
         }
 
         encoder.write_u64(self.attestations.len() as u64);
@@ -1176,6 +2365,135 @@ impl Encodable for V2Transaction {
     }
 }
 
+/// The full binary encoding of a V2Transaction, equivalent to Go's `V2Transaction.EncodeTo`.
+/// V2Transaction's own Encodable impl is the "V2TransactionSemantics" encoding used for txids and
+/// sig hashes. That encoding omits input parents and satisfied policies so it cannot be decoded.
+// https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/types/encoding.go#L770
+#[derive(Clone, Debug, Default, Deref, From, Into, PartialEq)]
+pub struct V2TransactionFull(pub V2Transaction);
+
+impl V2TransactionFull {
+    /// bitfield indicating which fields are present in the encoding
+    fn fields(&self) -> u64 {
+        [
+            !self.siacoin_inputs.is_empty(),
+            !self.siacoin_outputs.is_empty(),
+            !self.siafund_inputs.is_empty(),
+            !self.siafund_outputs.is_empty(),
+            !self.file_contracts.is_empty(),
+            !self.file_contract_revisions.is_empty(),
+            !self.file_contract_resolutions.is_empty(),
+            !self.attestations.is_empty(),
+            !self.arbitrary_data.is_empty(),
+            self.new_foundation_address.is_some(),
+            self.miner_fee != Currency::ZERO,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0u64, |fields, (i, present)| fields | ((*present as u64) << i))
+    }
+}
+
+impl Encodable for V2TransactionFull {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_u8(V2_TRANSACTION_VERSION);
+        let fields = self.fields();
+        encoder.write_u64(fields);
+
+        if fields & (1 << 0) != 0 {
+            encoder.write_len_prefixed_vec(&self.siacoin_inputs);
+        }
+        if fields & (1 << 1) != 0 {
+            encoder.write_u64(self.siacoin_outputs.len() as u64);
+            for so in &self.siacoin_outputs {
+                SiacoinOutputVersion::V2(so).encode(encoder);
+            }
+        }
+        if fields & (1 << 2) != 0 {
+            encoder.write_len_prefixed_vec(&self.siafund_inputs);
+        }
+        if fields & (1 << 3) != 0 {
+            encoder.write_u64(self.siafund_outputs.len() as u64);
+            for so in &self.siafund_outputs {
+                SiafundOutputVersion::V2(so).encode(encoder);
+            }
+        }
+        if fields & (1 << 4) != 0 {
+            encoder.write_len_prefixed_vec(&self.file_contracts);
+        }
+        if fields & (1 << 5) != 0 {
+            encoder.write_len_prefixed_vec(&self.file_contract_revisions);
+        }
+        if fields & (1 << 6) != 0 {
+            encoder.write_len_prefixed_vec(&self.file_contract_resolutions);
+        }
+        if fields & (1 << 7) != 0 {
+            encoder.write_len_prefixed_vec(&self.attestations);
+        }
+        if fields & (1 << 8) != 0 {
+            self.arbitrary_data.encode(encoder);
+        }
+        if let Some(addr) = &self.new_foundation_address {
+            addr.encode(encoder);
+        }
+        if fields & (1 << 10) != 0 {
+            CurrencyVersion::V2(&self.miner_fee).encode(encoder);
+        }
+    }
+}
+
+impl Decodable for V2TransactionFull {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        let version = decoder.read_u8()?;
+        if version != V2_TRANSACTION_VERSION {
+            return Err(DecoderError::TransactionVersion(version));
+        }
+        let fields = decoder.read_u64()?;
+        let mut tx = V2Transaction::default();
+
+        if fields & (1 << 0) != 0 {
+            tx.siacoin_inputs = decoder.read_len_prefixed_vec()?;
+        }
+        if fields & (1 << 1) != 0 {
+            let len = decoder.read_prefix()?;
+            tx.siacoin_outputs = (0..len)
+                .map(|_| SiacoinOutput::decode_v2(decoder))
+                .collect::<Result<_, _>>()?;
+        }
+        if fields & (1 << 2) != 0 {
+            tx.siafund_inputs = decoder.read_len_prefixed_vec()?;
+        }
+        if fields & (1 << 3) != 0 {
+            let len = decoder.read_prefix()?;
+            tx.siafund_outputs = (0..len)
+                .map(|_| SiafundOutput::decode_v2(decoder))
+                .collect::<Result<_, _>>()?;
+        }
+        if fields & (1 << 4) != 0 {
+            tx.file_contracts = decoder.read_len_prefixed_vec()?;
+        }
+        if fields & (1 << 5) != 0 {
+            tx.file_contract_revisions = decoder.read_len_prefixed_vec()?;
+        }
+        if fields & (1 << 6) != 0 {
+            tx.file_contract_resolutions = decoder.read_len_prefixed_vec()?;
+        }
+        if fields & (1 << 7) != 0 {
+            tx.attestations = decoder.read_len_prefixed_vec()?;
+        }
+        if fields & (1 << 8) != 0 {
+            tx.arbitrary_data = ArbitraryData::decode(decoder)?;
+        }
+        if fields & (1 << 9) != 0 {
+            tx.new_foundation_address = Some(Address::decode(decoder)?);
+        }
+        if fields & (1 << 10) != 0 {
+            tx.miner_fee = Currency::decode_v2(decoder)?;
+        }
+        Ok(V2TransactionFull(tx))
+    }
+}
+
 /// FeePolicy is data optionally included in V2TransactionBuilder to allow easier fee calculation.
 /// Sia fee calculation can be complex in comparison to a typical UTXO protocol because the fee paid
 /// to the miner is not simply the sum of the inputs minus the sum of the outputs. Instead, the
@@ -1183,13 +2501,23 @@ impl Encodable for V2Transaction {
 /// of signature calculation. As a result, you can build a transaction, produce signatures and preimages
 /// for the inputs only to find out that the miner_fee hastings/byte rate is lower than expected.
 /// Therefore a precise hastings/byte calculation requires correctly estimating the size of all
-/// satisfied inputs prior to producing signatures.
-#[derive(Clone, Debug)]
+/// satisfied inputs prior to producing signatures. See `V2TransactionBuilder::apply_fee_policy`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum FeePolicy {
     HastingsPerByte(Currency),
     HastingsFixed(Currency),
 }
 
+impl FeePolicy {
+    /// The miner fee for a transaction of the given weight
+    pub fn fee(&self, weight: u64) -> Currency {
+        match self {
+            FeePolicy::HastingsPerByte(rate) => Currency(rate.saturating_mul(weight as u128)),
+            FeePolicy::HastingsFixed(fee) => *fee,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, From, Into)]
 pub struct ArbitraryData(pub Vec<u8>);
 
@@ -1201,6 +2529,12 @@ impl Encodable for ArbitraryData {
     fn encode(&self, encoder: &mut Encoder) { encoder.write_len_prefixed_bytes(&self.0); }
 }
 
+impl Decodable for ArbitraryData {
+    fn decode(decoder: &mut Decoder) -> Result<Self, DecoderError> {
+        Ok(ArbitraryData(decoder.read_len_prefixed_bytes()?))
+    }
+}
+
 impl Serialize for ArbitraryData {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         serializer.collect_str(&base64.encode(&self.0))
@@ -1234,21 +2568,34 @@ impl<'de> Deserialize<'de> for ArbitraryData {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
 pub struct V2TransactionBuilder {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub siacoin_inputs: Vec<SiacoinInputV2>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub siacoin_outputs: Vec<SiacoinOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub siafund_inputs: Vec<SiafundInputV2>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub siafund_outputs: Vec<SiafundOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub file_contracts: Vec<V2FileContract>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub file_contract_revisions: Vec<FileContractRevisionV2>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub file_contract_resolutions: Vec<V2FileContractResolution>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub attestations: Vec<Attestation>,
+    #[serde(skip_serializing_if = "ArbitraryData::is_empty")]
     pub arbitrary_data: ArbitraryData,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub new_foundation_address: Option<Address>,
     pub miner_fee: Currency,
     // fee_policy is not part Sia consensus and it not encoded into any resulting transaction.
-    // fee_policy has no effect unless a helper like `ApiClientHelpers::fund_tx_single_source` utilizes it.
+    // fee_policy has no effect unless `apply_fee_policy` or a helper like
+    // `ApiClientHelpers::fund_tx_single_source` utilizes it.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fee_policy: Option<FeePolicy>,
 }
 
@@ -1288,8 +2635,7 @@ impl Encodable for V2TransactionBuilder {
         encoder.write_u64(self.file_contract_resolutions.len() as u64);
         for fcr in &self.file_contract_resolutions {
             fcr.parent.id.encode(encoder);
-            fcr.with_nil_sigs().encode(encoder);
-            // FIXME .encode() leads to unimplemented!()
+            fcr.resolution.with_nil_sigs().encode(encoder);
         }
 
         encoder.write_u64(self.attestations.len() as u64);
@@ -1314,6 +2660,23 @@ pub enum V2TransactionBuilderError {
     SatisfySuccessIndexOutOfBounds { len: usize, index: u32 },
     #[error("V2TransactionBuilder::satisfy_atomic_swap_refund: provided index: {index} is out of bounds for inputs of length: {len}")]
     SatisfyRefundIndexOutOfBounds { len: usize, index: u32 },
+    #[error("V2TransactionBuilder::apply_fee_policy: no change output paying to {0}")]
+    ChangeOutputNotFound(Address),
+    #[error("V2TransactionBuilder::apply_fee_policy: change output {change} cannot cover fee increase {fee_increase}")]
+    InsufficientChange { change: Currency, fee_increase: Currency },
+    #[error("V2TransactionBuilder::apply_fee_policy: change output {change} plus miner fee {miner_fee} overflows")]
+    ChangeOverflow { change: Currency, miner_fee: Currency },
+}
+
+/// The indices of inputs `V2TransactionBuilder::sign_policies` was unable to satisfy
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnsatisfiedInputs {
+    pub siacoin_inputs: Vec<usize>,
+    pub siafund_inputs: Vec<usize>,
+}
+
+impl UnsatisfiedInputs {
+    pub fn is_empty(&self) -> bool { self.siacoin_inputs.is_empty() && self.siafund_inputs.is_empty() }
 }
 
 impl V2TransactionBuilder {
@@ -1400,6 +2763,58 @@ impl V2TransactionBuilder {
         encoder.buffer.len() as u64
     }
 
+    /// The weight of the transaction once every input is satisfied. Each SatisfiedPolicy is assumed to
+    /// be the largest its SpendPolicy allows, so the result is never below the weight of the signed
+    /// transaction. See `SpendPolicy::max_satisfied_size`.
+    pub fn satisfied_weight(&self) -> u64 {
+        self.clone()
+            .build()
+            .weight_with(|satisfied_policy| satisfied_policy.policy.max_satisfied_size())
+    }
+
+    /// Set `miner_fee` according to `fee_policy`, taking the difference from the last output paying to
+    /// `change_address`. This must be called after all inputs and outputs are added and before signing.
+    /// If the change left over is dust, the change output is removed and its value is added to the fee.
+    /// Does nothing if no fee policy is set.
+    /// Only the change output is adjusted, inputs are never added. If the change cannot cover the new
+    /// fee, `InsufficientChange` is returned and the caller must add inputs and call this again.
+    pub fn apply_fee_policy(&mut self, change_address: &Address) -> Result<&mut Self, V2TransactionBuilderError> {
+        let fee = match &self.fee_policy {
+            Some(fee_policy) => fee_policy.fee(self.satisfied_weight()),
+            None => return Ok(self),
+        };
+        let change_index = self
+            .siacoin_outputs
+            .iter()
+            .rposition(|output| &output.address == change_address)
+            .ok_or_else(|| V2TransactionBuilderError::ChangeOutputNotFound(change_address.clone()))?;
+
+        // the change output absorbs the difference between the new and current fee
+        let change_value = self.siacoin_outputs[change_index].value;
+        let available = change_value
+            .checked_add(*self.miner_fee)
+            .ok_or(V2TransactionBuilderError::ChangeOverflow {
+                change: change_value,
+                miner_fee: self.miner_fee,
+            })?;
+        if available < *fee {
+            return Err(V2TransactionBuilderError::InsufficientChange {
+                change: change_value,
+                fee_increase: Currency(*fee - *self.miner_fee),
+            });
+        }
+        let change = Currency(available - *fee);
+        if change > Currency::DUST {
+            self.siacoin_outputs[change_index].value = change;
+            self.miner_fee = fee;
+        } else {
+            // removing an output can only lower the weight so the rate stays above the requested rate
+            self.siacoin_outputs.remove(change_index);
+            self.miner_fee = Currency(available);
+        }
+        Ok(self)
+    }
+
     /* Input is a special case becuase we cannot generate signatures until after fully constructing
     the transaction. Only the parent field is utilized while encoding the transaction to
     calculate the signature hash.
@@ -1423,6 +2838,30 @@ impl V2TransactionBuilder {
         self
     }
 
+    /// See `add_siacoin_input`. Any siacoins claimed by the spent siafunds are sent to `claim_address`.
+    pub fn add_siafund_input(
+        &mut self,
+        parent: SiafundElement,
+        claim_address: Address,
+        policy: SpendPolicy,
+    ) -> &mut Self {
+        self.siafund_inputs.push(SiafundInputV2 {
+            parent,
+            claim_address,
+            satisfied_policy: SatisfiedPolicy {
+                policy,
+                signatures: Vec::new(),
+                preimages: Vec::new(),
+            },
+        });
+        self
+    }
+
+    pub fn add_siafund_output(&mut self, output: SiafundOutput) -> &mut Self {
+        self.siafund_outputs.push(output);
+        self
+    }
+
     pub fn input_sig_hash(&self) -> Hash256 {
         let mut encoder = Encoder::default();
         encoder.write_distinguisher("sig/input");
@@ -1432,21 +2871,26 @@ impl V2TransactionBuilder {
     }
 
     // Sign all PublicKey or UnlockConditions policies with the provided keypairs
-    // Incapable of handling threshold policies
+    // Incapable of handling threshold policies, see sign_policies
     pub fn sign_simple(&mut self, keypairs: Vec<&Keypair>) -> &mut Self {
         let sig_hash = self.input_sig_hash();
         for keypair in keypairs {
             let sig = keypair.sign(&sig_hash.0);
-            for si in &mut self.siacoin_inputs {
-                match &si.satisfied_policy.policy {
+            let satisfied_policies = self
+                .siacoin_inputs
+                .iter_mut()
+                .map(|si| &mut si.satisfied_policy)
+                .chain(self.siafund_inputs.iter_mut().map(|si| &mut si.satisfied_policy));
+            for satisfied_policy in satisfied_policies {
+                match &satisfied_policy.policy {
                     SpendPolicy::PublicKey(pk) if pk == &keypair.public() => {
-                        si.satisfied_policy.signatures.push(sig.clone())
+                        satisfied_policy.signatures.push(sig.clone())
                     },
                     SpendPolicy::UnlockConditions(uc) => {
                         for p in &uc.unlock_keys {
                             match p {
                                 UnlockKey::Ed25519(pk) if pk == &keypair.public() => {
-                                    si.satisfied_policy.signatures.push(sig.clone())
+                                    satisfied_policy.signatures.push(sig.clone())
                                 },
                                 _ => (),
                             }
@@ -1459,6 +2903,52 @@ impl V2TransactionBuilder {
         self
     }
 
+    /// Sign every input whose SpendPolicy can be satisfied by the provided keypairs and preimages at
+    /// the given height and median timestamp. These should be those of the block the transaction is
+    /// expected to be included in. Unlike `sign_simple`, this handles arbitrarily nested Threshold
+    /// policies such as M-of-N multisig or timelocked refunds. See `SatisfiedPolicy::sign`.
+    ///
+    /// Inputs that cannot be satisfied are left untouched and reported in the returned
+    /// `UnsatisfiedInputs`.
+    pub fn sign_policies(
+        &mut self,
+        keypairs: Vec<&Keypair>,
+        preimages: Vec<Preimage>,
+        height: u64,
+        median_timestamp: u64,
+    ) -> UnsatisfiedInputs {
+        let sig_hash = self.input_sig_hash();
+        let mut unsatisfied = UnsatisfiedInputs::default();
+        let sign = |satisfied_policy: &mut SatisfiedPolicy| match SatisfiedPolicy::sign(
+            &satisfied_policy.policy,
+            height,
+            median_timestamp,
+            &sig_hash,
+            &keypairs,
+            &preimages,
+        ) {
+            Some(signed) => {
+                *satisfied_policy = signed;
+                true
+            },
+            None => false,
+        };
+        for (index, si) in self.siacoin_inputs.iter_mut().enumerate() {
+            if !sign(&mut si.satisfied_policy) {
+                unsatisfied.siacoin_inputs.push(index);
+            }
+        }
+        for (index, si) in self.siafund_inputs.iter_mut().enumerate() {
+            if !sign(&mut si.satisfied_policy) {
+                unsatisfied.siafund_inputs.push(index);
+            }
+        }
+        unsatisfied
+    }
+
+    /// Satisfy the success path of an HTLC input with a signature from `keypair` and the `secret`.
+    /// The input's policy must be one of the `_success` HTLC policies, eg,
+    /// `SpendPolicy::atomic_swap_success` or `SpendPolicy::atomic_swap_height_success`.
     pub fn satisfy_atomic_swap_success(
         &mut self,
         keypair: &Keypair,
@@ -1482,6 +2972,9 @@ impl V2TransactionBuilder {
         Ok(self)
     }
 
+    /// Satisfy the refund path of an HTLC input with a signature from `keypair`.
+    /// The input's policy must be one of the `_refund` HTLC policies. The refund lock is not checked,
+    /// the transaction is only valid once it has expired, see `RefundLock::is_expired`.
     pub fn satisfy_atomic_swap_refund(
         &mut self,
         keypair: &Keypair,
@@ -1524,3 +3017,160 @@ impl V2TransactionBuilder {
 impl Default for V2TransactionBuilder {
     fn default() -> Self { V2TransactionBuilder::new() }
 }
+
+/// Builder for v1 transactions. v1 transactions are only accepted by consensus prior to the v2 require
+/// height. Each input's UnlockCondition timelock is carried into its signatures, so a transaction spending
+/// a timelocked UnlockCondition is not valid until the chain reaches that height.
+#[derive(Clone, Debug)]
+pub struct V1TransactionBuilder {
+    pub siacoin_inputs: Vec<SiacoinInputV1>,
+    pub siacoin_outputs: Vec<SiacoinOutput>,
+    pub siafund_inputs: Vec<SiafundInputV1>,
+    pub siafund_outputs: Vec<SiafundOutput>,
+    pub miner_fees: Vec<Currency>,
+    pub arbitrary_data: Vec<Vec<u8>>,
+    pub signatures: Vec<TransactionSignature>,
+}
+
+impl V1TransactionBuilder {
+    pub fn new() -> Self {
+        Self {
+            siacoin_inputs: Vec::new(),
+            siacoin_outputs: Vec::new(),
+            siafund_inputs: Vec::new(),
+            siafund_outputs: Vec::new(),
+            miner_fees: Vec::new(),
+            arbitrary_data: Vec::new(),
+            signatures: Vec::new(),
+        }
+    }
+
+    pub fn siacoin_inputs(&mut self, inputs: Vec<SiacoinInputV1>) -> &mut Self {
+        self.siacoin_inputs = inputs;
+        self
+    }
+
+    pub fn siacoin_outputs(&mut self, outputs: Vec<SiacoinOutput>) -> &mut Self {
+        self.siacoin_outputs = outputs;
+        self
+    }
+
+    pub fn siafund_inputs(&mut self, inputs: Vec<SiafundInputV1>) -> &mut Self {
+        self.siafund_inputs = inputs;
+        self
+    }
+
+    pub fn siafund_outputs(&mut self, outputs: Vec<SiafundOutput>) -> &mut Self {
+        self.siafund_outputs = outputs;
+        self
+    }
+
+    pub fn miner_fees(&mut self, fees: Vec<Currency>) -> &mut Self {
+        self.miner_fees = fees;
+        self
+    }
+
+    pub fn arbitrary_data(&mut self, data: Vec<Vec<u8>>) -> &mut Self {
+        self.arbitrary_data = data;
+        self
+    }
+
+    pub fn signatures(&mut self, signatures: Vec<TransactionSignature>) -> &mut Self {
+        self.signatures = signatures;
+        self
+    }
+
+    pub fn add_siacoin_input(&mut self, parent_id: SiacoinOutputId, unlock_condition: UnlockCondition) -> &mut Self {
+        self.siacoin_inputs.push(SiacoinInputV1 {
+            parent_id,
+            unlock_condition,
+        });
+        self
+    }
+
+    pub fn add_siacoin_output(&mut self, output: SiacoinOutput) -> &mut Self {
+        self.siacoin_outputs.push(output);
+        self
+    }
+
+    pub fn add_siafund_input(
+        &mut self,
+        parent_id: SiafundOutputId,
+        unlock_condition: UnlockCondition,
+        claim_address: Address,
+    ) -> &mut Self {
+        self.siafund_inputs.push(SiafundInputV1 {
+            parent_id,
+            unlock_condition,
+            claim_address,
+        });
+        self
+    }
+
+    pub fn add_siafund_output(&mut self, output: SiafundOutput) -> &mut Self {
+        self.siafund_outputs.push(output);
+        self
+    }
+
+    pub fn add_miner_fee(&mut self, fee: Currency) -> &mut Self {
+        self.miner_fees.push(fee);
+        self
+    }
+
+    pub fn add_arbitrary_data(&mut self, data: Vec<u8>) -> &mut Self {
+        self.arbitrary_data.push(data);
+        self
+    }
+
+    /// size of the encoded transaction including any signatures added so far
+    pub fn weight(&self) -> u64 {
+        let mut encoder = Encoder::default();
+        self.build().encode(&mut encoder);
+        encoder.buffer.len() as u64
+    }
+
+    /// Sign the input spent by `parent_id` with a single key of its UnlockCondition.
+    /// Signatures cover the whole transaction so all other fields must be final.
+    pub fn sign_input(
+        &mut self,
+        replay_prefix: ReplayPrefix,
+        parent_id: &Hash256,
+        keypair: &Keypair,
+    ) -> Result<&mut Self, V1SignatureError> {
+        let mut tx = self.build();
+        tx.sign_input(replay_prefix, parent_id, keypair)?;
+        self.signatures = tx.signatures;
+        Ok(self)
+    }
+
+    /// Sign every input whose UnlockCondition includes one of the provided keypairs.
+    /// Multisig UnlockConditions can be signed across multiple calls as keys become available.
+    pub fn sign_simple(&mut self, replay_prefix: ReplayPrefix, keypairs: Vec<&Keypair>) -> &mut Self {
+        let mut tx = self.build();
+        tx.sign_simple(replay_prefix, keypairs);
+        self.signatures = tx.signatures;
+        self
+    }
+
+    pub fn build(&self) -> V1Transaction {
+        let cloned = self.clone();
+        V1Transaction {
+            siacoin_inputs: cloned.siacoin_inputs,
+            siacoin_outputs: cloned.siacoin_outputs,
+            file_contracts: Vec::new(),
+            file_contract_revisions: Vec::new(),
+            storage_proofs: Vec::new(),
+            siafund_inputs: cloned.siafund_inputs,
+            siafund_outputs: cloned.siafund_outputs,
+            miner_fees: cloned.miner_fees,
+            arbitrary_data: (!cloned.arbitrary_data.is_empty()).then_some(V1ArbitraryData {
+                data: cloned.arbitrary_data,
+            }),
+            signatures: cloned.signatures,
+        }
+    }
+}
+
+impl Default for V1TransactionBuilder {
+    fn default() -> Self { V1TransactionBuilder::new() }
+}
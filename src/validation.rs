@@ -0,0 +1,201 @@
+//! Stateless validation of V2 transactions.
+//!
+//! These checks cover the consensus rules that can be evaluated from the transaction itself plus a
+//! small amount of chain context. Rules that require the UTXO set, such as whether a parent output
+//! is unspent, are left to walletd.
+use crate::types::{Currency, SiacoinOutputId, SiafundOutputId, SpendPolicyVerifyError, V2FileContract,
+                   V2FileContractResolutionWrapper, V2Transaction};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// Chain context required to validate a transaction.
+/// `height` and `median_timestamp` should be those of the block the transaction is expected to be
+/// included in, ie, the child of the current tip.
+#[derive(Clone, Debug)]
+pub struct ValidationContext {
+    pub height: u64,
+    pub median_timestamp: u64,
+    pub max_weight: u64,
+}
+
+impl ValidationContext {
+    /// Sia Go's `State.MaxBlockWeight()`; a transaction can be no heavier than a block
+    pub const DEFAULT_MAX_WEIGHT: u64 = 2_000_000;
+
+    pub fn new(height: u64, median_timestamp: u64) -> Self {
+        ValidationContext {
+            height,
+            median_timestamp,
+            max_weight: Self::DEFAULT_MAX_WEIGHT,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    #[error("validate_v2_transaction: transaction weight {weight} exceeds maximum {max}")]
+    Weight { weight: u64, max: u64 },
+    #[error("validate_v2_transaction: siacoin input {0} is spent more than once")]
+    DuplicateSiacoinInput(SiacoinOutputId),
+    #[error("validate_v2_transaction: siafund input {0} is spent more than once")]
+    DuplicateSiafundInput(SiafundOutputId),
+    #[error("validate_v2_transaction: siacoin output {0} has zero value")]
+    ZeroValueSiacoinOutput(usize),
+    #[error("validate_v2_transaction: siafund output {0} has zero value")]
+    ZeroValueSiafundOutput(usize),
+    #[error("validate_v2_transaction: {0} overflows")]
+    CurrencyOverflow(&'static str),
+    #[error("validate_v2_transaction: siacoin inputs {inputs} do not equal outputs {outputs}")]
+    SiacoinImbalance { inputs: Currency, outputs: Currency },
+    #[error("validate_v2_transaction: siafund inputs {inputs} do not equal outputs {outputs}")]
+    SiafundImbalance { inputs: u64, outputs: u64 },
+    #[error("validate_v2_transaction: siacoin input {index} is immature until height {maturity_height}")]
+    ImmatureSiacoinInput { index: usize, maturity_height: u64 },
+    #[error("validate_v2_transaction: siacoin input {0} policy does not match the parent address")]
+    SiacoinInputAddress(usize),
+    #[error("validate_v2_transaction: siafund input {0} policy does not match the parent address")]
+    SiafundInputAddress(usize),
+    #[error("validate_v2_transaction: siacoin input {index} is not satisfied: {source}")]
+    SiacoinInputPolicy {
+        index: usize,
+        source: SpendPolicyVerifyError,
+    },
+    #[error("validate_v2_transaction: siafund input {index} is not satisfied: {source}")]
+    SiafundInputPolicy {
+        index: usize,
+        source: SpendPolicyVerifyError,
+    },
+}
+
+fn checked_sum<I>(values: I, field: &'static str) -> Result<Currency, ValidationError>
+where
+    I: IntoIterator<Item = Currency>,
+{
+    values.into_iter().try_fold(Currency::ZERO, |sum, value| {
+        sum.checked_add(*value)
+            .map(Currency)
+            .ok_or(ValidationError::CurrencyOverflow(field))
+    })
+}
+
+/// Sia Go's `State.V2FileContractTax`, 4% of the contract's total value
+fn file_contract_tax(fc: &V2FileContract) -> Currency {
+    Currency((*fc.renter_output.value).saturating_add(*fc.host_output.value) / 25)
+}
+
+/// the siacoins a new file contract locks, including the tax paid to siafund holders
+fn file_contract_cost(fc: &V2FileContract, field: &'static str) -> Result<Currency, ValidationError> {
+    checked_sum(
+        [fc.renter_output.value, fc.host_output.value, file_contract_tax(fc)],
+        field,
+    )
+}
+
+/// Check the consensus rules of a V2Transaction that do not depend on the UTXO set.
+///
+/// Returns the first rule violated. A transaction that passes may still be rejected by walletd,
+/// for instance if an input's parent has already been spent.
+// https://github.com/SiaFoundation/core/blob/092850cc52d3d981b19c66cd327b5d945b3c18d3/consensus/validation.go#L620
+pub fn validate_v2_transaction(tx: &V2Transaction, ctx: &ValidationContext) -> Result<(), ValidationError> {
+    let weight = tx.weight();
+    if weight > ctx.max_weight {
+        return Err(ValidationError::Weight {
+            weight,
+            max: ctx.max_weight,
+        });
+    }
+
+    let mut spent_siacoins = HashSet::new();
+    for si in &tx.siacoin_inputs {
+        if !spent_siacoins.insert(&si.parent.id) {
+            return Err(ValidationError::DuplicateSiacoinInput(si.parent.id.clone()));
+        }
+    }
+    let mut spent_siafunds = HashSet::new();
+    for si in &tx.siafund_inputs {
+        if !spent_siafunds.insert(&si.parent.id) {
+            return Err(ValidationError::DuplicateSiafundInput(si.parent.id.clone()));
+        }
+    }
+
+    if let Some(index) = tx.siacoin_outputs.iter().position(|so| so.value == Currency::ZERO) {
+        return Err(ValidationError::ZeroValueSiacoinOutput(index));
+    }
+    if let Some(index) = tx.siafund_outputs.iter().position(|so| so.value == 0) {
+        return Err(ValidationError::ZeroValueSiafundOutput(index));
+    }
+
+    // siacoin balance
+    let mut inputs = checked_sum(
+        tx.siacoin_inputs.iter().map(|si| si.parent.siacoin_output.value),
+        "siacoin inputs",
+    )?;
+    let mut outputs = checked_sum(tx.siacoin_outputs.iter().map(|so| so.value), "siacoin outputs")?;
+    for fc in &tx.file_contracts {
+        outputs = checked_sum([outputs, file_contract_cost(fc, "file contracts")?], "siacoin outputs")?;
+    }
+    for fcr in &tx.file_contract_resolutions {
+        if let V2FileContractResolutionWrapper::Renewal(renewal) = &fcr.resolution {
+            outputs = checked_sum(
+                [
+                    outputs,
+                    file_contract_cost(&renewal.new_contract, "file contract renewals")?,
+                ],
+                "siacoin outputs",
+            )?;
+            inputs = checked_sum(
+                [inputs, renewal.renter_rollover, renewal.host_rollover],
+                "siacoin inputs",
+            )?;
+        }
+    }
+    outputs = checked_sum([outputs, tx.miner_fee], "siacoin outputs")?;
+    if inputs != outputs {
+        return Err(ValidationError::SiacoinImbalance { inputs, outputs });
+    }
+
+    // siafund balance
+    let sf_inputs = tx
+        .siafund_inputs
+        .iter()
+        .try_fold(0u64, |sum, si| sum.checked_add(si.parent.siafund_output.value))
+        .ok_or(ValidationError::CurrencyOverflow("siafund inputs"))?;
+    let sf_outputs = tx
+        .siafund_outputs
+        .iter()
+        .try_fold(0u64, |sum, so| sum.checked_add(so.value))
+        .ok_or(ValidationError::CurrencyOverflow("siafund outputs"))?;
+    if sf_inputs != sf_outputs {
+        return Err(ValidationError::SiafundImbalance {
+            inputs: sf_inputs,
+            outputs: sf_outputs,
+        });
+    }
+
+    // spend policies
+    let sig_hash = tx.input_sig_hash();
+    for (index, si) in tx.siacoin_inputs.iter().enumerate() {
+        if si.parent.maturity_height > ctx.height {
+            return Err(ValidationError::ImmatureSiacoinInput {
+                index,
+                maturity_height: si.parent.maturity_height,
+            });
+        }
+        if si.satisfied_policy.policy.address() != si.parent.siacoin_output.address {
+            return Err(ValidationError::SiacoinInputAddress(index));
+        }
+        si.satisfied_policy
+            .verify(ctx.height, ctx.median_timestamp, &sig_hash)
+            .map_err(|source| ValidationError::SiacoinInputPolicy { index, source })?;
+    }
+    for (index, si) in tx.siafund_inputs.iter().enumerate() {
+        if si.satisfied_policy.policy.address() != si.parent.siafund_output.address {
+            return Err(ValidationError::SiafundInputAddress(index));
+        }
+        si.satisfied_policy
+            .verify(ctx.height, ctx.median_timestamp, &sig_hash)
+            .map_err(|source| ValidationError::SiafundInputPolicy { index, source })?;
+    }
+
+    Ok(())
+}